@@ -0,0 +1,173 @@
+//! Upload throughput of the vertex buffer strategies, each upload drawn before the next one
+//! so the GPU is still reading the buffer when it is rewritten. Needs a display to create an
+//! OpenGL context, run with `cargo bench --bench buffer_upload`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use engine::renderer::{
+    IndexBuffer, RenderCommand, ShaderProgram, Vertex, VertexArray, VertexBuffer,
+};
+use glfw::Context;
+
+// Same layout as the vertices of the quad batch
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct QuadVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+    texture_coord: [f32; 2],
+    texture_index: f32,
+    normal_index: f32,
+}
+
+const QUAD_COUNTS: [u32; 3] = [1_000, 10_000, 100_000];
+// Uploads per frame, like the batches of a scene
+const BATCHES: u32 = 8;
+
+const VERTEX_SHADER: &str = "#version 450 core
+layout (location = 0) in vec3 a_position;
+void main() { gl_Position = vec4(a_position, 1.0); }";
+
+const FRAGMENT_SHADER: &str = "#version 450 core
+layout (location = 0) out vec4 o_color;
+void main() { o_color = vec4(1.0); }";
+
+#[derive(Clone, Copy)]
+enum Strategy {
+    SetData,
+    Stream,
+}
+
+struct Target {
+    vertex_array: VertexArray,
+    vertex_buffer: Arc<VertexBuffer>,
+    shader: ShaderProgram,
+}
+
+impl Target {
+    fn new(quad_count: u32) -> Self {
+        let mut vertex_buffer =
+            VertexBuffer::new(quad_count as usize * 4 * std::mem::size_of::<QuadVertex>());
+        vertex_buffer.set_layout(QuadVertex::layout());
+        let vertex_buffer = Arc::new(vertex_buffer);
+
+        let indices = (0..quad_count)
+            .flat_map(|quad| [0, 1, 2, 2, 3, 0].map(|corner| quad * 4 + corner))
+            .collect();
+
+        let mut vertex_array = VertexArray::new();
+        vertex_array.add_vertex_buffer(vertex_buffer.clone());
+        vertex_array.set_index_buffer(Arc::new(IndexBuffer::new(indices)));
+
+        Target {
+            vertex_array,
+            vertex_buffer,
+            shader: ShaderProgram::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)
+                .unwrap_or_else(|error| panic!("{}", error)),
+        }
+    }
+
+    // Uploads and draws a frame of batches, waiting for the GPU to finish it
+    fn draw_frame(&self, vertices: &[QuadVertex], strategy: Strategy) {
+        let index_count = vertices.len() as u32 / 4 * 6;
+        self.shader.bind();
+
+        for _ in 0..BATCHES {
+            match strategy {
+                Strategy::SetData => {
+                    self.vertex_buffer.set_data(vertices, 0);
+                    RenderCommand::draw_indexed(&self.vertex_array, Some(index_count));
+                }
+                Strategy::Stream => {
+                    let base_vertex = self.vertex_buffer.stream(vertices);
+                    RenderCommand::draw_indexed_base_vertex(
+                        &self.vertex_array,
+                        index_count,
+                        base_vertex,
+                    );
+                }
+            }
+        }
+
+        unsafe {
+            gl::Finish();
+        }
+    }
+}
+
+// Tiny quads spread over the screen, so the GPU time goes to reading vertices
+fn quad_vertices(quad_count: u32) -> Vec<QuadVertex> {
+    (0..quad_count * 4)
+        .map(|vertex| {
+            let quad = (vertex / 4) as f32 / quad_count as f32;
+            let corner = (vertex % 4) as f32 * 0.001;
+            QuadVertex {
+                position: [quad * 2.0 - 1.0 + corner, quad * 2.0 - 1.0 - corner, 0.0],
+                color: [1.0; 4],
+                texture_coord: [0.0; 2],
+                texture_index: 0.0,
+                normal_index: 0.0,
+            }
+        })
+        .collect()
+}
+
+fn buffer_upload(criterion: &mut Criterion) {
+    let mut glfw = match glfw::init(glfw::LOG_ERRORS) {
+        Ok(glfw) => glfw,
+        Err(error) => {
+            eprintln!(
+                "Skipping the buffer upload benchmark, no display: {:?}",
+                error
+            );
+            return;
+        }
+    };
+    glfw.window_hint(glfw::WindowHint::Visible(false));
+    glfw.window_hint(glfw::WindowHint::ContextVersion(4, 5));
+    glfw.window_hint(glfw::WindowHint::OpenGlProfile(
+        glfw::OpenGlProfileHint::Core,
+    ));
+    let Some((mut window, _events)) =
+        glfw.create_window(64, 64, "buffer_upload", glfw::WindowMode::Windowed)
+    else {
+        eprintln!("Skipping the buffer upload benchmark, no OpenGL 4.5 context");
+        return;
+    };
+    window.make_current();
+    gl::load_with(|name| window.get_proc_address(name) as *const _);
+
+    let mut group = criterion.benchmark_group("buffer_upload");
+    for quad_count in QUAD_COUNTS {
+        let vertices = quad_vertices(quad_count);
+        let target = Target::new(quad_count);
+        group.throughput(Throughput::Bytes(
+            (std::mem::size_of_val(vertices.as_slice()) * BATCHES as usize) as u64,
+        ));
+
+        for (name, strategy) in [
+            ("set_data", Strategy::SetData),
+            ("stream", Strategy::Stream),
+        ] {
+            group.bench_with_input(
+                BenchmarkId::new(name, quad_count),
+                &vertices,
+                |bench, vertices| {
+                    bench.iter_custom(|iterations| {
+                        let start = Instant::now();
+                        for _ in 0..iterations {
+                            target.draw_frame(vertices, strategy);
+                        }
+                        start.elapsed().max(Duration::from_nanos(1))
+                    });
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, buffer_upload);
+criterion_main!(benches);
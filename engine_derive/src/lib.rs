@@ -0,0 +1,158 @@
+//! Derive macros of the engine, re-exported by the `engine` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+
+/// Implements `engine::renderer::Vertex` for a `#[repr(C)]` struct, one buffer element per
+/// field in declaration order. Elements are named after their field with an "a_" prefix, and
+/// fields take these attributes:
+///
+/// - `#[vertex(name = "a_uv")]` names the element.
+/// - `#[vertex(normalized)]` maps integers to [0, 1], or [-1, 1] if signed.
+/// - `#[vertex(integer)]` passes integers to the shader as integers, not floats.
+///
+/// Fails to compile if a field is not where its layout expects it, e.g. because of padding.
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_vertex(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct FieldOptions {
+    name: Option<String>,
+    normalized: bool,
+    integer: bool,
+}
+
+fn expand_vertex(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+
+    if !is_repr_c(input)? {
+        return Err(Error::new_spanned(
+            ident,
+            "vertices must be #[repr(C)] so fields keep their order",
+        ));
+    }
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "vertices cannot be generic",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "vertices must be structs with named fields",
+                ))
+            }
+        },
+        _ => return Err(Error::new_spanned(ident, "vertices must be structs")),
+    };
+
+    let renderer = quote!(::engine::renderer);
+    let mut elements = Vec::new();
+    let mut checks = Vec::new();
+
+    for field in fields {
+        let field_ident = field.ident.as_ref().expect("fields are named");
+        let ty = &field.ty;
+        let options = field_options(field)?;
+
+        let name = options.name.unwrap_or_else(|| format!("a_{}", field_ident));
+        let normalized = options.normalized;
+        let data_type = quote!(<#ty as #renderer::VertexAttribute>::DATA_TYPE);
+
+        let mut element = quote! {
+            #renderer::BufferElement::new(
+                ::std::string::String::from(#name),
+                #data_type,
+                ::std::option::Option::Some(#normalized),
+            )
+        };
+        if options.integer {
+            element = quote!(#element.as_integer());
+
+            let message = format!("`{}` of `{}` is not an integer", field_ident, ident);
+            checks.push(quote!(::core::assert!(#data_type.is_integer(), #message);));
+        }
+        elements.push(element);
+
+        let message = format!(
+            "`{}` of `{}` is not at the offset its buffer element gets, \
+             vertices cannot have padding",
+            field_ident, ident
+        );
+        checks.push(quote! {
+            ::core::assert!(::core::mem::offset_of!(#ident, #field_ident) == offset, #message);
+            offset += #data_type.get_size() as usize;
+        });
+    }
+
+    let message = format!(
+        "`{}` is bigger than its buffer layout, vertices cannot have padding",
+        ident
+    );
+
+    Ok(quote! {
+        impl #renderer::Vertex for #ident {
+            fn layout() -> #renderer::BufferLayout {
+                #renderer::BufferLayout::new(::std::vec![#(#elements),*])
+            }
+        }
+
+        // Same offsets and stride as `BufferLayout` computes, checked when compiling
+        const _: () = {
+            let mut offset: usize = 0;
+            #(#checks)*
+            ::core::assert!(::core::mem::size_of::<#ident>() == offset, #message);
+        };
+    })
+}
+
+fn is_repr_c(input: &DeriveInput) -> syn::Result<bool> {
+    let mut repr_c = false;
+    for attribute in &input.attrs {
+        if attribute.path().is_ident("repr") {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("C") {
+                    repr_c = true;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(repr_c)
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attribute in &field.attrs {
+        if !attribute.path().is_ident("vertex") {
+            continue;
+        }
+
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("normalized") {
+                options.normalized = true;
+            } else if meta.path.is_ident("integer") {
+                options.integer = true;
+            } else if meta.path.is_ident("name") {
+                let name: LitStr = meta.value()?.parse()?;
+                options.name = Some(name.value());
+            } else {
+                return Err(meta.error("expected `normalized`, `integer` or `name = \"...\"`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
@@ -0,0 +1,17 @@
+#version 420 core
+
+layout (location = 0) out vec4 o_color;
+
+in vec2 v_texture_coord;
+
+layout (binding = 0) uniform sampler2D u_scene;
+layout (binding = 1) uniform sampler2D u_light;
+
+uniform vec3 u_ambient;
+
+void main() {
+	vec4 scene = texture(u_scene, v_texture_coord);
+	vec3 light = u_ambient + texture(u_light, v_texture_coord).rgb;
+
+	o_color = vec4(scene.rgb * light, scene.a);
+}
\ No newline at end of file
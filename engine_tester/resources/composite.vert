@@ -0,0 +1,10 @@
+#version 420 core
+
+layout (location = 0) in vec2 a_position;
+
+out vec2 v_texture_coord;
+
+void main() {
+	v_texture_coord = a_position * 0.5 + 0.5;
+	gl_Position = vec4(a_position, 0.0, 1.0);
+}
\ No newline at end of file
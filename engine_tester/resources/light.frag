@@ -0,0 +1,45 @@
+#version 420 core
+
+layout (location = 0) out vec4 o_color;
+
+in vec2 v_world_position;
+
+layout (binding = 0) uniform sampler2D u_normals;
+
+uniform vec2 u_screen_size;
+
+uniform int u_light_kind; // 0 point, 1 spot, 2 directional
+uniform vec2 u_light_position;
+uniform float u_light_height;
+uniform vec3 u_light_color;
+uniform float u_light_radius;
+uniform float u_light_falloff;
+uniform vec2 u_light_direction;
+uniform float u_spot_cos_outer;
+uniform float u_spot_cos_inner;
+
+void main() {
+	vec3 normal = normalize(texture(u_normals, gl_FragCoord.xy / u_screen_size).xyz * 2.0 - 1.0);
+
+	vec3 to_light;
+	float attenuation = 1.0;
+
+	if (u_light_kind == 2) {
+		to_light = normalize(vec3(-u_light_direction, u_light_height));
+	} else {
+		vec2 offset = u_light_position - v_world_position;
+		float distance = length(offset);
+
+		attenuation = pow(clamp(1.0 - distance / u_light_radius, 0.0, 1.0), u_light_falloff);
+		to_light = normalize(vec3(offset, u_light_height));
+
+		if (u_light_kind == 1 && distance > 0.0) {
+			float cos_angle = dot(-offset / distance, u_light_direction);
+			attenuation *= smoothstep(u_spot_cos_outer, u_spot_cos_inner, cos_angle);
+		}
+	}
+
+	float diffuse = max(dot(normal, to_light), 0.0);
+
+	o_color = vec4(u_light_color * diffuse * attenuation, 1.0);
+}
\ No newline at end of file
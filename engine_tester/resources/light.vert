@@ -0,0 +1,12 @@
+#version 420 core
+
+layout (location = 0) in vec2 a_position;
+
+uniform mat4 u_view_projection;
+
+out vec2 v_world_position;
+
+void main() {
+	v_world_position = a_position;
+	gl_Position = u_view_projection * vec4(a_position, 0.0, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,55 @@
+#version 420 core
+
+layout (location = 0) out vec4 o_color;
+
+in vec3 v_world_position;
+in vec3 v_normal;
+in vec2 v_texture_coord;
+
+struct PointLight {
+	vec3 position;
+	vec3 color;
+	float radius;
+};
+
+layout (binding = 0) uniform sampler2D u_texture;
+
+uniform vec3 u_camera_position;
+uniform vec4 u_color;
+uniform vec3 u_specular;
+uniform float u_shininess;
+
+uniform vec3 u_ambient;
+uniform vec3 u_directional_color;
+uniform vec3 u_directional_direction;
+uniform PointLight u_point_lights[8];
+uniform int u_point_light_count;
+
+vec3 blinn_phong(vec3 albedo, vec3 normal, vec3 to_light, vec3 to_camera, vec3 light_color) {
+	float diffuse = max(dot(normal, to_light), 0.0);
+	vec3 halfway = normalize(to_light + to_camera);
+	float specular = diffuse > 0.0 ? pow(max(dot(normal, halfway), 0.0), u_shininess) : 0.0;
+
+	return (albedo * diffuse + u_specular * specular) * light_color;
+}
+
+void main() {
+	vec4 albedo = u_color * texture(u_texture, v_texture_coord);
+	if (albedo.a == 0.0) discard;
+
+	vec3 normal = normalize(v_normal);
+	vec3 to_camera = normalize(u_camera_position - v_world_position);
+
+	vec3 color = albedo.rgb * u_ambient;
+	color += blinn_phong(albedo.rgb, normal, -u_directional_direction, to_camera, u_directional_color);
+
+	for (int i = 0; i < u_point_light_count; i++) {
+		vec3 to_light = u_point_lights[i].position - v_world_position;
+		float distance = length(to_light);
+		float attenuation = pow(clamp(1.0 - distance / u_point_lights[i].radius, 0.0, 1.0), 2.0);
+
+		color += blinn_phong(albedo.rgb, normal, to_light / distance, to_camera, u_point_lights[i].color) * attenuation;
+	}
+
+	o_color = vec4(color, albedo.a);
+}
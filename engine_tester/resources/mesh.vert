@@ -0,0 +1,22 @@
+#version 420 core
+
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_texture_coord;
+
+uniform mat4 u_view_projection;
+uniform mat4 u_model;
+uniform mat4 u_normal_matrix;
+
+out vec3 v_world_position;
+out vec3 v_normal;
+out vec2 v_texture_coord;
+
+void main() {
+	vec4 world_position = u_model * vec4(a_position, 1.0);
+
+	v_world_position = world_position.xyz;
+	v_normal = mat3(u_normal_matrix) * a_normal;
+	v_texture_coord = a_texture_coord;
+	gl_Position = u_view_projection * world_position;
+}
@@ -1,10 +1,12 @@
 #version 420 core
 
 layout (location = 0) out vec4 o_color;
+layout (location = 1) out vec4 o_normal; // only written to when lighting is enabled
 
 in vec4 v_color;
 in vec2 v_texture_coord;
 in float v_texture_index;
+in float v_normal_index;
 
 layout (binding = 0) uniform sampler2D u_textures[32];
 
@@ -15,6 +17,11 @@ void main() {
 
 	texture_color *= texture(u_textures[int(v_texture_index)], v_texture_coord);
 
-	
+	vec3 normal = vec3(0.0, 0.0, 1.0);
+	if (v_normal_index > 0.0) {
+		normal = normalize(texture(u_textures[int(v_normal_index)], v_texture_coord).rgb * 2.0 - 1.0);
+	}
+
 	o_color = texture_color;
+	o_normal = vec4(normal * 0.5 + 0.5, texture_color.a);
 }
\ No newline at end of file
@@ -4,15 +4,18 @@ layout (location = 0) in vec3 a_position;
 layout (location = 1) in vec4 a_color;
 layout (location = 2) in vec2 a_texture_coord;
 layout (location = 3) in float a_texture_index;
+layout (location = 4) in float a_normal_index;
 
 uniform mat4 u_view_projection;
 
 out vec4 v_color;
 out vec2 v_texture_coord;
 out float v_texture_index;
+out float v_normal_index;
 
 void main() {
 	v_texture_index = a_texture_index;
+	v_normal_index = a_normal_index;
 	v_texture_coord = a_texture_coord;
 	v_color = a_color;
 	gl_Position = u_view_projection * vec4(a_position, 1.0);
@@ -22,13 +22,16 @@ void main() {
 	vec4 texture_color = v_input.color;
 	texture_color *= texture(u_textures[int(v_texture_index)], v_input.texture_coord);
 
-	vec2 d = abs((v_input.texture_coord - 0.5) * v_input.size) - (v_input.size * 0.5 - v_input.corner_radius);
-	float dist = length(max(d, 0.0)) + min(max(d.x, d.y), 0.0) - v_input.corner_radius;
-	
-	float outlineA = smoothstep(-v_input.outline_thickness, -v_input.outline_thickness + 2.0, dist);
-	texture_color = mix(texture_color, v_input.outline_color, outlineA);
-
-	texture_color.a *= smoothstep(1.0, -1.0, dist);
+	// Sprites have a negative radius, their edges must not fade or slices would show seams
+	if (v_input.corner_radius >= 0.0) {
+		vec2 d = abs((v_input.texture_coord - 0.5) * v_input.size) - (v_input.size * 0.5 - v_input.corner_radius);
+		float dist = length(max(d, 0.0)) + min(max(d.x, d.y), 0.0) - v_input.corner_radius;
+		
+		float outlineA = smoothstep(-v_input.outline_thickness, -v_input.outline_thickness + 2.0, dist);
+		texture_color = mix(texture_color, v_input.outline_color, outlineA);
+
+		texture_color.a *= smoothstep(1.0, -1.0, dist);
+	}
 
 	o_color = texture_color;
 }
\ No newline at end of file
@@ -1,37 +1,242 @@
-use engine::core::{logger, Game, GameEngine, KeyCode};
-use engine::renderer::{Camera, RenderCommand};
+use engine::asset::{AssetEvent, Handle};
+use engine::core::{logger, FrameRecorder, Game, GameEngine, KeyCode};
+use engine::model::Model;
+use engine::particles::{EmitterConfig, ParticleEmitter};
+use engine::renderer::{
+    BlendMode, Camera, CameraController, DirectionalLight, FillRule, Gradient, Light, LineCap,
+    Material, Mesh, NineSlice, Occluder, Path, PerspectiveCamera, PointLight, RenderCommand,
+    Renderer3D, SliceFill, StrokeStyle, Texture2D, Viewport,
+};
+use engine::tilemap::Tilemap;
 use engine::ui::*;
 
 use engine::math::*;
+use std::rc::Rc;
 
 struct MyGame {
     camera: Camera,
+    camera_controller: CameraController,
+    minimap_camera: Camera,
+    renderer_3d: Option<Renderer3D>,
+    cube: Option<Mesh>,
+    models: Vec<Handle<Model>>,
+    cube_camera: PerspectiveCamera,
+    cube_rotation: f32,
     player_position: Vector2<f32>,
     player_color: Vector4<f32>,
+    checker: Option<Handle<Texture2D>>,
+    checker_normal: Option<Handle<Texture2D>>,
+    level: Option<Handle<Tilemap>>,
+    fire_config: Option<Handle<EmitterConfig>>,
+    fire: Option<ParticleEmitter>,
+    lighting_key_down: bool,
+    culling_key_down: bool,
+    recording_key_down: bool,
+    stats_key_down: bool,
+    show_stats: bool,
     ui: Option<Ui>,
 }
 
 const PLAYER_SPEED: f32 = 2.0;
+const PILLAR_POSITION: Vector2<f32> = Vector2::new(3.0, 1.0);
+const ZOOM_SPEED: f32 = 1.5;
+
+impl MyGame {
+    fn draw_world(&mut self, engine: &mut GameEngine) {
+        if let Some(level) = self
+            .level
+            .as_ref()
+            .and_then(|level| engine.assets.get_mut(level))
+        {
+            level.position = Vector2::new(-12.0, 8.0);
+            engine.renderer.draw_tilemap(level);
+        }
+
+        if let (Some(checker), Some(normal)) = (&self.checker, &self.checker_normal) {
+            let white = Vector4::new(1.0, 1.0, 1.0, 1.0);
+            let checker = engine.assets.get_or_placeholder(checker);
+
+            match (checker, engine.assets.get(normal)) {
+                (Some(checker), Some(normal)) => engine.renderer.draw_quad_with_normal_map(
+                    Vector2::new(-3.0, 0.0),
+                    Vector2::new(2.0, 2.0),
+                    white,
+                    checker,
+                    normal,
+                ),
+                _ => engine.renderer.draw_quad(
+                    Vector2::new(-3.0, 0.0),
+                    Vector2::new(2.0, 2.0),
+                    white,
+                    checker,
+                ),
+            }
+        }
+
+        engine.renderer.set_blend_mode(BlendMode::Additive);
+        engine.renderer.draw_gradient_quad(
+            PILLAR_POSITION,
+            Vector2::new(3.0, 3.0),
+            0.0,
+            &Gradient::radial(Vector4::new(1.0, 0.8, 0.4, 0.5), Vector4::zero()),
+            None,
+        );
+        engine.renderer.set_blend_mode(BlendMode::Alpha);
+        engine.renderer.draw_quad(
+            PILLAR_POSITION,
+            Vector2::new(1.0, 1.0),
+            Vector4::new(0.5, 0.5, 0.55, 1.0),
+            None,
+        );
+        // Dashed ring around the pillar
+        let mut ring = Path::new();
+        ring.circle(PILLAR_POSITION, 1.2);
+        engine.renderer.stroke_path(
+            &ring,
+            &StrokeStyle {
+                cap: LineCap::Round,
+                dashes: vec![0.3, 0.2],
+                ..StrokeStyle::new(0.08)
+            },
+            Vector4::new(1.0, 0.8, 0.4, 1.0),
+        );
+        engine
+            .renderer
+            .submit_occluder(Occluder::rectangle(PILLAR_POSITION, Vector2::new(1.0, 1.0)));
+
+        engine.renderer.submit_light(Light::point(
+            self.player_position,
+            Vector3::new(1.0, 0.7, 0.4),
+            6.0,
+        ));
+        engine.renderer.submit_light(Light::spot(
+            Vector2::new(-6.0, 4.0),
+            Vector2::new(1.0, -0.6),
+            0.4,
+            Vector3::new(0.4, 0.6, 1.0),
+            12.0,
+        ));
+
+        if let Some(fire) = &self.fire {
+            engine.renderer.draw_particles(fire, None);
+        }
+
+        engine.renderer.draw_quad(
+            self.player_position,
+            Vector2::new(1.0, 1.0),
+            self.player_color,
+            None,
+        );
+
+        // Health bar going from red to green
+        engine.renderer.draw_gradient_quad(
+            self.player_position + Vector2::new(0.0, 0.8),
+            Vector2::new(1.0, 0.15),
+            0.0,
+            &Gradient::horizontal(
+                Vector4::new(0.9, 0.1, 0.1, 1.0),
+                Vector4::new(0.2, 0.9, 0.2, 1.0),
+            ),
+            None,
+        );
+    }
+}
+
+/// Five-pointed star in [0, 1] coordinates, pointing up.
+fn star_path() -> Path {
+    let mut star = Path::new();
+    for point in 0..10 {
+        let angle = std::f32::consts::PI * point as f32 / 5.0 - std::f32::consts::FRAC_PI_2;
+        let radius = if point % 2 == 0 { 0.5 } else { 0.2 };
+        star.line_to(Vector2::new(0.5, 0.5) + Vector2::new(angle.cos(), angle.sin()) * radius);
+    }
+    star.close();
+    star
+}
 
 impl Game for MyGame {
     fn init(&mut self, engine: &mut GameEngine) {
         RenderCommand::set_clear_color(0.06, 0.06, 0.06, 1.0);
         engine.get_window().set_vsync(true);
 
+        self.checker = Some(engine.assets.load_async::<Texture2D>("checker.png"));
+        self.checker_normal = Some(engine.assets.load_async::<Texture2D>("checker_normal.png"));
+        self.level = Some(engine.assets.load_async::<Tilemap>("level.tmj"));
+        self.fire_config = Some(engine.assets.load_async::<EmitterConfig>("fire.json"));
+        self.models = vec![
+            engine.assets.load_async::<Model>("models/pyramid.obj"),
+            engine.assets.load_async::<Model>("models/tree.gltf"),
+        ];
+
+        match Renderer3D::new(&engine.assets) {
+            Ok(mut renderer_3d) => {
+                renderer_3d.set_directional_light(Some(DirectionalLight {
+                    direction: Vector3::new(-0.4, -1.0, -0.3),
+                    color: Vector3::new(1.0, 0.95, 0.9),
+                    intensity: 0.8,
+                }));
+                renderer_3d.submit_point_light(PointLight {
+                    position: Vector3::new(1.5, 0.5, 1.5),
+                    color: Vector3::new(1.0, 0.5, 0.2),
+                    intensity: 1.5,
+                    radius: 4.0,
+                });
+                self.renderer_3d = Some(renderer_3d);
+                self.cube = Some(Mesh::cube(1.0));
+            }
+            Err(error) => logger::error!("Cannot create the 3D renderer: {}", error),
+        }
+
         self.ui = Some(Ui::new(engine));
 
         let ui = self.ui.as_mut().unwrap();
 
-        let container = ui.base.push_child(Box::new(UiBox::new(
-            Cordinate::default(),
-            Cordinate::default(),
-            Dimension::new(DimensionType::Relative, 0.5),
-            Dimension::new(DimensionType::Relative, 0.5),
-            Vector4::new(0.0, 0.0, 1.0, 1.0),
-            20.0,
-            3.0,
-            Vector4::new(1.0, 1.0, 1.0, 1.0),
-        )));
+        let container = ui.base.push_child(Box::new(
+            UiBox::new(
+                Cordinate::default(),
+                Cordinate::default(),
+                Dimension::new(DimensionType::Relative, 0.5),
+                Dimension::new(DimensionType::Relative, 0.5),
+                Vector4::new(0.0, 0.0, 1.0, 1.0),
+                20.0,
+                3.0,
+                Vector4::new(1.0, 1.0, 1.0, 1.0),
+            )
+            .with_gradient(Gradient::vertical(
+                Vector4::new(0.0, 0.0, 0.4, 1.0),
+                Vector4::new(0.3, 0.6, 1.0, 1.0),
+            ))
+            .with_clip_children(),
+        ));
+
+        match engine.assets.load_owned::<Texture2D>("panel.png") {
+            Ok(panel) => {
+                ui.base.push_child(Box::new(UiPanel::new(
+                    Cordinate::new(CordinateType::Pixels, CordinateCenter::Max, 20.0),
+                    Cordinate::new(CordinateType::Pixels, CordinateCenter::Min, 20.0),
+                    Dimension::new(DimensionType::Pixels, 220.0),
+                    Dimension::new(DimensionType::Pixels, 140.0),
+                    Rc::new(panel),
+                    NineSlice {
+                        fill: SliceFill::Tile,
+                        scale: 2.0,
+                        ..NineSlice::uniform(8.0)
+                    },
+                )));
+            }
+            Err(error) => logger::error!("{}", error),
+        }
+
+        let mut icon = UiIcon::new(
+            Cordinate::new(CordinateType::Pixels, CordinateCenter::Min, 20.0),
+            Cordinate::new(CordinateType::Pixels, CordinateCenter::Min, 20.0),
+            Dimension::new(DimensionType::Pixels, 48.0),
+            Dimension::new(DimensionType::Pixels, 48.0),
+            star_path(),
+        );
+        icon.fill = Some((FillRule::NonZero, Vector4::new(1.0, 0.85, 0.2, 1.0)));
+        icon.stroke = Some((StrokeStyle::new(2.0), Vector4::new(0.4, 0.25, 0.0, 1.0)));
+        ui.base.push_child(Box::new(icon));
 
         let inner = container.borrow_mut().push_child(Box::new(UiBox::new(
             Cordinate::new(CordinateType::Relative, CordinateCenter::Min, 0.0),
@@ -63,29 +268,181 @@ impl Game for MyGame {
             self.player_position.x += PLAYER_SPEED * engine.timestep();
         }
 
+        if self.fire.is_none() {
+            if let Some(config) = self.fire_config.as_ref().and_then(|c| engine.assets.get(c)) {
+                self.fire = Some(ParticleEmitter::new(config.clone()));
+            }
+        }
+        if let Some(fire) = &mut self.fire {
+            fire.position = self.player_position;
+            fire.update(engine.timestep());
+        }
+
         let (width, height) = engine.get_window().get_size();
-        self.camera.set_viewport_size(width, height);
+        self.camera.set_window_size(width, height);
+        self.minimap_camera.set_window_size(width, height);
+        self.minimap_camera.recalculate_view();
+        self.cube_camera.set_window_size(width, height);
+        self.cube_rotation += engine.timestep();
+
+        let (cursor_x, cursor_y) = engine.get_cursor_position();
+        let cursor = Vector2::new(cursor_x, cursor_y);
+        if engine.get_key(KeyCode::E) {
+            self.camera
+                .zoom_at(cursor, ZOOM_SPEED.powf(engine.timestep()));
+        }
+        if engine.get_key(KeyCode::Q) {
+            self.camera
+                .zoom_at(cursor, ZOOM_SPEED.powf(-engine.timestep()));
+        }
+        if engine.get_key(KeyCode::Q) || engine.get_key(KeyCode::E) {
+            self.camera_controller.teleport(self.camera.position);
+        }
+        if engine.get_key(KeyCode::Space) {
+            self.camera_controller.add_trauma(2.0 * engine.timestep());
+        }
+        self.camera_controller
+            .update(&mut self.camera, self.player_position, engine.timestep());
 
         println!("FPS: {}", 1.0 / engine.timestep());
+        let lighting_key_down = engine.get_key(KeyCode::L);
+        if lighting_key_down && !self.lighting_key_down {
+            if engine.renderer.is_lighting_enabled() {
+                engine.renderer.disable_lighting();
+            } else if let Err(error) = engine.renderer.enable_lighting(&engine.assets) {
+                logger::error!("Cannot enable lighting: {}", error);
+            }
+        }
+        self.lighting_key_down = lighting_key_down;
+
+        let culling_key_down = engine.get_key(KeyCode::C);
+        if culling_key_down && !self.culling_key_down {
+            let culling = !engine.renderer.is_culling_enabled();
+            engine.renderer.set_culling(culling);
+        }
+        self.culling_key_down = culling_key_down;
+
+        // F12 saves a screenshot, R records every other frame at 30 fps
+        let recording_key_down = engine.get_key(KeyCode::R);
+        if recording_key_down && !self.recording_key_down {
+            match engine.stop_recording() {
+                Some(recorder) => logger::info!(
+                    "Saved {} frames to {}",
+                    recorder.get_saved_count(),
+                    recorder.get_directory().display()
+                ),
+                None => engine.start_recording(FrameRecorder::new("recording", 2, 1.0 / 60.0)),
+            }
+        }
+        self.recording_key_down = recording_key_down;
+
+        // F3 shows what the last frame drew
+        let stats_key_down = engine.get_key(KeyCode::F3);
+        if stats_key_down && !self.stats_key_down {
+            self.show_stats = !self.show_stats;
+        }
+        self.stats_key_down = stats_key_down;
+        if self.show_stats {
+            let stats = engine.get_render_stats().to_string();
+            engine.debug.screen_text(
+                Vector2::new(10.0, 10.0),
+                &stats,
+                Vector4::new(1.0, 1.0, 1.0, 1.0),
+            );
+        }
+
+        // F1 hides these
+        let green = Vector4::new(0.2, 1.0, 0.3, 1.0);
+        let yellow = Vector4::new(1.0, 0.9, 0.2, 1.0);
+        engine.debug.grid(
+            &AABB::from_min_max(-12.0, -8.0, 12.0, 8.0),
+            4.0,
+            Vector4::new(1.0, 1.0, 1.0, 0.2),
+        );
+        engine.debug.aabb(
+            &AABB::from_position_and_size(PILLAR_POSITION, Vector2::new(1.0, 1.0)),
+            green,
+        );
+        engine.debug.circle(self.player_position, 0.5, green);
+        engine
+            .debug
+            .arrow(self.player_position, PILLAR_POSITION, yellow);
+        engine
+            .debug
+            .cross(self.player_position, 0.2, yellow)
+            .duration(1.0);
+        engine.debug.text(
+            self.player_position + Vector2::new(0.6, 0.6),
+            "Player",
+            yellow,
+        );
     }
 
     fn draw(&mut self, engine: &mut GameEngine) {
         engine.renderer.begin_scene(&self.camera);
+        self.draw_world(engine);
+        engine.renderer.end_scene();
 
-        engine.renderer.draw_quad(
-            self.player_position,
-            Vector2::new(1.0, 1.0),
-            self.player_color,
-            None,
-        );
-
+        // Round minimap, the main scene shows around it
+        engine.renderer.begin_scene(&self.minimap_camera);
+        let bounds = engine.renderer.get_visible_bounds();
+        let center = (bounds.min() + bounds.max()) / 2.0;
+        let size = bounds.max() - bounds.min();
+        let mut circle = Path::new();
+        circle.circle(center, size.x.min(size.y) / 2.0);
+        engine.renderer.push_clip_path(&circle, FillRule::NonZero);
+        engine
+            .renderer
+            .draw_quad(center, size, Vector4::new(0.0, 0.0, 0.0, 1.0), None);
+        self.draw_world(engine);
+        engine.renderer.pop_mask();
         engine.renderer.end_scene();
 
+        if let (Some(renderer_3d), Some(cube)) = (&mut self.renderer_3d, &self.cube) {
+            let checker = self
+                .checker
+                .as_ref()
+                .and_then(|checker| engine.assets.get(checker));
+            let transform = Matrix4::from_angle_y(Rad(self.cube_rotation))
+                * Matrix4::from_angle_x(Rad(self.cube_rotation * 0.5));
+
+            renderer_3d.begin_scene(&self.cube_camera);
+            renderer_3d.draw_mesh(cube, &transform, &Material::default(), checker);
+
+            let placements = [Vector3::new(-1.8, -0.5, 0.0), Vector3::new(1.8, -0.5, 0.0)];
+            for (model, position) in self.models.iter().zip(placements) {
+                if let Some(model) = engine.assets.get(model) {
+                    let transform = Matrix4::from_translation(position)
+                        * Matrix4::from_angle_y(Rad(-self.cube_rotation));
+                    renderer_3d.draw_model(model, &transform);
+                }
+            }
+            renderer_3d.end_scene();
+        }
+
         let ui = self.ui.as_mut().unwrap();
 
         ui.update(engine);
         ui.render();
     }
+    fn on_asset_event(&mut self, engine: &mut GameEngine, event: &AssetEvent) {
+        if let AssetEvent::Reloaded { id, path } = event {
+            logger::info!("{} changed on disk", path);
+
+            if let (Some(handle), Some(fire)) = (&self.fire_config, &mut self.fire) {
+                if handle.id() == *id {
+                    fire.config = engine.assets.get(handle).unwrap().clone();
+                }
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.ui = None;
+        self.cube = None;
+        self.models.clear();
+        self.renderer_3d = None;
+    }
 }
 
 fn main() {
@@ -93,10 +450,35 @@ fn main() {
 
     let mut game = MyGame {
         camera: Camera::new(Vector2::new(0.0, 0.0), 10.0),
+        camera_controller: CameraController::new(Vector2::new(0.0, 0.0)),
+        minimap_camera: Camera::new(Vector2::new(0.0, 0.0), 18.0),
+        renderer_3d: None,
+        cube: None,
+        models: Vec::new(),
+        cube_camera: PerspectiveCamera::new(Vector3::new(0.0, 2.0, 5.0), 0.8),
+        cube_rotation: 0.0,
         player_position: Vector2::new(0.0, 0.0),
         player_color: Vector4::new(1.0, 0.0, 0.0, 1.0),
+        checker: None,
+        checker_normal: None,
+        level: None,
+        fire_config: None,
+        fire: None,
+        lighting_key_down: false,
+        culling_key_down: false,
+        recording_key_down: false,
+        stats_key_down: false,
+        show_stats: false,
         ui: None,
     };
 
+    game.camera.pixels_per_unit = Some(16.0);
+    game.minimap_camera.viewport = Viewport::normalized(0.73, 0.7, 0.25, 0.27);
+    game.cube_camera.viewport = Viewport::normalized(0.02, 0.7, 0.25, 0.27);
+    game.cube_camera.clear_color = Some(Vector4::new(0.1, 0.12, 0.18, 1.0));
+    game.cube_camera.look_at(Vector3::new(0.0, 0.5, 0.0));
+    game.camera_controller.dead_zone = Vector2::new(1.5, 1.0);
+    game.camera_controller.bounds = Some(AABB::from_min_max(-12.0, -8.0, 12.0, 8.0));
+
     game.run(800, 600, "Game");
 }
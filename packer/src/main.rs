@@ -0,0 +1,71 @@
+use std::process::ExitCode;
+
+use engine::asset::{Archive, ArchiveWriter, Compression};
+
+const USAGE: &str = "usage:
+    packer <directory> <output.pak> [--store]   pack every file below <directory>
+    packer --list <archive.pak>                 list the files of an archive
+    packer --verify <archive.pak>               read every file and check its checksum";
+
+fn pack(directory: &str, output: &str, compression: Compression) -> Result<(), String> {
+    let mut writer = ArchiveWriter::new(compression);
+    writer
+        .add_directory(directory)
+        .map_err(|error| format!("Failed to read {}: {}", directory, error))?;
+    writer
+        .write_to_file(output)
+        .map_err(|error| format!("Failed to write {}: {}", output, error))?;
+
+    println!("Packed {} files into {}", writer.file_count(), output);
+    Ok(())
+}
+
+fn list(path: &str) -> Result<(), String> {
+    let archive = Archive::open(path).map_err(|error| format!("{}: {}", path, error))?;
+    for (name, entry) in archive.entries() {
+        println!(
+            "{:>10} {:>10} {:?} {}",
+            entry.size, entry.stored_size, entry.compression, name
+        );
+    }
+    Ok(())
+}
+
+fn verify(path: &str) -> Result<(), String> {
+    let archive = Archive::open(path).map_err(|error| format!("{}: {}", path, error))?;
+
+    let mut failures = 0;
+    for (name, _) in archive.entries() {
+        if let Err(error) = archive.read(name) {
+            eprintln!("{}: {}", name, error);
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        return Err(format!("{} files are corrupt", failures));
+    }
+    println!("{} is intact", path);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["--list", path] => list(path),
+        ["--verify", path] => verify(path),
+        [directory, output] => pack(directory, output, Compression::Deflate),
+        [directory, output, "--store"] => pack(directory, output, Compression::None),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! Packed asset archives.
+//!
+//! Layout, all integers little endian:
+//! - header: magic `RPAK`, version (u16), reserved (u16), entry count (u32)
+//! - index, one record per file: path length (u16), path (UTF-8, `/` separated), offset (u64),
+//!   stored size (u64), size (u64), CRC32 of the uncompressed data (u32), compression (u8)
+//! - file data, at the offsets given by the index
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use thiserror::Error;
+
+const MAGIC: &[u8; 4] = b"RPAK";
+const VERSION: u16 = 1;
+const HEADER_SIZE: u64 = 12;
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("not an asset archive")]
+    InvalidMagic,
+    #[error("unsupported archive version {0}")]
+    UnsupportedVersion(u16),
+    #[error("archive index is corrupt: {0}")]
+    CorruptIndex(String),
+    #[error("unknown compression method {method} for {path}")]
+    UnknownCompression { path: String, method: u8 },
+    #[error("checksum mismatch for {path}")]
+    ChecksumMismatch { path: String },
+    #[error("{0} is not in the archive")]
+    NotFound(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Deflate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArchiveEntry {
+    pub offset: u64,
+    pub stored_size: u64,
+    pub size: u64,
+    pub checksum: u32,
+    pub compression: Compression,
+}
+
+/// Read-only view of an archive file. Only the index is kept in memory, file data is read on
+/// demand.
+pub struct Archive {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: BTreeMap<String, ArchiveEntry>,
+}
+
+impl Archive {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+
+        let mut header = [0; HEADER_SIZE as usize];
+        file.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC {
+            return Err(ArchiveError::InvalidMagic);
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+        let entry_count = u32::from_le_bytes(header[8..12].try_into().unwrap());
+
+        let file_size = file.metadata()?.len();
+        let mut entries = BTreeMap::new();
+        for _ in 0..entry_count {
+            let path_len = u16::from_le_bytes(read_array(&mut file)?) as usize;
+            let mut name = vec![0; path_len];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| ArchiveError::CorruptIndex(String::from("path is not UTF-8")))?;
+
+            let offset = u64::from_le_bytes(read_array(&mut file)?);
+            let stored_size = u64::from_le_bytes(read_array(&mut file)?);
+            let size = u64::from_le_bytes(read_array(&mut file)?);
+            let checksum = u32::from_le_bytes(read_array(&mut file)?);
+            let [method] = read_array::<1>(&mut file)?;
+
+            let compression =
+                Compression::from_byte(method).ok_or_else(|| ArchiveError::UnknownCompression {
+                    path: name.clone(),
+                    method,
+                })?;
+
+            if !matches!(offset.checked_add(stored_size), Some(end) if end <= file_size) {
+                return Err(ArchiveError::CorruptIndex(format!(
+                    "{} points past the end of the file",
+                    name
+                )));
+            }
+
+            entries.insert(
+                name,
+                ArchiveEntry {
+                    offset,
+                    stored_size,
+                    size,
+                    checksum,
+                    compression,
+                },
+            );
+        }
+
+        Ok(Archive {
+            path,
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Every file in the archive, sorted by path.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ArchiveEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry))
+    }
+
+    /// Reads, decompresses and verifies a file.
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, ArchiveError> {
+        let entry = self
+            .entries
+            .get(path)
+            .ok_or_else(|| ArchiveError::NotFound(path.to_string()))?;
+
+        let corrupt = || ArchiveError::ChecksumMismatch {
+            path: path.to_string(),
+        };
+
+        // The index was checked against the file length on open, but the file may have been
+        // truncated since, so grow the buffer as data actually arrives
+        let mut stored = Vec::new();
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(entry.offset))?;
+            (&mut *file)
+                .take(entry.stored_size)
+                .read_to_end(&mut stored)?;
+        }
+        if stored.len() as u64 != entry.stored_size {
+            return Err(corrupt());
+        }
+
+        let data = match entry.compression {
+            Compression::None => stored,
+            Compression::Deflate => {
+                // Reading one byte past the expected size is enough to spot a bad entry without
+                // inflating all of it
+                let mut data = Vec::new();
+                DeflateDecoder::new(stored.as_slice())
+                    .take(entry.size.saturating_add(1))
+                    .read_to_end(&mut data)?;
+                if data.len() as u64 > entry.size {
+                    return Err(corrupt());
+                }
+                data
+            }
+        };
+
+        if data.len() as u64 != entry.size || crc32fast::hash(&data) != entry.checksum {
+            return Err(corrupt());
+        }
+
+        Ok(data)
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Builds archive files. Files are kept in memory until `write` is called.
+pub struct ArchiveWriter {
+    compression: Compression,
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ArchiveWriter {
+    pub fn new(compression: Compression) -> Self {
+        ArchiveWriter {
+            compression,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a file under `path`, replacing any file previously added with the same path.
+    pub fn add_file(&mut self, path: &str, data: Vec<u8>) {
+        let path = path.replace('\\', "/").trim_start_matches('/').to_string();
+        self.files.insert(path, data);
+    }
+
+    /// Adds every file below `directory`, with paths relative to it.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, directory: P) -> Result<(), ArchiveError> {
+        let directory = directory.as_ref();
+        let mut pending = vec![directory.to_path_buf()];
+
+        while let Some(current) = pending.pop() {
+            for dir_entry in std::fs::read_dir(&current)? {
+                let dir_entry = dir_entry?;
+                let path = dir_entry.path();
+                if dir_entry.file_type()?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                let relative = path.strip_prefix(directory).unwrap();
+                let name = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self.add_file(&name, std::fs::read(&path)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), ArchiveError> {
+        let mut blobs = Vec::with_capacity(self.files.len());
+        for (path, data) in &self.files {
+            if path.len() > u16::MAX as usize {
+                return Err(ArchiveError::CorruptIndex(format!(
+                    "path too long: {}",
+                    path
+                )));
+            }
+
+            let (compression, stored) = match self.compression {
+                Compression::None => (Compression::None, None),
+                Compression::Deflate => {
+                    let mut encoder =
+                        DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                    encoder.write_all(data)?;
+                    let compressed = encoder.finish()?;
+
+                    // Files that don't shrink, like PNGs, are stored as is
+                    if compressed.len() < data.len() {
+                        (Compression::Deflate, Some(compressed))
+                    } else {
+                        (Compression::None, None)
+                    }
+                }
+            };
+            blobs.push((path, data, compression, stored));
+        }
+
+        let index_size: u64 = self
+            .files
+            .keys()
+            .map(|path| 2 + path.len() as u64 + 8 + 8 + 8 + 4 + 1)
+            .sum();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&0u16.to_le_bytes())?;
+        writer.write_all(&(blobs.len() as u32).to_le_bytes())?;
+
+        let mut offset = HEADER_SIZE + index_size;
+        for (path, data, compression, stored) in &blobs {
+            let stored_size = stored.as_ref().map_or(data.len(), Vec::len) as u64;
+
+            writer.write_all(&(path.len() as u16).to_le_bytes())?;
+            writer.write_all(path.as_bytes())?;
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&stored_size.to_le_bytes())?;
+            writer.write_all(&(data.len() as u64).to_le_bytes())?;
+            writer.write_all(&crc32fast::hash(data).to_le_bytes())?;
+            writer.write_all(&[compression.to_byte()])?;
+
+            offset += stored_size;
+        }
+
+        for (_, data, _, stored) in &blobs {
+            writer.write_all(stored.as_deref().unwrap_or(data))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        let mut file = std::io::BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_archive(name: &str, writer: &ArchiveWriter) -> PathBuf {
+        let directory = std::env::temp_dir().join("engine_archive_tests");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        writer.write_to_file(&path).unwrap();
+        path
+    }
+
+    fn sample_files() -> Vec<(&'static str, Vec<u8>)> {
+        vec![
+            ("shaders/quad.glsl", b"#type vertex\n".repeat(64)),
+            ("textures/noise.bin", (0..=255).collect()),
+            ("empty.txt", Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn round_trips_files() {
+        for (name, compression) in [
+            ("stored.rpak", Compression::None),
+            ("deflated.rpak", Compression::Deflate),
+        ] {
+            let mut writer = ArchiveWriter::new(compression);
+            for (path, data) in sample_files() {
+                writer.add_file(path, data);
+            }
+            writer.add_file("\\scripts\\main.lua", b"print()".to_vec());
+
+            let archive = Archive::open(write_archive(name, &writer)).unwrap();
+            let paths: Vec<_> = archive.entries().map(|(path, _)| path).collect();
+            assert_eq!(
+                paths,
+                [
+                    "empty.txt",
+                    "scripts/main.lua",
+                    "shaders/quad.glsl",
+                    "textures/noise.bin"
+                ]
+            );
+            for (path, data) in sample_files() {
+                assert_eq!(archive.read(path).unwrap(), data);
+            }
+            assert!(matches!(
+                archive.read("missing.txt"),
+                Err(ArchiveError::NotFound(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn compresses_only_files_that_shrink() {
+        let mut writer = ArchiveWriter::new(Compression::Deflate);
+        for (path, data) in sample_files() {
+            writer.add_file(path, data);
+        }
+
+        let archive = Archive::open(write_archive("mixed.rpak", &writer)).unwrap();
+        let compression: Vec<_> = archive
+            .entries()
+            .map(|(_, entry)| entry.compression)
+            .collect();
+        assert_eq!(
+            compression,
+            [Compression::None, Compression::Deflate, Compression::None]
+        );
+    }
+
+    #[test]
+    fn rejects_entries_past_the_end_of_the_file() {
+        let mut writer = ArchiveWriter::new(Compression::None);
+        writer.add_file("data.bin", vec![7; 100]);
+        let path = write_archive("truncated.rpak", &writer);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            Archive::open(&path),
+            Err(ArchiveError::CorruptIndex(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_entries_that_inflate_past_their_size() {
+        let mut writer = ArchiveWriter::new(Compression::Deflate);
+        writer.add_file("data.bin", vec![0; 4096]);
+        let path = write_archive("inflated.rpak", &writer);
+
+        // Shrink the recorded size, which sits right after the offset and stored size
+        let mut bytes = std::fs::read(&path).unwrap();
+        let size_at = HEADER_SIZE as usize + 2 + "data.bin".len() + 16;
+        bytes[size_at..size_at + 8].copy_from_slice(&16u64.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+
+        let archive = Archive::open(&path).unwrap();
+        assert!(matches!(
+            archive.read("data.bin"),
+            Err(ArchiveError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let directory = std::env::temp_dir().join("engine_archive_tests");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("magic.rpak");
+        std::fs::write(&path, b"ZPAK\x01\x00\x00\x00\x00\x00\x00\x00").unwrap();
+        assert!(matches!(
+            Archive::open(&path),
+            Err(ArchiveError::InvalidMagic)
+        ));
+
+        let path = directory.join("version.rpak");
+        std::fs::write(&path, b"RPAK\x02\x00\x00\x00\x00\x00\x00\x00").unwrap();
+        assert!(matches!(
+            Archive::open(&path),
+            Err(ArchiveError::UnsupportedVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,735 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Weak};
+
+use thiserror::Error;
+
+use crate::asset::{vfs, AssetId, FileWatcher, Handle, HandleInner, LoadState, ThreadPool};
+use crate::renderer::{ImageError, ShaderError};
+
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to load image {path}: {source}")]
+    Image { path: PathBuf, source: ImageError },
+    #[error("failed to load shader {path}: {source}")]
+    Shader { path: PathBuf, source: ShaderError },
+    #[error("failed to parse {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("{path} is not a supported {kind} file")]
+    UnsupportedFormat { path: PathBuf, kind: &'static str },
+}
+
+/// Emitted by the `AssetServer` and passed to `Game::on_asset_event`.
+#[derive(Clone, Debug)]
+pub enum AssetEvent {
+    /// The source of a loaded asset changed on disk and the asset was updated.
+    Reloaded { id: AssetId, path: String },
+    /// The source changed but could not be loaded, the previous version is kept.
+    ReloadFailed {
+        id: AssetId,
+        path: String,
+        error: String,
+    },
+}
+
+/// Gives loaders access to files relative to the asset root, read through the `vfs`.
+#[derive(Clone)]
+pub struct LoadContext {
+    root: PathBuf,
+}
+
+impl LoadContext {
+    /// Context for running loaders outside of an `AssetServer`, e.g. in tools.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        LoadContext { root: root.into() }
+    }
+
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        let path = self.resolve(path);
+        vfs::read(&path).map_err(|source| AssetError::Io { path, source })
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Result<String, AssetError> {
+        let path = self.resolve(path);
+        vfs::read_to_string(&path).map_err(|source| AssetError::Io { path, source })
+    }
+}
+
+/// Resolves `path` found inside `file`, e.g. an image referenced by a map, which is relative
+/// to the directory of `file`. Both are relative to the asset root.
+pub fn relative_to(file: &str, path: &str) -> String {
+    Path::new(file)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Loading is split in two so that file IO and decoding can run on worker threads while
+/// anything touching the GL context happens on the main thread.
+pub trait Asset: Sized + 'static {
+    type Data: Send + 'static;
+
+    /// Reads and decodes the asset. May run on a worker thread.
+    fn decode(path: &str, context: &LoadContext) -> Result<Self::Data, AssetError>;
+
+    /// Builds the asset from decoded data. Always runs on the main thread.
+    fn create(path: &str, data: Self::Data, context: &LoadContext) -> Result<Self, AssetError>;
+
+    fn load(path: &str, context: &LoadContext) -> Result<Self, AssetError> {
+        let data = Self::decode(path, context)?;
+        Self::create(path, data, context)
+    }
+
+    /// Files `decode` reads, relative to the asset root. Hot reload watches these.
+    fn source_files(path: &str) -> Vec<String> {
+        vec![path.to_string()]
+    }
+
+    /// Files found to be read only once the asset is loaded, e.g. the images referenced by a
+    /// map. Hot reload watches these too.
+    fn dependencies(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Updates a loaded asset after its source files changed. Replaces it by default, assets
+    /// whose GL ids are held elsewhere should update in place instead.
+    fn reload(
+        &mut self,
+        path: &str,
+        data: Self::Data,
+        context: &LoadContext,
+    ) -> Result<(), AssetError> {
+        *self = Self::create(path, data, context)?;
+        Ok(())
+    }
+}
+
+struct AssetEntry<T> {
+    path: String,
+    asset: Option<T>, // None while pending or after a failed load
+    state: LoadState,
+    handle: Weak<HandleInner>,
+}
+
+struct AssetStorage<T> {
+    entries: HashMap<AssetId, AssetEntry<T>>,
+    placeholder: Option<T>,
+}
+
+// Lets the server walk every storage without knowing the asset types
+trait AnyStorage {
+    fn unload_unused(&mut self) -> Vec<AssetId>;
+    fn source_files(&self) -> Vec<(AssetId, Vec<String>)>;
+    fn dependencies(&self, id: AssetId) -> Vec<String>;
+    fn reload(&mut self, id: AssetId, context: &LoadContext) -> Option<AssetEvent>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Asset> AnyStorage for AssetStorage<T> {
+    fn unload_unused(&mut self) -> Vec<AssetId> {
+        let unused: Vec<AssetId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.handle.strong_count() == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &unused {
+            self.entries.remove(id);
+        }
+        unused
+    }
+
+    fn source_files(&self) -> Vec<(AssetId, Vec<String>)> {
+        self.entries
+            .iter()
+            .map(|(id, entry)| {
+                let mut files = T::source_files(&entry.path);
+                files.extend(entry.asset.iter().flat_map(T::dependencies));
+                (*id, files)
+            })
+            .collect()
+    }
+
+    fn dependencies(&self, id: AssetId) -> Vec<String> {
+        self.entries
+            .get(&id)
+            .and_then(|entry| entry.asset.as_ref())
+            .map_or_else(Vec::new, T::dependencies)
+    }
+
+    fn reload(&mut self, id: AssetId, context: &LoadContext) -> Option<AssetEvent> {
+        let entry = self.entries.get_mut(&id)?;
+        let path = entry.path.clone();
+
+        let result = match (&mut entry.asset, &entry.state) {
+            (Some(asset), _) => {
+                T::decode(&path, context).and_then(|data| asset.reload(&path, data, context))
+            }
+            // Give assets that failed to load another chance once their file is fixed
+            (None, LoadState::Failed(_)) => {
+                T::load(&path, context).map(|asset| entry.asset = Some(asset))
+            }
+            (None, _) => return None, // still loading, the worker will pick up the new file
+        };
+
+        match result {
+            Ok(()) => {
+                entry.state = LoadState::Ready;
+                if let Some(inner) = entry.handle.upgrade() {
+                    inner.set_state(LoadState::Ready);
+                }
+                log::info!("Reloaded {}", path);
+                Some(AssetEvent::Reloaded { id, path })
+            }
+            Err(error) => {
+                log::error!("Failed to reload {}", error);
+                Some(AssetEvent::ReloadFailed {
+                    id,
+                    path,
+                    error: error.to_string(),
+                })
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+type Completion = Box<dyn FnOnce(&mut AssetServer) + Send>;
+
+pub struct AssetServer {
+    root: PathBuf,
+    next_id: u64,
+    paths: HashMap<(TypeId, String), AssetId>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+
+    thread_pool: Option<ThreadPool>, // spawned on the first asynchronous load
+    completion_sender: Sender<Completion>,
+    completion_receiver: Receiver<Completion>,
+
+    watcher: Option<FileWatcher>, // set while hot reload is enabled
+    watched_files: HashMap<PathBuf, Vec<(TypeId, AssetId)>>,
+    events: Vec<AssetEvent>,
+}
+
+impl AssetServer {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        let (completion_sender, completion_receiver) = channel();
+
+        AssetServer {
+            root: root.into(),
+            next_id: 0,
+            paths: HashMap::new(),
+            storages: HashMap::new(),
+
+            thread_pool: None,
+            completion_sender,
+            completion_receiver,
+
+            watcher: None,
+            watched_files: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Changes where asset paths are resolved from. Already loaded assets are kept.
+    pub fn set_root<P: Into<PathBuf>>(&mut self, root: P) {
+        self.root = root.into();
+    }
+
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    fn context(&self) -> LoadContext {
+        LoadContext {
+            root: self.root.clone(),
+        }
+    }
+
+    // Returns a handle to an asset that is already known, reviving it if every handle was dropped
+    fn existing_handle<T: Asset>(&mut self, path: &str) -> Option<Handle<T>> {
+        let id = *self.paths.get(&(TypeId::of::<T>(), path.to_string()))?;
+        let entry = self.storage_mut::<T>().entries.get_mut(&id)?;
+
+        if let Some(inner) = entry.handle.upgrade() {
+            return Some(Handle::new(inner));
+        }
+
+        let handle = Handle::new(Arc::new(HandleInner::new(id, path, entry.state.clone())));
+        entry.handle = handle.downgrade();
+        Some(handle)
+    }
+
+    fn insert_entry<T: Asset>(
+        &mut self,
+        path: &str,
+        asset: Option<T>,
+        state: LoadState,
+    ) -> Handle<T> {
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+
+        let handle = Handle::new(Arc::new(HandleInner::new(id, path, state.clone())));
+        let mut files = T::source_files(path);
+        files.extend(asset.iter().flat_map(T::dependencies));
+        self.storage_mut::<T>().entries.insert(
+            id,
+            AssetEntry {
+                path: path.to_string(),
+                asset,
+                state,
+                handle: handle.downgrade(),
+            },
+        );
+        self.paths.insert((TypeId::of::<T>(), path.to_string()), id);
+        self.watch_sources(TypeId::of::<T>(), id, files);
+
+        handle
+    }
+
+    /// Watches the files of every asset loaded through the server and reloads assets whose
+    /// files change. `Game::run` enables it in debug builds. Files inside archives are never
+    /// watched.
+    pub fn set_hot_reload(&mut self, enabled: bool) {
+        if enabled == self.watcher.is_some() {
+            return;
+        }
+
+        self.watched_files.clear();
+        if !enabled {
+            self.watcher = None;
+            return;
+        }
+
+        self.watcher = Some(FileWatcher::new());
+        let sources: Vec<_> = self
+            .storages
+            .iter()
+            .flat_map(|(type_id, storage)| {
+                storage
+                    .source_files()
+                    .into_iter()
+                    .map(move |(id, files)| (*type_id, id, files))
+            })
+            .collect();
+        for (type_id, id, files) in sources {
+            self.watch_sources(type_id, id, files);
+        }
+    }
+
+    pub fn is_hot_reload_enabled(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    fn watch_sources(&mut self, type_id: TypeId, id: AssetId, files: Vec<String>) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+
+        for file in files {
+            if let Some(native) = vfs::native_path(self.root.join(&file)) {
+                let native = watcher.watch(&native);
+                let assets = self.watched_files.entry(native).or_default();
+                if !assets.contains(&(type_id, id)) {
+                    assets.push((type_id, id));
+                }
+            }
+        }
+    }
+
+    /// Reloads every asset whose source files changed since the last call, queueing an
+    /// `AssetEvent` for each. `Game::run` calls it once per frame.
+    pub fn check_for_changes(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let changed_files = watcher.changed_files();
+
+        let context = self.context();
+        let mut reloaded = Vec::new();
+        for path in changed_files {
+            let Some(assets) = self.watched_files.get(&path) else {
+                continue;
+            };
+
+            for &(type_id, id) in assets {
+                if let Some(event) = self
+                    .storages
+                    .get_mut(&type_id)
+                    .and_then(|storage| storage.reload(id, &context))
+                {
+                    if let AssetEvent::Reloaded { .. } = event {
+                        reloaded.push((type_id, id));
+                    }
+                    self.events.push(event);
+                }
+            }
+        }
+
+        // A reloaded asset may now read other files, e.g. a map given a new tileset
+        for (type_id, id) in reloaded {
+            self.watch_dependencies(type_id, id);
+        }
+    }
+
+    fn watch_dependencies(&mut self, type_id: TypeId, id: AssetId) {
+        let files = self
+            .storages
+            .get(&type_id)
+            .map_or_else(Vec::new, |storage| storage.dependencies(id));
+        self.watch_sources(type_id, id, files);
+    }
+
+    /// Takes the events queued since the last call.
+    pub fn drain_events(&mut self) -> Vec<AssetEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Loads an asset on the calling thread, or returns a new handle to it if it is already
+    /// loaded or loading. Assets that failed to load are loaded again.
+    pub fn load<T: Asset>(&mut self, path: &str) -> Result<Handle<T>, AssetError> {
+        if let Some(handle) = self.existing_handle::<T>(path) {
+            if matches!(handle.load_state(), LoadState::Failed(_)) {
+                let asset = self.load_owned::<T>(path)?;
+                self.finish_load(handle.id(), Ok(asset));
+            }
+            return Ok(handle);
+        }
+
+        let asset = self.load_owned::<T>(path)?;
+        Ok(self.insert_entry(path, Some(asset), LoadState::Ready))
+    }
+
+    /// Starts loading an asset on a worker thread and returns its handle right away. The GL
+    /// side of the load happens in `process_loads`, until then `get` returns `None`. Assets
+    /// that failed to load are loaded again.
+    pub fn load_async<T: Asset>(&mut self, path: &str) -> Handle<T> {
+        if let Some(handle) = self.existing_handle::<T>(path) {
+            if matches!(handle.load_state(), LoadState::Failed(_)) {
+                // Keeps the entry so handles given out before see the new attempt
+                if let Some(entry) = self.storage_mut::<T>().entries.get_mut(&handle.id()) {
+                    entry.state = LoadState::Pending;
+                }
+                handle.inner.set_state(LoadState::Pending);
+                self.spawn_load::<T>(handle.id(), path);
+            }
+            return handle;
+        }
+
+        let handle = self.insert_entry::<T>(path, None, LoadState::Pending);
+        self.spawn_load::<T>(handle.id(), path);
+        handle
+    }
+
+    fn spawn_load<T: Asset>(&mut self, id: AssetId, path: &str) {
+        let context = self.context();
+        let sender = self.completion_sender.clone();
+        let path = path.to_string();
+
+        let thread_pool = self.thread_pool.get_or_insert_with(|| {
+            let threads = std::thread::available_parallelism()
+                .map(|count| count.get().saturating_sub(1))
+                .unwrap_or(1);
+            ThreadPool::new(threads.min(4))
+        });
+
+        thread_pool.execute(move || {
+            let data = T::decode(&path, &context);
+            let completion: Completion = Box::new(move |server: &mut AssetServer| {
+                let result = data.and_then(|data| T::create(&path, data, &context));
+                server.finish_load::<T>(id, result);
+            });
+            // The server may already be gone, in which case the result is simply dropped
+            let _ = sender.send(completion);
+        });
+    }
+
+    fn finish_load<T: Asset>(&mut self, id: AssetId, result: Result<T, AssetError>) {
+        let state = match &result {
+            Ok(_) => LoadState::Ready,
+            Err(error) => {
+                log::error!("{}", error);
+                LoadState::Failed(error.to_string())
+            }
+        };
+
+        // The entry is gone if it was unloaded while loading
+        if let Some(entry) = self.storage_mut::<T>().entries.get_mut(&id) {
+            entry.asset = result.ok();
+            entry.state = state.clone();
+            if let Some(inner) = entry.handle.upgrade() {
+                inner.set_state(state);
+            }
+            self.watch_dependencies(TypeId::of::<T>(), id);
+        }
+    }
+
+    /// Finishes loads whose decoding completed on a worker thread. Must be called on the main
+    /// thread, `Game::run` does so once per frame.
+    pub fn process_loads(&mut self) {
+        while let Ok(completion) = self.completion_receiver.try_recv() {
+            completion(self);
+        }
+    }
+
+    /// Loads an asset from the asset root without caching it.
+    pub fn load_owned<T: Asset>(&self, path: &str) -> Result<T, AssetError> {
+        T::load(path, &self.context())
+    }
+
+    pub fn get<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.storage::<T>()
+            .and_then(|storage| storage.entries.get(&handle.id()))
+            .and_then(|entry| entry.asset.as_ref())
+    }
+
+    pub fn get_mut<T: Asset>(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<AssetStorage<T>>())
+            .and_then(|storage| storage.entries.get_mut(&handle.id()))
+            .and_then(|entry| entry.asset.as_mut())
+    }
+
+    /// Like `get`, but falls back to the placeholder of `T` while the asset is pending or if
+    /// it failed to load.
+    pub fn get_or_placeholder<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.get(handle).or_else(|| {
+            self.storage::<T>()
+                .and_then(|storage| storage.placeholder.as_ref())
+        })
+    }
+
+    pub fn set_placeholder<T: Asset>(&mut self, placeholder: T) {
+        self.storage_mut::<T>().placeholder = Some(placeholder);
+    }
+
+    pub fn is_loaded<T: Asset>(&self, path: &str) -> bool {
+        self.paths
+            .get(&(TypeId::of::<T>(), path.to_string()))
+            .and_then(|id| {
+                self.storage::<T>()
+                    .and_then(|storage| storage.entries.get(id))
+            })
+            .is_some_and(|entry| entry.asset.is_some())
+    }
+
+    /// Drops every asset that no handle refers to anymore and returns how many were unloaded.
+    pub fn unload_unused(&mut self) -> usize {
+        let mut unloaded = Vec::new();
+        for storage in self.storages.values_mut() {
+            unloaded.extend(storage.unload_unused());
+        }
+
+        self.paths.retain(|_, id| !unloaded.contains(id));
+        self.watched_files.retain(|path, assets| {
+            assets.retain(|(_, id)| !unloaded.contains(id));
+            if assets.is_empty() {
+                if let Some(watcher) = &mut self.watcher {
+                    watcher.unwatch(path);
+                }
+            }
+            !assets.is_empty()
+        });
+        unloaded.len()
+    }
+
+    fn storage<T: Asset>(&self) -> Option<&AssetStorage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref::<AssetStorage<T>>())
+    }
+
+    fn storage_mut<T: Asset>(&mut self) -> &mut AssetStorage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(AssetStorage::<T> {
+                    entries: HashMap::new(),
+                    placeholder: None,
+                })
+            })
+            .as_any_mut()
+            .downcast_mut::<AssetStorage<T>>()
+            .unwrap()
+    }
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        AssetServer::new("resources")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Text(String);
+
+    impl Asset for Text {
+        type Data = String;
+
+        fn decode(path: &str, context: &LoadContext) -> Result<String, AssetError> {
+            context.read_to_string(path)
+        }
+
+        fn create(_path: &str, data: String, _context: &LoadContext) -> Result<Self, AssetError> {
+            Ok(Text(data))
+        }
+    }
+
+    // Text naming another file, which is loaded too
+    struct Include(String);
+
+    impl Asset for Include {
+        type Data = String;
+
+        fn decode(path: &str, context: &LoadContext) -> Result<String, AssetError> {
+            let name = context.read_to_string(path)?;
+            context.read_to_string(&name)?;
+            Ok(name)
+        }
+
+        fn create(_path: &str, data: String, _context: &LoadContext) -> Result<Self, AssetError> {
+            Ok(Include(data))
+        }
+
+        fn dependencies(&self) -> Vec<String> {
+            vec![self.0.clone()]
+        }
+    }
+
+    fn test_server(name: &str) -> (AssetServer, PathBuf) {
+        let root = std::env::temp_dir()
+            .join("engine_asset_server_tests")
+            .join(name);
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        (AssetServer::new(&root), root)
+    }
+
+    fn wait_for_load(server: &mut AssetServer, handle: &Handle<Text>) -> LoadState {
+        for _ in 0..500 {
+            server.process_loads();
+            let state = handle.load_state();
+            if state != LoadState::Pending {
+                return state;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("{} never finished loading", handle.path());
+    }
+
+    #[test]
+    fn retries_failed_loads() {
+        let (mut server, root) = test_server("sync");
+
+        let handle = server.load_async::<Text>("message.txt");
+        assert!(matches!(
+            wait_for_load(&mut server, &handle),
+            LoadState::Failed(_)
+        ));
+        assert!(server.load::<Text>("message.txt").is_err());
+
+        std::fs::write(root.join("message.txt"), "hello").unwrap();
+        let retried = server.load::<Text>("message.txt").unwrap();
+        assert_eq!(retried.id(), handle.id());
+        assert!(handle.is_ready());
+        assert_eq!(server.get(&handle).unwrap().0, "hello");
+    }
+
+    #[test]
+    fn retries_failed_async_loads() {
+        let (mut server, root) = test_server("async");
+
+        let handle = server.load_async::<Text>("message.txt");
+        assert!(matches!(
+            wait_for_load(&mut server, &handle),
+            LoadState::Failed(_)
+        ));
+
+        std::fs::write(root.join("message.txt"), "hello").unwrap();
+        let retried = server.load_async::<Text>("message.txt");
+        assert_eq!(retried.id(), handle.id());
+        assert_eq!(handle.load_state(), LoadState::Pending);
+        assert_eq!(wait_for_load(&mut server, &handle), LoadState::Ready);
+        assert_eq!(server.get(&retried).unwrap().0, "hello");
+    }
+
+    #[test]
+    fn keeps_loaded_assets() {
+        let (mut server, root) = test_server("cached");
+        std::fs::write(root.join("message.txt"), "hello").unwrap();
+
+        let handle = server.load::<Text>("message.txt").unwrap();
+        std::fs::write(root.join("message.txt"), "changed").unwrap();
+        let again = server.load::<Text>("message.txt").unwrap();
+        assert_eq!(again.id(), handle.id());
+        assert_eq!(server.get(&again).unwrap().0, "hello");
+    }
+
+    #[test]
+    fn watches_dependencies() {
+        let (mut server, root) = test_server("dependencies");
+        std::fs::write(root.join("sync.txt"), "first.txt").unwrap();
+        std::fs::write(root.join("async.txt"), "second.txt").unwrap();
+        std::fs::write(root.join("first.txt"), "").unwrap();
+        std::fs::write(root.join("second.txt"), "").unwrap();
+        server.set_hot_reload(true);
+
+        let sync = server.load::<Include>("sync.txt").unwrap();
+        let async_handle = server.load_async::<Include>("async.txt");
+        for _ in 0..500 {
+            server.process_loads();
+            if async_handle.is_ready() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(async_handle.is_ready());
+
+        let watcher = server.watcher.as_ref().unwrap();
+        assert!(watcher.is_watching(&root.join("first.txt")));
+        assert!(watcher.is_watching(&root.join("second.txt")));
+
+        // Turning hot reload back on watches the dependencies of loaded assets again
+        server.set_hot_reload(false);
+        server.set_hot_reload(true);
+        let watched = |file: &str| {
+            let path = root.join(file).canonicalize().unwrap();
+            server.watched_files.get(&path).cloned().unwrap_or_default()
+        };
+        assert_eq!(watched("first.txt"), [(TypeId::of::<Include>(), sync.id())]);
+        assert_eq!(
+            watched("second.txt"),
+            [(TypeId::of::<Include>(), async_handle.id())]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::path::{Path, PathBuf};
+
+    const EVENT_HEADER_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+
+    pub(super) struct Inotify {
+        fd: i32,
+        directories: HashMap<i32, PathBuf>, // watch descriptor -> directory
+    }
+
+    impl Inotify {
+        pub(super) fn new() -> Option<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return None;
+            }
+
+            Some(Inotify {
+                fd,
+                directories: HashMap::new(),
+            })
+        }
+
+        // Directories are watched instead of files so that editors which save by writing a
+        // new file and renaming it over the old one are noticed too
+        pub(super) fn watch_directory(&mut self, directory: &Path) -> bool {
+            if self
+                .directories
+                .values()
+                .any(|watched| watched == directory)
+            {
+                return true;
+            }
+
+            let Ok(c_path) = CString::new(directory.as_os_str().as_bytes()) else {
+                return false;
+            };
+            let wd = unsafe {
+                libc::inotify_add_watch(
+                    self.fd,
+                    c_path.as_ptr(),
+                    libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO,
+                )
+            };
+            if wd < 0 {
+                return false;
+            }
+
+            self.directories.insert(wd, directory.to_path_buf());
+            true
+        }
+
+        /// Paths of every file written since the last call.
+        pub(super) fn read_events(&mut self) -> Vec<PathBuf> {
+            let mut changed = Vec::new();
+            let mut buffer = [0u8; 4096];
+
+            loop {
+                let read = unsafe {
+                    libc::read(
+                        self.fd,
+                        buffer.as_mut_ptr() as *mut libc::c_void,
+                        buffer.len(),
+                    )
+                };
+                if read <= 0 {
+                    break; // EAGAIN once every event was read
+                }
+
+                let mut offset = 0;
+                while offset + EVENT_HEADER_SIZE <= read as usize {
+                    let event = unsafe {
+                        std::ptr::read_unaligned(
+                            buffer.as_ptr().add(offset) as *const libc::inotify_event
+                        )
+                    };
+                    let name_start = offset + EVENT_HEADER_SIZE;
+                    let name = &buffer[name_start..name_start + event.len as usize];
+                    offset = name_start + event.len as usize;
+
+                    // The name is padded with nul bytes
+                    let name_len = name
+                        .iter()
+                        .position(|&byte| byte == 0)
+                        .unwrap_or(name.len());
+                    if let Some(directory) = self.directories.get(&event.wd) {
+                        let name = std::ffi::OsString::from_vec(name[..name_len].to_vec());
+                        changed.push(directory.join(name));
+                    }
+                }
+            }
+
+            changed
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    Inotify(inotify::Inotify),
+    Polling {
+        interval: Duration,
+        last_poll: Instant,
+    },
+}
+
+/// Reports files of the native filesystem that were modified. Uses inotify on Linux and
+/// compares modification times everywhere else, or if inotify is unavailable.
+pub struct FileWatcher {
+    backend: Backend,
+    files: HashMap<PathBuf, Option<SystemTime>>, // canonical path -> last modification time
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(inotify) = inotify::Inotify::new() {
+                return FileWatcher {
+                    backend: Backend::Inotify(inotify),
+                    files: HashMap::new(),
+                };
+            }
+            log::warn!("inotify is unavailable, falling back to polling for file changes");
+        }
+
+        Self::polling(Duration::from_millis(500))
+    }
+
+    /// Checks the modification time of every watched file at most once per `interval`.
+    pub fn polling(interval: Duration) -> Self {
+        FileWatcher {
+            backend: Backend::Polling {
+                interval,
+                last_poll: Instant::now(),
+            },
+            files: HashMap::new(),
+        }
+    }
+
+    /// Starts watching `path` and returns the canonical path changes are reported with.
+    pub fn watch(&mut self, path: &Path) -> PathBuf {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        #[cfg(target_os = "linux")]
+        if let Backend::Inotify(inotify) = &mut self.backend {
+            let watched = path
+                .parent()
+                .is_some_and(|directory| inotify.watch_directory(directory));
+            if !watched {
+                log::warn!("Failed to watch {}", path.display());
+            }
+        }
+
+        let modified = modification_time(&path);
+        self.files.entry(path.clone()).or_insert(modified);
+        path
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.files.remove(&path);
+    }
+
+    pub fn is_watching(&self, path: &Path) -> bool {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.files.contains_key(&path)
+    }
+
+    /// Watched files modified since the last call, each reported once.
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        let mut changed = match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Inotify(inotify) => inotify
+                .read_events()
+                .into_iter()
+                .filter(|path| self.files.contains_key(path))
+                .collect(),
+            Backend::Polling {
+                interval,
+                last_poll,
+            } => {
+                if last_poll.elapsed() < *interval {
+                    return Vec::new();
+                }
+                *last_poll = Instant::now();
+
+                let mut changed = Vec::new();
+                for (path, last_modified) in self.files.iter_mut() {
+                    let modified = modification_time(path);
+                    if modified != *last_modified {
+                        *last_modified = modified;
+                        changed.push(path.clone());
+                    }
+                }
+                changed
+            }
+        };
+
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
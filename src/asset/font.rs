@@ -0,0 +1,42 @@
+use crate::asset::{Asset, AssetError, LoadContext};
+
+/// TrueType/OpenType font file. The engine has no text renderer yet, so fonts are kept as
+/// validated file contents for one to rasterize.
+pub struct Font {
+    data: Vec<u8>,
+}
+
+impl Font {
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        let is_font = matches!(
+            data.get(0..4),
+            Some([0x00, 0x01, 0x00, 0x00]) | Some(b"OTTO") | Some(b"true") | Some(b"ttcf")
+        );
+
+        if is_font {
+            Some(Font { data })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Asset for Font {
+    type Data = Font;
+
+    fn decode(path: &str, context: &LoadContext) -> Result<Font, AssetError> {
+        let data = context.read(path)?;
+        Font::from_bytes(data).ok_or_else(|| AssetError::UnsupportedFormat {
+            path: context.resolve(path),
+            kind: "font",
+        })
+    }
+
+    fn create(_path: &str, font: Font, _context: &LoadContext) -> Result<Self, AssetError> {
+        Ok(font)
+    }
+}
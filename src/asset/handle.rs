@@ -0,0 +1,104 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, Weak};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(pub(crate) u64);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    Pending,
+    Ready,
+    Failed(String),
+}
+
+pub(crate) struct HandleInner {
+    pub(crate) id: AssetId,
+    pub(crate) path: String,
+    pub(crate) state: Mutex<LoadState>,
+}
+
+impl HandleInner {
+    pub(crate) fn new(id: AssetId, path: &str, state: LoadState) -> Self {
+        HandleInner {
+            id,
+            path: path.to_string(),
+            state: Mutex::new(state),
+        }
+    }
+
+    pub(crate) fn set_state(&self, state: LoadState) {
+        *self.state.lock().unwrap() = state;
+    }
+}
+
+/// Reference counted, typed reference to an asset owned by the `AssetServer`.
+/// The asset can be unloaded once every handle to it has been dropped.
+pub struct Handle<T> {
+    pub(crate) inner: Arc<HandleInner>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn new(inner: Arc<HandleInner>) -> Self {
+        Handle {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn downgrade(&self) -> Weak<HandleInner> {
+        Arc::downgrade(&self.inner)
+    }
+
+    pub fn id(&self) -> AssetId {
+        self.inner.id
+    }
+
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    pub fn load_state(&self) -> LoadState {
+        self.inner.state.lock().unwrap().clone()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        *self.inner.state.lock().unwrap() == LoadState::Ready
+    }
+
+    /// Number of live handles to this asset, including this one.
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle::new(self.inner.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.id == other.inner.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("id", &self.inner.id)
+            .field("path", &self.inner.path)
+            .finish()
+    }
+}
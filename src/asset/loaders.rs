@@ -0,0 +1,93 @@
+use crate::asset::{Asset, AssetError, LoadContext};
+use crate::renderer::{Image, ShaderProgram, Texture2D, TextureFilter, TextureSpec};
+
+impl Asset for Texture2D {
+    type Data = Image;
+
+    fn decode(path: &str, context: &LoadContext) -> Result<Image, AssetError> {
+        let bytes = context.read(path)?;
+        Image::from_memory(&bytes).map_err(|source| AssetError::Image {
+            path: context.resolve(path),
+            source,
+        })
+    }
+
+    fn create(_path: &str, image: Image, _context: &LoadContext) -> Result<Self, AssetError> {
+        let spec = TextureSpec {
+            min_filter: TextureFilter::Linear,
+            ..TextureSpec::default()
+        };
+        Ok(Texture2D::from_image(&image, spec))
+    }
+
+    // Re-upload in place so the renderer's texture slots keep pointing at a valid id
+    fn reload(
+        &mut self,
+        _path: &str,
+        image: Image,
+        _context: &LoadContext,
+    ) -> Result<(), AssetError> {
+        self.reload_image(&image);
+        Ok(())
+    }
+}
+
+/// Shaders are loaded by name: "quad" reads "quad.vert" and "quad.frag".
+impl Asset for ShaderProgram {
+    type Data = (String, String);
+
+    fn source_files(path: &str) -> Vec<String> {
+        vec![format!("{}.vert", path), format!("{}.frag", path)]
+    }
+
+    fn decode(path: &str, context: &LoadContext) -> Result<(String, String), AssetError> {
+        let vertex_source = context.read_to_string(&format!("{}.vert", path))?;
+        let fragment_source = context.read_to_string(&format!("{}.frag", path))?;
+        Ok((vertex_source, fragment_source))
+    }
+
+    fn create(
+        path: &str,
+        (vertex_source, fragment_source): (String, String),
+        context: &LoadContext,
+    ) -> Result<Self, AssetError> {
+        ShaderProgram::from_sources(&vertex_source, &fragment_source).map_err(|source| {
+            AssetError::Shader {
+                path: context.resolve(path),
+                source,
+            }
+        })
+    }
+
+    // Replacing the program would lose the uniforms created on it
+    fn reload(
+        &mut self,
+        path: &str,
+        (vertex_source, fragment_source): (String, String),
+        context: &LoadContext,
+    ) -> Result<(), AssetError> {
+        self.reload_sources(&vertex_source, &fragment_source)
+            .map_err(|source| AssetError::Shader {
+                path: context.resolve(path),
+                source,
+            })
+    }
+}
+
+/// Magenta and black checkerboard shown in place of textures that are not loaded.
+pub(crate) fn placeholder_texture() -> Texture2D {
+    const SIZE: u32 = 8;
+    let mut image = Image::new(SIZE, SIZE, 4);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let pixel = if (x / 4 + y / 4) % 2 == 0 {
+                [255, 0, 255, 255]
+            } else {
+                [0, 0, 0, 255]
+            };
+            image.set_pixel(x, y, &pixel);
+        }
+    }
+
+    Texture2D::from_image(&image, TextureSpec::default())
+}
@@ -0,0 +1,17 @@
+pub mod archive;
+pub mod asset_server;
+pub mod file_watcher;
+pub mod font;
+pub mod handle;
+pub mod loaders;
+pub mod sound;
+pub mod thread_pool;
+pub mod vfs;
+
+pub use self::archive::*;
+pub use self::asset_server::*;
+pub use self::file_watcher::*;
+pub use self::font::*;
+pub use self::handle::*;
+pub use self::sound::*;
+pub(crate) use self::thread_pool::*;
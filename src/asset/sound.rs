@@ -0,0 +1,55 @@
+use crate::asset::{Asset, AssetError, LoadContext};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundFormat {
+    Wav,
+    Ogg,
+    Flac,
+    Mp3,
+}
+
+/// Encoded audio file. The engine has no audio backend yet, so sounds are kept as validated
+/// file contents for one to decode.
+pub struct Sound {
+    format: SoundFormat,
+    data: Vec<u8>,
+}
+
+impl Sound {
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        let format = match data.as_slice() {
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => SoundFormat::Wav,
+            [b'O', b'g', b'g', b'S', ..] => SoundFormat::Ogg,
+            [b'f', b'L', b'a', b'C', ..] => SoundFormat::Flac,
+            [b'I', b'D', b'3', ..] => SoundFormat::Mp3,
+            [0xff, second, ..] if second & 0xe0 == 0xe0 => SoundFormat::Mp3,
+            _ => return None,
+        };
+
+        Some(Sound { format, data })
+    }
+
+    pub fn get_format(&self) -> SoundFormat {
+        self.format
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Asset for Sound {
+    type Data = Sound;
+
+    fn decode(path: &str, context: &LoadContext) -> Result<Sound, AssetError> {
+        let data = context.read(path)?;
+        Sound::from_bytes(data).ok_or_else(|| AssetError::UnsupportedFormat {
+            path: context.resolve(path),
+            kind: "sound",
+        })
+    }
+
+    fn create(_path: &str, sound: Sound, _context: &LoadContext) -> Result<Self, AssetError> {
+        Ok(sound)
+    }
+}
@@ -0,0 +1,226 @@
+//! Virtual filesystem every engine loader reads through.
+//!
+//! Paths are relative, `/` separated and resolved against the mounts, the most recently
+//! mounted first. By default the working directory is mounted at the root, so loose files work
+//! without any setup. Mounting an archive at "resources" makes it shadow the loose files:
+//!
+//! ```ignore
+//! engine::asset::vfs::mount_archive("resources", "resources.pak")?;
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use crate::asset::{Archive, ArchiveError};
+
+enum MountSource {
+    Directory(PathBuf),
+    Archive(Archive),
+}
+
+struct Mount {
+    point: String,
+    source: MountSource,
+}
+
+impl Mount {
+    // Path of `path` inside this mount, if the mount covers it
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.point.is_empty() {
+            return Some(path);
+        }
+
+        let rest = path.strip_prefix(self.point.as_str())?;
+        if rest.is_empty() {
+            Some(rest)
+        } else {
+            rest.strip_prefix('/')
+        }
+    }
+
+    fn read(&self, relative: &str) -> Option<std::io::Result<Vec<u8>>> {
+        match &self.source {
+            MountSource::Directory(directory) => {
+                let native = directory.join(relative);
+                native.is_file().then(|| std::fs::read(native))
+            }
+            MountSource::Archive(archive) => archive.contains(relative).then(|| {
+                archive.read(relative).map_err(|error| match error {
+                    ArchiveError::Io(error) => error,
+                    error => Error::new(ErrorKind::InvalidData, error),
+                })
+            }),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Vfs {
+    mounts: Vec<Mount>,
+}
+
+impl Vfs {
+    /// Mounts a directory of the native filesystem at `point` ("" for the root).
+    pub fn mount_directory<P: Into<PathBuf>>(&mut self, point: &str, directory: P) {
+        self.mounts.push(Mount {
+            point: normalize(point),
+            source: MountSource::Directory(directory.into()),
+        });
+    }
+
+    pub fn mount_archive_file(&mut self, point: &str, archive: Archive) {
+        self.mounts.push(Mount {
+            point: normalize(point),
+            source: MountSource::Archive(archive),
+        });
+    }
+
+    /// Removes every mount at `point` and returns how many there were.
+    pub fn unmount(&mut self, point: &str) -> usize {
+        let point = normalize(point);
+        let count = self.mounts.len();
+        self.mounts.retain(|mount| mount.point != point);
+        count - self.mounts.len()
+    }
+
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> std::io::Result<Vec<u8>> {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            return std::fs::read(path);
+        }
+
+        let virtual_path = normalize_path(path);
+        for mount in self.mounts.iter().rev() {
+            if let Some(result) = mount
+                .relative(&virtual_path)
+                .and_then(|relative| mount.read(relative))
+            {
+                return result;
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("{} not found in any mount", virtual_path),
+        ))
+    }
+
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> std::io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))
+    }
+
+    /// Path on the native filesystem `path` is read from, `None` if it comes from an archive
+    /// or does not exist.
+    pub fn native_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            return path.is_file().then(|| path.to_path_buf());
+        }
+
+        let virtual_path = normalize_path(path);
+        for mount in self.mounts.iter().rev() {
+            let Some(relative) = mount.relative(&virtual_path) else {
+                continue;
+            };
+            match &mount.source {
+                MountSource::Directory(directory) => {
+                    let native = directory.join(relative);
+                    if native.is_file() {
+                        return Some(native);
+                    }
+                }
+                MountSource::Archive(archive) => {
+                    if archive.contains(relative) {
+                        return None;
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            return path.is_file();
+        }
+
+        let virtual_path = normalize_path(path);
+        self.mounts.iter().any(|mount| {
+            mount
+                .relative(&virtual_path)
+                .is_some_and(|relative| match &mount.source {
+                    MountSource::Directory(directory) => directory.join(relative).is_file(),
+                    MountSource::Archive(archive) => archive.contains(relative),
+                })
+        })
+    }
+}
+
+fn normalize(path: &str) -> String {
+    normalize_path(Path::new(path))
+}
+
+// Turns a native path into "a/b/c", resolving "." and ".."
+fn normalize_path(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                // Windows style separators in paths written by hand
+                for part in part.to_string_lossy().split('\\') {
+                    if !part.is_empty() {
+                        parts.push(part.to_string());
+                    }
+                }
+            }
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+fn global() -> &'static RwLock<Vfs> {
+    static VFS: OnceLock<RwLock<Vfs>> = OnceLock::new();
+    VFS.get_or_init(|| {
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("", ".");
+        RwLock::new(vfs)
+    })
+}
+
+pub fn mount_directory<P: Into<PathBuf>>(point: &str, directory: P) {
+    global().write().unwrap().mount_directory(point, directory);
+}
+
+/// Opens an archive and mounts it at `point`, on top of everything mounted before.
+pub fn mount_archive<P: AsRef<Path>>(point: &str, archive_path: P) -> Result<(), ArchiveError> {
+    let archive = Archive::open(archive_path)?;
+    global().write().unwrap().mount_archive_file(point, archive);
+    Ok(())
+}
+
+pub fn unmount(point: &str) -> usize {
+    global().write().unwrap().unmount(point)
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<u8>> {
+    global().read().unwrap().read(path)
+}
+
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> std::io::Result<String> {
+    global().read().unwrap().read_to_string(path)
+}
+
+pub fn exists<P: AsRef<Path>>(path: P) -> bool {
+    global().read().unwrap().exists(path)
+}
+
+pub fn native_path<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    global().read().unwrap().native_path(path)
+}
@@ -1,10 +1,32 @@
-use crate::core::{KeyCode, MouseCode, Window};
-use crate::renderer::{RenderCommand, Renderer};
+use std::path::{Path, PathBuf};
+
+use crate::asset::{loaders, vfs, AssetEvent, AssetServer};
+use crate::core::{save_capture, screenshot_path, FrameRecorder, KeyCode, MouseCode, Window};
+use crate::debug::DebugDraw;
+use crate::renderer::{
+    report_live_resources, Framebuffer, FramebufferSpec, ImageError, RenderCommand, RenderStats,
+    Renderer, TextureFormat,
+};
+
+/// Directory the screenshot key saves to.
+pub const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
+/// Archive mounted over "resources" in release builds, if present in the working directory.
+pub const RESOURCE_PACK: &str = "resources.pak";
 
 pub struct GameEngine {
     ts: f32,
-    pub(crate) window: Window,
+    /// Saves a screenshot to `SCREENSHOT_DIRECTORY` when pressed.
+    pub screenshot_key: Option<KeyCode>,
+    screenshot_key_down: bool,
+    screenshot_requests: Vec<PathBuf>,
+    recorder: Option<FrameRecorder>,
+    // Declared before the window so GPU resources are released while the GL context still exists
+    headless_frame: Option<Framebuffer>, // drawn to instead of the window by headless runs
     pub renderer: Renderer,
+    pub assets: AssetServer,
+    pub debug: DebugDraw,
+    pub(crate) window: Window,
 }
 
 impl GameEngine {
@@ -47,6 +69,10 @@ impl GameEngine {
         return self.ts;
     }
 
+    pub fn get_render_stats(&self) -> &RenderStats {
+        self.renderer.get_stats()
+    }
+
     pub fn get_window(&mut self) -> &mut Window {
         &mut self.window
     }
@@ -54,43 +80,204 @@ impl GameEngine {
     pub fn exit(&mut self) {
         self.window.set_should_close(true);
     }
+
+    /// Started by `Game::run_headless`, frames are drawn off-screen.
+    pub fn is_headless(&self) -> bool {
+        self.headless_frame.is_some()
+    }
+
+    /// Saves the window, or the frame of a headless run, to a PNG at `path` once the current
+    /// frame is drawn, including the debug shapes. Errors are logged.
+    pub fn capture_screenshot<P: AsRef<Path>>(&mut self, path: P) {
+        self.screenshot_requests.push(path.as_ref().to_path_buf());
+    }
+
+    /// Saves the first color attachment of `framebuffer` to a PNG at `path` right away, e.g.
+    /// to capture an off-screen render without a visible window.
+    pub fn capture_framebuffer<P: AsRef<Path>>(
+        &self,
+        framebuffer: &Framebuffer,
+        path: P,
+    ) -> Result<(), ImageError> {
+        let image = framebuffer.get_color_attachment(0).read_pixels();
+        save_capture(&image, path.as_ref())
+    }
+
+    /// Starts saving frames with `recorder`, replacing the current recording.
+    pub fn start_recording(&mut self, recorder: FrameRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Stops the recording and returns it, e.g. to know how many frames were saved.
+    pub fn stop_recording(&mut self) -> Option<FrameRecorder> {
+        self.recorder.take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    fn update_screenshot_key(&mut self) {
+        let key_down = self.screenshot_key.is_some_and(|key| self.get_key(key));
+        if key_down && !self.screenshot_key_down {
+            self.capture_screenshot(screenshot_path(SCREENSHOT_DIRECTORY));
+        }
+        self.screenshot_key_down = key_down;
+    }
+
+    // Reads the drawn frame once for the screenshots and the recording
+    fn capture_frame(&mut self, width: u32, height: u32) {
+        let mut paths = std::mem::take(&mut self.screenshot_requests);
+        if let Some(path) = self
+            .recorder
+            .as_mut()
+            .and_then(|recorder| recorder.next_frame())
+        {
+            paths.push(path);
+        }
+        if paths.is_empty() {
+            return;
+        }
+
+        // Binds the frame of a headless run, the window otherwise
+        Framebuffer::unbind();
+        let image = RenderCommand::read_pixels(0, 0, width, height);
+        for path in paths {
+            if let Err(error) = save_capture(&image, &path) {
+                log::error!("Failed to save {}: {}", path.display(), error);
+            }
+        }
+    }
 }
 
 pub trait Game {
     fn init(&mut self, _engine: &mut GameEngine) {}
     fn update(&mut self, _engine: &mut GameEngine) {}
     fn draw(&mut self, _engine: &mut GameEngine) {}
+    /// Called before `update` for every asset event of the frame, e.g. hot reloads.
+    fn on_asset_event(&mut self, _engine: &mut GameEngine, _event: &AssetEvent) {}
+    /// Called once the main loop ends. The game is dropped right after, while the GL context
+    /// still exists, so GPU resources it owns are released either way.
     fn close(&mut self) {}
 
-    fn run(&mut self, window_width: u32, window_height: u32, title: &str) {
-        let mut last_frame_time = 0.0;
+    /// Runs the game until the window closes. Takes the game so it can be dropped before the
+    /// engine destroys the window and its GL context.
+    fn run(self, window_width: u32, window_height: u32, title: &str)
+    where
+        Self: Sized,
+    {
+        run_game(self, Window::new(window_width, window_height, title), false);
+    }
+
+    /// Like `run` with a window that is never shown, every frame being drawn to an off-screen
+    /// framebuffer that screenshots and recordings read, e.g. to render a sequence from a
+    /// script. Creating the GL context still needs a display. Stops on `GameEngine::exit`.
+    fn run_headless(self, window_width: u32, window_height: u32, title: &str)
+    where
+        Self: Sized,
+    {
+        run_game(
+            self,
+            Window::new_hidden(window_width, window_height, title),
+            true,
+        );
+    }
+}
+
+fn run_game<G: Game>(mut game: G, mut window: Window, headless: bool) {
+    let mut last_frame_time = 0.0;
+
+    window.init_gl();
 
-        let mut window = Window::new(window_width, window_height, title);
-        window.init_gl();
+    // Release builds read assets from the pack built by `packer` when one is shipped
+    if cfg!(not(debug_assertions)) && std::path::Path::new(RESOURCE_PACK).is_file() {
+        if let Err(error) = vfs::mount_archive("resources", RESOURCE_PACK) {
+            log::error!("Failed to mount {}: {}", RESOURCE_PACK, error);
+        }
+    }
+
+    let mut assets = AssetServer::default();
+    assets.set_placeholder(loaders::placeholder_texture());
+    assets.set_hot_reload(cfg!(debug_assertions));
+    let renderer = Renderer::new(&assets);
 
-        let renderer = Renderer::new();
+    // A window that was never shown owns no pixels, what is drawn to it cannot be read back
+    let headless_frame = headless.then(|| {
+        let (width, height) = window.get_size();
+        Framebuffer::new(FramebufferSpec {
+            width,
+            height,
+            color_attachments: vec![TextureFormat::RGBA8],
+            depth_stencil: true,
+        })
+    });
 
-        let mut game_engine = GameEngine {
-            ts: 0.0,
-            window: window,
-            renderer: renderer,
+    let mut game_engine = GameEngine {
+        ts: 0.0,
+        screenshot_key: Some(KeyCode::F12),
+        screenshot_key_down: false,
+        screenshot_requests: Vec::new(),
+        recorder: None,
+        headless_frame,
+        renderer: renderer,
+        assets,
+        debug: DebugDraw::new(),
+        window: window,
+    };
+
+    game.init(&mut game_engine);
+
+    while !game_engine.window.should_close() {
+        let time = game_engine.window.get_time();
+        game_engine.ts = match &game_engine.recorder {
+            Some(recorder) => recorder.get_timestep(),
+            None => time - last_frame_time,
         };
+        last_frame_time = time;
 
-        self.init(&mut game_engine);
+        game_engine.assets.process_loads();
+        game_engine.assets.check_for_changes();
+        for event in game_engine.assets.drain_events() {
+            game.on_asset_event(&mut game_engine, &event);
+        }
 
-        while !game_engine.window.should_close() {
-            let time = game_engine.window.get_time();
-            game_engine.ts = time - last_frame_time;
-            last_frame_time = time;
+        if let Some(key) = game_engine.debug.toggle_key {
+            let key_down = game_engine.get_key(key);
+            game_engine.debug.update_toggle(key_down);
+        }
 
-            self.update(&mut game_engine);
+        game_engine.update_screenshot_key();
 
-            RenderCommand::clear();
-            self.draw(&mut game_engine);
+        game.update(&mut game_engine);
 
-            game_engine.window.update();
+        let (width, height) = game_engine.window.get_size();
+        if let Some(frame) = &mut game_engine.headless_frame {
+            frame.resize(width, height);
         }
+        Framebuffer::set_screen(game_engine.headless_frame.as_ref());
+        Framebuffer::unbind();
+        RenderCommand::set_viewport(0, 0, width, height);
+        RenderCommand::clear();
+
+        game_engine.renderer.begin_frame();
+        game.draw(&mut game_engine);
+        game_engine
+            .debug
+            .render(&mut game_engine.renderer, (width, height));
+        game_engine.renderer.end_frame();
+        game_engine.debug.end_frame(game_engine.ts);
+        game_engine.capture_frame(width, height);
+
+        game_engine.window.update();
+    }
+
+    game.close();
 
-        self.close();
+    drop(game);
+    Framebuffer::set_screen(None);
+    drop(game_engine);
+    let leaked = report_live_resources();
+    if leaked > 0 {
+        log::warn!("{} GPU resources were still alive at shutdown", leaked);
     }
 }
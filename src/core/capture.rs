@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::renderer::{Image, ImageError};
+
+/// Saves every `interval`-th frame to numbered PNGs in a directory. While recording, the
+/// engine advances the game by a fixed timestep instead of the real frame time, so the
+/// sequence plays back at a steady rate however long the captures take.
+#[derive(Clone, Debug)]
+pub struct FrameRecorder {
+    directory: PathBuf,
+    interval: u32,
+    timestep: f32,
+    frame: u32,
+    saved: u32,
+}
+
+impl FrameRecorder {
+    /// Records one frame out of `interval`, simulating `timestep` seconds per frame. A
+    /// 60 Hz video with every frame kept is `FrameRecorder::new(directory, 1, 1.0 / 60.0)`.
+    pub fn new<P: Into<PathBuf>>(directory: P, interval: u32, timestep: f32) -> Self {
+        FrameRecorder {
+            directory: directory.into(),
+            interval: interval.max(1),
+            timestep,
+            frame: 0,
+            saved: 0,
+        }
+    }
+
+    /// Where the current frame goes if it is one to keep. Moves on to the next frame.
+    pub fn next_frame(&mut self) -> Option<PathBuf> {
+        let keep = self.frame.is_multiple_of(self.interval);
+        self.frame += 1;
+        if !keep {
+            return None;
+        }
+
+        let path = self.directory.join(frame_file_name(self.saved));
+        self.saved += 1;
+        Some(path)
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn get_timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    /// Number of frames saved so far.
+    pub fn get_saved_count(&self) -> u32 {
+        self.saved
+    }
+}
+
+/// Name of the `index`-th image of a sequence, e.g. "frame_00042.png".
+pub fn frame_file_name(index: u32) -> String {
+    format!("frame_{:05}.png", index)
+}
+
+/// Path of a new screenshot in `directory`, named after the current time.
+pub fn screenshot_path<P: AsRef<Path>>(directory: P) -> PathBuf {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    directory.as_ref().join(format!(
+        "screenshot_{}_{:03}.png",
+        time.as_secs(),
+        time.subsec_millis()
+    ))
+}
+
+/// Writes `image` to `path` as a PNG, creating the missing directories.
+pub fn save_capture(image: &Image, path: &Path) -> Result<(), ImageError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save_png(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_names(recorder: &mut FrameRecorder, frames: u32) -> Vec<Option<String>> {
+        (0..frames)
+            .map(|_| {
+                recorder.next_frame().map(|path| {
+                    assert_eq!(path.parent(), Some(recorder.get_directory()));
+                    path.file_name().unwrap().to_string_lossy().into_owned()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_every_frame() {
+        let mut recorder = FrameRecorder::new("frames", 1, 1.0 / 60.0);
+        assert_eq!(
+            file_names(&mut recorder, 3),
+            [
+                Some("frame_00000.png".to_string()),
+                Some("frame_00001.png".to_string()),
+                Some("frame_00002.png".to_string())
+            ]
+        );
+        assert_eq!(recorder.get_saved_count(), 3);
+    }
+
+    #[test]
+    fn keeps_one_frame_per_interval() {
+        let mut recorder = FrameRecorder::new("frames", 3, 1.0 / 30.0);
+        let names = file_names(&mut recorder, 7);
+        assert_eq!(names[0].as_deref(), Some("frame_00000.png"));
+        assert_eq!(names[3].as_deref(), Some("frame_00001.png"));
+        assert_eq!(names[6].as_deref(), Some("frame_00002.png"));
+        assert_eq!(names.iter().filter(|name| name.is_some()).count(), 3);
+        assert_eq!(recorder.get_saved_count(), 3);
+        assert_eq!(recorder.get_timestep(), 1.0 / 30.0);
+    }
+
+    #[test]
+    fn treats_a_zero_interval_as_every_frame() {
+        let mut recorder = FrameRecorder::new("frames", 0, 1.0);
+        assert!(file_names(&mut recorder, 4).iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn names_frames_in_order() {
+        assert_eq!(frame_file_name(42), "frame_00042.png");
+        assert_eq!(frame_file_name(123456), "frame_123456.png");
+        assert!(frame_file_name(9) < frame_file_name(10));
+    }
+
+    #[test]
+    fn saves_captures_in_new_directories() {
+        let directory = std::env::temp_dir().join("engine_capture_tests");
+        let _ = std::fs::remove_dir_all(&directory);
+        let path = directory.join("nested").join(frame_file_name(0));
+
+        save_capture(&Image::new(2, 2, 3), &path).unwrap();
+        let image = Image::from_memory(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!((image.get_width(), image.get_height()), (2, 2));
+    }
+}
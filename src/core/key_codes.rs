@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum KeyCode {
     // From glfw3.h
     Space = 32,
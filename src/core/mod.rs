@@ -1,10 +1,12 @@
 pub mod base;
+pub mod capture;
 pub mod key_codes;
 pub mod logger;
 pub mod mouse_codes;
 pub mod window;
 
 pub use self::base::*;
+pub use self::capture::*;
 pub use self::key_codes::*;
 pub use self::logger::*;
 pub use self::mouse_codes::*;
@@ -1,6 +1,8 @@
 use glfw::{ffi::glfwSwapInterval, Context, WindowEvent};
 use std::sync::mpsc::Receiver;
 
+use crate::renderer::{BlendMode, RenderCommand};
+
 pub struct Window {
     glfw: glfw::Glfw,
     pub(crate) window_handle: glfw::Window,
@@ -9,7 +11,19 @@ pub struct Window {
 
 impl Window {
     pub fn new(width: u32, height: u32, title: &str) -> Self {
-        let glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+        Self::create(width, height, title, true)
+    }
+
+    /// Creates a window that is never shown, with a GL context all the same. It still needs a
+    /// display. A window that was never shown owns no pixels, so reading back what is drawn to
+    /// it is undefined: draw to a `Framebuffer` instead, as `Game::run_headless` does.
+    pub fn new_hidden(width: u32, height: u32, title: &str) -> Self {
+        Self::create(width, height, title, false)
+    }
+
+    fn create(width: u32, height: u32, title: &str, visible: bool) -> Self {
+        let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+        glfw.window_hint(glfw::WindowHint::Visible(visible));
 
         let (mut window, events) = glfw
             .create_window(width, height, title, glfw::WindowMode::Windowed)
@@ -34,8 +48,8 @@ impl Window {
         gl::load_with(|s| self.window_handle.get_proc_address(s) as *const _);
         unsafe {
             gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
+        RenderCommand::set_blend_mode(BlendMode::Alpha);
     }
 
     pub fn should_close(&self) -> bool {
@@ -52,15 +66,10 @@ impl Window {
         self.window_handle.swap_buffers();
     }
 
+    // The viewport is set by the engine every frame and by the cameras' viewports, events
+    // are only drained so they do not pile up
     fn process_events(&mut self) {
-        for (_, event) in glfw::flush_messages(&self.events) {
-            match event {
-                glfw::WindowEvent::FramebufferSize(width, height) => unsafe {
-                    gl::Viewport(0, 0, width, height)
-                },
-                _ => {}
-            }
-        }
+        for _ in glfw::flush_messages(&self.events) {}
     }
 
     pub fn get_size(&self) -> (u32, u32) {
@@ -0,0 +1,367 @@
+//! Shapes drawn on top of everything for debugging, e.g. collision boxes or paths. They are
+//! recorded at any point of the frame and drawn by the engine after `Game::draw`.
+//!
+//! Everything is compiled out unless the "debug-draw" feature is enabled, and always in release
+//! builds, so a game can enable the feature in its manifest and still ship without it.
+
+use cgmath::*;
+
+use crate::core::KeyCode;
+use crate::math::AABB;
+use crate::renderer::Renderer;
+
+#[cfg(all(feature = "debug-draw", debug_assertions))]
+use crate::debug::debug_font;
+#[cfg(all(feature = "debug-draw", debug_assertions))]
+use crate::renderer::SceneView;
+
+const CIRCLE_SEGMENTS: u32 = 32;
+// Keeps a tiny spacing from producing millions of lines
+const MAX_GRID_LINES: i64 = 512;
+
+#[cfg_attr(not(all(feature = "debug-draw", debug_assertions)), allow(dead_code))]
+enum DebugShape {
+    Lines(Vec<[Vector2<f32>; 2]>),
+    /// Text at a world position, keeping its size in pixels.
+    WorldText(Vector2<f32>, String),
+    /// Text in window pixels from the top-left.
+    ScreenText(Vector2<f32>, String),
+}
+
+#[cfg(all(feature = "debug-draw", debug_assertions))]
+struct DebugItemData {
+    shape: DebugShape,
+    color: Vector4<f32>,
+    time_left: f32,
+}
+
+/// A recorded shape, drawn for a single frame unless given a duration.
+pub struct DebugItem<'a> {
+    time_left: Option<&'a mut f32>,
+}
+
+impl DebugItem<'_> {
+    /// Keeps drawing the shape for `seconds`.
+    pub fn duration(self, seconds: f32) {
+        if let Some(time_left) = self.time_left {
+            *time_left = seconds;
+        }
+    }
+}
+
+/// Collects debug shapes and draws them through the first camera of the frame. World shapes
+/// use world units, line widths and text sizes are in pixels.
+pub struct DebugDraw {
+    /// Whether shapes are drawn, flipped by pressing `toggle_key`.
+    pub enabled: bool,
+    pub toggle_key: Option<KeyCode>,
+    /// Width of lines in pixels.
+    pub line_width: f32,
+    /// Size of a font pixel in window pixels.
+    pub text_scale: f32,
+
+    toggle_key_down: bool,
+    #[cfg(all(feature = "debug-draw", debug_assertions))]
+    items: Vec<DebugItemData>,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        DebugDraw {
+            enabled: true,
+            toggle_key: Some(KeyCode::F1),
+            line_width: 2.0,
+            text_scale: 2.0,
+
+            toggle_key_down: false,
+            #[cfg(all(feature = "debug-draw", debug_assertions))]
+            items: Vec::new(),
+        }
+    }
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(
+        &mut self,
+        from: Vector2<f32>,
+        to: Vector2<f32>,
+        color: Vector4<f32>,
+    ) -> DebugItem<'_> {
+        self.push(color, || DebugShape::Lines(vec![[from, to]]))
+    }
+
+    /// Line from `from` to `to` with a head at `to`, a quarter of its length.
+    pub fn arrow(
+        &mut self,
+        from: Vector2<f32>,
+        to: Vector2<f32>,
+        color: Vector4<f32>,
+    ) -> DebugItem<'_> {
+        self.push(color, || DebugShape::Lines(arrow_lines(from, to)))
+    }
+
+    pub fn aabb(&mut self, bounds: &AABB, color: Vector4<f32>) -> DebugItem<'_> {
+        self.push(color, || {
+            let (min, max) = (bounds.min(), bounds.max());
+            DebugShape::Lines(outline(&[
+                min,
+                Vector2::new(max.x, min.y),
+                max,
+                Vector2::new(min.x, max.y),
+            ]))
+        })
+    }
+
+    pub fn circle(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        color: Vector4<f32>,
+    ) -> DebugItem<'_> {
+        self.push(color, || DebugShape::Lines(circle_lines(center, radius)))
+    }
+
+    /// Two diagonal lines of `size` crossing at `position`, e.g. to mark a point.
+    pub fn cross(
+        &mut self,
+        position: Vector2<f32>,
+        size: f32,
+        color: Vector4<f32>,
+    ) -> DebugItem<'_> {
+        self.push(color, || {
+            let half = size / 2.0;
+            DebugShape::Lines(vec![
+                [
+                    position + Vector2::new(-half, -half),
+                    position + Vector2::new(half, half),
+                ],
+                [
+                    position + Vector2::new(-half, half),
+                    position + Vector2::new(half, -half),
+                ],
+            ])
+        })
+    }
+
+    /// Lines every `spacing` units inside `bounds`, aligned to the world origin.
+    pub fn grid(&mut self, bounds: &AABB, spacing: f32, color: Vector4<f32>) -> DebugItem<'_> {
+        self.push(color, || DebugShape::Lines(grid_lines(bounds, spacing)))
+    }
+
+    /// Text starting at a world position, with a fixed size on screen.
+    pub fn text(
+        &mut self,
+        position: Vector2<f32>,
+        text: &str,
+        color: Vector4<f32>,
+    ) -> DebugItem<'_> {
+        self.push(color, || DebugShape::WorldText(position, text.to_string()))
+    }
+
+    /// Text at a position in window pixels from the top-left.
+    pub fn screen_text(
+        &mut self,
+        position: Vector2<f32>,
+        text: &str,
+        color: Vector4<f32>,
+    ) -> DebugItem<'_> {
+        self.push(color, || DebugShape::ScreenText(position, text.to_string()))
+    }
+
+    /// Removes every shape, including the ones with a duration left.
+    pub fn clear(&mut self) {
+        #[cfg(all(feature = "debug-draw", debug_assertions))]
+        self.items.clear();
+    }
+
+    #[cfg(all(feature = "debug-draw", debug_assertions))]
+    fn push(&mut self, color: Vector4<f32>, shape: impl FnOnce() -> DebugShape) -> DebugItem<'_> {
+        self.items.push(DebugItemData {
+            shape: shape(),
+            color,
+            time_left: 0.0,
+        });
+
+        DebugItem {
+            time_left: self.items.last_mut().map(|item| &mut item.time_left),
+        }
+    }
+
+    #[cfg(not(all(feature = "debug-draw", debug_assertions)))]
+    fn push(&mut self, _color: Vector4<f32>, _shape: impl FnOnce() -> DebugShape) -> DebugItem<'_> {
+        DebugItem { time_left: None }
+    }
+
+    /// Flips `enabled` when the toggle key goes down, called by the engine every frame.
+    pub(crate) fn update_toggle(&mut self, key_down: bool) {
+        if key_down && !self.toggle_key_down {
+            self.enabled = !self.enabled;
+        }
+        self.toggle_key_down = key_down;
+    }
+
+    /// Draws the shapes on top of the frame, called by the engine after `Game::draw`. World
+    /// shapes are drawn through the first scene of the frame and skipped if there was none.
+    #[cfg(all(feature = "debug-draw", debug_assertions))]
+    pub fn render(&self, renderer: &mut Renderer, window_size: (u32, u32)) {
+        if !self.enabled || self.items.is_empty() {
+            return;
+        }
+
+        let world = renderer.get_first_scene();
+        if let Some(view) = world {
+            let width = self.line_width * view.get_pixel_size();
+
+            renderer.begin_overlay(view);
+            for item in &self.items {
+                if let DebugShape::Lines(lines) = &item.shape {
+                    for [from, to] in lines {
+                        draw_line(renderer, *from, *to, width, item.color);
+                    }
+                }
+            }
+            renderer.end_overlay();
+        }
+
+        let (window_width, window_height) = window_size;
+        renderer.begin_overlay(SceneView {
+            view_projection: ortho(
+                0.0,
+                window_width as f32,
+                window_height as f32,
+                0.0,
+                -1.0,
+                1.0,
+            ),
+            viewport: (0, 0, window_width, window_height),
+        });
+        for item in &self.items {
+            let (position, text) = match &item.shape {
+                DebugShape::ScreenText(position, text) => (*position, text),
+                DebugShape::WorldText(position, text) => match &world {
+                    Some(view) => (world_to_window(view, *position, window_height), text),
+                    None => continue,
+                },
+                DebugShape::Lines(_) => continue,
+            };
+            self.draw_text(renderer, position, text, item.color);
+        }
+        renderer.end_overlay();
+    }
+
+    #[cfg(not(all(feature = "debug-draw", debug_assertions)))]
+    pub fn render(&self, _renderer: &mut Renderer, _window_size: (u32, u32)) {}
+
+    /// Ages the shapes and drops the expired ones, called by the engine after `render`.
+    pub fn end_frame(&mut self, timestep: f32) {
+        #[cfg(all(feature = "debug-draw", debug_assertions))]
+        self.items.retain_mut(|item| {
+            item.time_left -= timestep;
+            item.time_left > 0.0
+        });
+        #[cfg(not(all(feature = "debug-draw", debug_assertions)))]
+        let _ = timestep;
+    }
+
+    #[cfg(all(feature = "debug-draw", debug_assertions))]
+    fn draw_text(
+        &self,
+        renderer: &mut Renderer,
+        position: Vector2<f32>,
+        text: &str,
+        color: Vector4<f32>,
+    ) {
+        let scale = self.text_scale;
+        for (line_index, line) in text.lines().enumerate() {
+            let top = position.y + (line_index as u32 * debug_font::LINE_HEIGHT) as f32 * scale;
+            for (index, character) in line.chars().enumerate() {
+                let left = position.x + (index as u32 * debug_font::ADVANCE) as f32 * scale;
+                for (column, row, length) in debug_font::glyph_runs(character) {
+                    let size = Vector2::new(length as f32, 1.0) * scale;
+                    let corner =
+                        Vector2::new(left + column as f32 * scale, top + row as f32 * scale);
+                    renderer.draw_quad(corner + size / 2.0, size, color, None);
+                }
+            }
+        }
+    }
+}
+
+fn outline(points: &[Vector2<f32>]) -> Vec<[Vector2<f32>; 2]> {
+    (0..points.len())
+        .map(|index| [points[index], points[(index + 1) % points.len()]])
+        .collect()
+}
+
+fn arrow_lines(from: Vector2<f32>, to: Vector2<f32>) -> Vec<[Vector2<f32>; 2]> {
+    let mut lines = vec![[from, to]];
+
+    let direction = to - from;
+    if direction.magnitude2() > 0.0 {
+        let back = -direction * 0.25;
+        let side = Vector2::new(-back.y, back.x) * 0.5;
+        lines.push([to, to + back + side]);
+        lines.push([to, to + back - side]);
+    }
+    lines
+}
+
+fn circle_lines(center: Vector2<f32>, radius: f32) -> Vec<[Vector2<f32>; 2]> {
+    let points: Vec<_> = (0..CIRCLE_SEGMENTS)
+        .map(|segment| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + Vector2::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect();
+    outline(&points)
+}
+
+fn grid_lines(bounds: &AABB, spacing: f32) -> Vec<[Vector2<f32>; 2]> {
+    if spacing <= 0.0 {
+        return Vec::new();
+    }
+
+    let (min, max) = (bounds.min(), bounds.max());
+    let steps = |from: f32, to: f32| {
+        let first = (from / spacing).ceil() as i64;
+        let last = ((to / spacing).floor() as i64).min(first + MAX_GRID_LINES - 1);
+        (first..=last).map(|step| step as f32 * spacing)
+    };
+
+    let vertical = steps(min.x, max.x).map(|x| [Vector2::new(x, min.y), Vector2::new(x, max.y)]);
+    let horizontal = steps(min.y, max.y).map(|y| [Vector2::new(min.x, y), Vector2::new(max.x, y)]);
+    vertical.chain(horizontal).collect()
+}
+
+#[cfg(all(feature = "debug-draw", debug_assertions))]
+fn draw_line(
+    renderer: &mut Renderer,
+    from: Vector2<f32>,
+    to: Vector2<f32>,
+    width: f32,
+    color: Vector4<f32>,
+) {
+    let direction = to - from;
+    // Lengthened by the width so the corners of connected lines are filled
+    let size = Vector2::new(direction.magnitude() + width, width);
+    let rotation = direction.y.atan2(direction.x);
+    renderer.draw_rotated_quad((from + to) / 2.0, size, rotation, color, None);
+}
+
+// Window pixels from the top-left of a world position seen through `view`
+#[cfg(all(feature = "debug-draw", debug_assertions))]
+fn world_to_window(view: &SceneView, position: Vector2<f32>, window_height: u32) -> Vector2<f32> {
+    let clip = view.view_projection * position.extend(0.0).extend(1.0);
+    let ndc = Vector2::new(clip.x / clip.w, clip.y / clip.w);
+
+    let (x, y, width, height) = view.viewport;
+    let from_bottom = y as f32 + (ndc.y + 1.0) / 2.0 * height as f32;
+    Vector2::new(
+        x as f32 + (ndc.x + 1.0) / 2.0 * width as f32,
+        window_height as f32 - from_bottom,
+    )
+}
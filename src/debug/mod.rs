@@ -0,0 +1,5 @@
+pub mod debug_draw;
+#[cfg(all(feature = "debug-draw", debug_assertions))]
+mod debug_font;
+
+pub use self::debug_draw::*;
@@ -1,4 +1,12 @@
+// Lets the derive macros name the crate as `::engine` from inside it too
+extern crate self as engine;
+
+pub mod asset;
 pub mod core;
+pub mod debug;
 pub mod math;
+pub mod model;
+pub mod particles;
 pub mod renderer;
+pub mod tilemap;
 pub mod ui;
@@ -33,6 +33,13 @@ impl AABB {
             && point.y <= self.max.y
     }
 
+    pub fn intersects(&self, other: &AABB) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
     pub fn merge(&self, other: &AABB) -> AABB {
         let min = Vector2::new(
             f32::min(self.min.x, other.min.x),
@@ -46,6 +53,14 @@ impl AABB {
         AABB::new(min, max)
     }
 
+    pub fn min(&self) -> Vector2<f32> {
+        self.min
+    }
+
+    pub fn max(&self) -> Vector2<f32> {
+        self.max
+    }
+
     pub fn center(&self) -> Vector2<f32> {
         Vector2::new(
             (self.min.x + self.max.x) / 2.0,
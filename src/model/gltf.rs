@@ -0,0 +1,684 @@
+//! Loader for glTF 2.0 files, either JSON (.gltf) with buffers in separate .bin files or
+//! embedded as data URIs, or binary (.glb). Triangle primitives become meshes, the node
+//! hierarchy of the default scene is kept. Skins, morph targets, animations, cameras and
+//! sparse accessors are not supported.
+//!
+//! Metallic-roughness materials are approximated with Blinn-Phong parameters.
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use cgmath::*;
+use serde_json::Value;
+
+use crate::asset::{relative_to, AssetError, LoadContext};
+use crate::model::{MaterialData, MeshData, ModelData, ModelNode, VertexDeduplicator};
+use crate::renderer::{Image, MeshVertex};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON_CHUNK: u32 = 0x4E4F534A;
+const GLB_BINARY_CHUNK: u32 = 0x004E4942;
+
+const TRIANGLES: u64 = 4;
+// Accessors without a buffer view are all zeros, nothing in the file bounds their size
+const MAX_ZERO_ACCESSOR_VALUES: usize = 1 << 20;
+
+struct GltfParser<'a> {
+    context: &'a LoadContext,
+    file: &'a str,
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+    images: HashMap<usize, Image>,
+}
+
+/// Reads a .gltf or .glb file and the buffers and images it references.
+pub fn parse_gltf(path: &str, context: &LoadContext) -> Result<ModelData, AssetError> {
+    let bytes = context.read(path)?;
+    let parse_error = |message: String| AssetError::Parse {
+        path: context.resolve(path),
+        message,
+    };
+
+    let (json, binary_chunk) = if bytes.starts_with(GLB_MAGIC) {
+        split_glb(&bytes).map_err(parse_error)?
+    } else {
+        (bytes.as_slice(), None)
+    };
+    let json: Value =
+        serde_json::from_slice(json).map_err(|error| parse_error(error.to_string()))?;
+
+    let version = json["asset"]["version"].as_str().unwrap_or("");
+    if !version.starts_with('2') {
+        return Err(parse_error(format!(
+            "glTF version {} is not supported",
+            version
+        )));
+    }
+
+    let mut parser = GltfParser {
+        context,
+        file: path,
+        json,
+        buffers: Vec::new(),
+        images: HashMap::new(),
+    };
+    parser.load_buffers(binary_chunk)?;
+
+    let mut model = ModelData {
+        materials: parser.parse_materials()?,
+        ..ModelData::default()
+    };
+
+    let mut mesh_primitives = Vec::new();
+    for (index, mesh) in array(&parser.json["meshes"]).iter().enumerate() {
+        let mut primitives = Vec::new();
+        for primitive in array(&mesh["primitives"]) {
+            let mode = primitive["mode"].as_u64().unwrap_or(TRIANGLES);
+            if mode != TRIANGLES {
+                log::warn!(
+                    "{}: skipping a primitive of mesh {} that is not made of triangles",
+                    path,
+                    index
+                );
+                continue;
+            }
+
+            let mut data = parser.parse_primitive(primitive)?;
+            data.name = mesh["name"].as_str().unwrap_or("").to_string();
+            if data
+                .material
+                .is_some_and(|material| material >= model.materials.len())
+            {
+                return Err(parser.error(format!("mesh {} uses a missing material", index)));
+            }
+
+            primitives.push(model.meshes.len());
+            model.meshes.push(data);
+        }
+        mesh_primitives.push(primitives);
+    }
+
+    model.nodes = parser.parse_nodes(&mesh_primitives)?;
+    model.roots = parser.parse_roots(&model.nodes)?;
+
+    Ok(model)
+}
+
+// The JSON and binary chunks of a .glb file
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let read_u32 = |offset: usize| -> Result<u32, String> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .ok_or_else(|| "the file is truncated".to_string())
+    };
+
+    if read_u32(4)? != 2 {
+        return Err("only version 2 of the binary format is supported".to_string());
+    }
+    let length = (read_u32(8)? as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut binary = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset)? as usize;
+        let chunk_type = read_u32(offset + 4)?;
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| "a chunk goes past the end of the file".to_string())?;
+
+        match chunk_type {
+            GLB_JSON_CHUNK if json.is_none() => json = Some(chunk),
+            GLB_BINARY_CHUNK if binary.is_none() => binary = Some(chunk),
+            _ => {}
+        }
+        // Chunks are aligned to 4 bytes
+        offset += 8 + chunk_length.div_ceil(4) * 4;
+    }
+
+    Ok((json.ok_or("the file has no JSON chunk")?, binary))
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or(&[])
+}
+
+fn index(value: &Value) -> Option<usize> {
+    value.as_u64().map(|index| index as usize)
+}
+
+fn floats<const N: usize>(value: &Value, default: [f32; N]) -> [f32; N] {
+    let mut values = default;
+    if let Some(array) = value.as_array() {
+        for (value, element) in values.iter_mut().zip(array) {
+            *value = element.as_f64().unwrap_or(0.0) as f32;
+        }
+    }
+    values
+}
+
+fn decode_data_uri(uri: &str) -> Option<Result<Vec<u8>, String>> {
+    let data = uri.strip_prefix("data:")?;
+    let (_, encoded) = data.split_once(";base64,")?;
+    Some(
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|error| error.to_string()),
+    )
+}
+
+impl<'a> GltfParser<'a> {
+    fn error(&self, message: impl ToString) -> AssetError {
+        AssetError::Parse {
+            path: self.context.resolve(self.file),
+            message: message.to_string(),
+        }
+    }
+
+    fn load_buffers(&mut self, binary_chunk: Option<&[u8]>) -> Result<(), AssetError> {
+        for (index, buffer) in array(&self.json["buffers"]).iter().enumerate() {
+            let data = match buffer["uri"].as_str() {
+                // Only the first buffer of a .glb can lack a URI
+                None if index == 0 => binary_chunk
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| self.error("buffer 0 has no data"))?,
+                None => return Err(self.error(format!("buffer {} has no URI", index))),
+                Some(uri) => match decode_data_uri(uri) {
+                    Some(data) => data.map_err(|message| self.error(message))?,
+                    None => self.context.read(&relative_to(self.file, uri))?,
+                },
+            };
+
+            let length = buffer["byteLength"].as_u64().unwrap_or(0) as usize;
+            if data.len() < length {
+                return Err(self.error(format!("buffer {} is shorter than its length", index)));
+            }
+            self.buffers.push(data);
+        }
+        Ok(())
+    }
+
+    fn buffer_view(&self, view: usize) -> Result<(&[u8], Option<usize>), AssetError> {
+        let view = &self.json["bufferViews"][view];
+        let buffer = index(&view["buffer"])
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| self.error("a buffer view uses a missing buffer"))?;
+
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        let data = offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| self.error("a buffer view goes past the end of its buffer"))?;
+
+        Ok((data, index(&view["byteStride"])))
+    }
+
+    // Elements of an accessor converted to numbers, with the number of components per element
+    fn read_accessor(&self, accessor: usize) -> Result<(Vec<f64>, usize), AssetError> {
+        let accessor = &self.json["accessors"][accessor];
+        if accessor.is_null() {
+            return Err(self.error("missing accessor"));
+        }
+        if !accessor["sparse"].is_null() {
+            return Err(self.error("sparse accessors are not supported"));
+        }
+
+        let components = match accessor["type"].as_str().unwrap_or("") {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            "MAT4" => 16,
+            other => return Err(self.error(format!("accessor type {} is not supported", other))),
+        };
+        let component_type = accessor["componentType"].as_u64().unwrap_or(0);
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => return Err(self.error(format!("component type {} is not supported", other))),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+        let count = accessor["count"].as_u64().unwrap_or(0) as usize;
+        let value_count = count
+            .checked_mul(components)
+            .ok_or_else(|| self.error("an accessor has too many elements"))?;
+
+        // Accessors without a buffer view are all zeros
+        let view = match index(&accessor["bufferView"]) {
+            Some(view) => view,
+            None if value_count <= MAX_ZERO_ACCESSOR_VALUES => {
+                return Ok((vec![0.0; value_count], components))
+            }
+            None => return Err(self.error("an accessor without data has too many elements")),
+        };
+        let (data, stride) = self.buffer_view(view)?;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let element_size = components * component_size;
+        let stride = stride.unwrap_or(element_size);
+        if stride < element_size {
+            return Err(self.error("an accessor has elements overlapping each other"));
+        }
+
+        // Checked before allocating, so the count is bounded by the size of the view
+        let end = match count {
+            0 => Some(offset),
+            _ => (count - 1)
+                .checked_mul(stride)
+                .and_then(|last| last.checked_add(offset))
+                .and_then(|last| last.checked_add(element_size)),
+        };
+        if end.is_none_or(|end| end > data.len()) {
+            return Err(self.error("an accessor goes past the end of its view"));
+        }
+
+        let mut values = Vec::with_capacity(value_count);
+        for element in 0..count {
+            for component in 0..components {
+                let start = offset + element * stride + component * component_size;
+                let bytes = &data[start..start + component_size];
+
+                let value = match component_type {
+                    5120 => bytes[0] as i8 as f64,
+                    5121 => bytes[0] as f64,
+                    5122 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5123 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5125 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    _ => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                };
+                values.push(match (normalized, component_type) {
+                    (true, 5120) => (value / 127.0).max(-1.0),
+                    (true, 5121) => value / 255.0,
+                    (true, 5122) => (value / 32767.0).max(-1.0),
+                    (true, 5123) => value / 65535.0,
+                    _ => value,
+                });
+            }
+        }
+        Ok((values, components))
+    }
+
+    fn read_attribute(
+        &self,
+        primitive: &Value,
+        name: &str,
+        components: usize,
+        vertex_count: Option<usize>,
+    ) -> Result<Option<Vec<f64>>, AssetError> {
+        let accessor = match index(&primitive["attributes"][name]) {
+            Some(accessor) => accessor,
+            None => return Ok(None),
+        };
+
+        let (values, found) = self.read_accessor(accessor)?;
+        if found != components {
+            return Err(self.error(format!("{} has {} components", name, found)));
+        }
+        if vertex_count.is_some_and(|count| values.len() != count * components) {
+            return Err(self.error(format!("{} does not have one value per vertex", name)));
+        }
+        Ok(Some(values))
+    }
+
+    fn parse_primitive(&self, primitive: &Value) -> Result<MeshData, AssetError> {
+        let positions = self
+            .read_attribute(primitive, "POSITION", 3, None)?
+            .ok_or_else(|| self.error("a primitive has no positions"))?;
+        let vertex_count = positions.len() / 3;
+        let normals = self.read_attribute(primitive, "NORMAL", 3, Some(vertex_count))?;
+        let texture_coords = self.read_attribute(primitive, "TEXCOORD_0", 2, Some(vertex_count))?;
+
+        let indices: Vec<usize> = match index(&primitive["indices"]) {
+            Some(accessor) => self
+                .read_accessor(accessor)?
+                .0
+                .into_iter()
+                .map(|index| index as usize)
+                .collect(),
+            None => (0..vertex_count).collect(),
+        };
+
+        let mut builder = VertexDeduplicator::new();
+        for &vertex in &indices {
+            if vertex >= vertex_count {
+                return Err(self.error(format!("vertex index {} is out of range", vertex)));
+            }
+
+            let vector3 = |values: &[f64]| {
+                Vector3::new(
+                    values[vertex * 3] as f32,
+                    values[vertex * 3 + 1] as f32,
+                    values[vertex * 3 + 2] as f32,
+                )
+            };
+            builder.push(MeshVertex {
+                position: vector3(&positions),
+                normal: normals.as_deref().map_or(Vector3::zero(), vector3),
+                // glTF puts the origin of textures at the top
+                texture_coord: texture_coords.as_ref().map_or(Vector2::zero(), |coords| {
+                    Vector2::new(
+                        coords[vertex * 2] as f32,
+                        1.0 - coords[vertex * 2 + 1] as f32,
+                    )
+                }),
+            });
+        }
+
+        let (vertices, indices) = builder.finish();
+        let mut mesh = MeshData {
+            name: String::new(),
+            vertices,
+            indices,
+            material: index(&primitive["material"]),
+        };
+        if normals.is_none() {
+            mesh.compute_normals();
+        }
+        Ok(mesh)
+    }
+
+    fn parse_materials(&mut self) -> Result<Vec<MaterialData>, AssetError> {
+        let materials = array(&self.json["materials"]).to_vec();
+
+        let mut parsed = Vec::with_capacity(materials.len());
+        for material in &materials {
+            let pbr = &material["pbrMetallicRoughness"];
+            let [r, g, b, a] = floats(&pbr["baseColorFactor"], [1.0; 4]);
+            let metallic = pbr["metallicFactor"].as_f64().unwrap_or(1.0) as f32;
+            let roughness = pbr["roughnessFactor"].as_f64().unwrap_or(1.0) as f32;
+
+            // Metals tint their highlights, rough surfaces spread them out
+            let color = Vector3::new(r, g, b);
+            let specular =
+                Vector3::from_value(0.04).lerp(color, metallic) * (1.0 - roughness * 0.5);
+            let shininess = (2.0 / roughness.max(0.05).powi(4) - 2.0).clamp(1.0, 256.0);
+
+            let texture = match index(&pbr["baseColorTexture"]["index"]) {
+                Some(texture) => Some(self.load_texture(texture)?),
+                None => None,
+            };
+
+            parsed.push(MaterialData {
+                name: material["name"].as_str().unwrap_or("").to_string(),
+                color: Vector4::new(r, g, b, a),
+                specular,
+                shininess,
+                texture,
+            });
+        }
+        Ok(parsed)
+    }
+
+    fn load_texture(&mut self, texture: usize) -> Result<Image, AssetError> {
+        let image = index(&self.json["textures"][texture]["source"])
+            .ok_or_else(|| self.error(format!("texture {} has no image", texture)))?;
+        if let Some(decoded) = self.images.get(&image) {
+            return Ok(decoded.clone());
+        }
+
+        let source = &self.json["images"][image];
+        let (bytes, path) = if let Some(view) = index(&source["bufferView"]) {
+            (self.buffer_view(view)?.0.to_vec(), self.file.to_string())
+        } else {
+            let uri = source["uri"]
+                .as_str()
+                .ok_or_else(|| self.error(format!("image {} has no data", image)))?;
+            match decode_data_uri(uri) {
+                Some(data) => (
+                    data.map_err(|message| self.error(message))?,
+                    self.file.to_string(),
+                ),
+                None => {
+                    let path = relative_to(self.file, uri);
+                    (self.context.read(&path)?, path)
+                }
+            }
+        };
+
+        let decoded = Image::from_memory(&bytes).map_err(|source| AssetError::Image {
+            path: self.context.resolve(&path),
+            source,
+        })?;
+        self.images.insert(image, decoded.clone());
+        Ok(decoded)
+    }
+
+    fn parse_nodes(&self, mesh_primitives: &[Vec<usize>]) -> Result<Vec<ModelNode>, AssetError> {
+        let nodes = array(&self.json["nodes"]);
+
+        let mut parsed = Vec::with_capacity(nodes.len());
+        for (number, node) in nodes.iter().enumerate() {
+            let transform = if node["matrix"].is_array() {
+                // Column major like cgmath
+                let m: [f32; 16] = floats(&node["matrix"], [0.0; 16]);
+                Matrix4::new(
+                    m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10], m[11],
+                    m[12], m[13], m[14], m[15],
+                )
+            } else {
+                let [tx, ty, tz] = floats(&node["translation"], [0.0; 3]);
+                let [x, y, z, w] = floats(&node["rotation"], [0.0, 0.0, 0.0, 1.0]);
+                let [sx, sy, sz] = floats(&node["scale"], [1.0; 3]);
+                Matrix4::from_translation(Vector3::new(tx, ty, tz))
+                    * Matrix4::from(Quaternion::new(w, x, y, z))
+                    * Matrix4::from_nonuniform_scale(sx, sy, sz)
+            };
+
+            let meshes = match index(&node["mesh"]) {
+                Some(mesh) => mesh_primitives
+                    .get(mesh)
+                    .cloned()
+                    .ok_or_else(|| self.error(format!("node {} uses a missing mesh", number)))?,
+                None => Vec::new(),
+            };
+
+            let children: Vec<usize> = array(&node["children"]).iter().filter_map(index).collect();
+            if children.iter().any(|&child| child >= nodes.len()) {
+                return Err(self.error(format!("node {} has a missing child", number)));
+            }
+
+            parsed.push(ModelNode {
+                name: node["name"].as_str().unwrap_or("").to_string(),
+                transform,
+                meshes,
+                children,
+            });
+        }
+        Ok(parsed)
+    }
+
+    // Nodes of the default scene, or every node without a parent if there are no scenes
+    fn parse_roots(&self, nodes: &[ModelNode]) -> Result<Vec<usize>, AssetError> {
+        let scene = index(&self.json["scene"]).unwrap_or(0);
+        let roots: Vec<usize> = match self.json["scenes"].get(scene) {
+            Some(scene) => array(&scene["nodes"]).iter().filter_map(index).collect(),
+            None => {
+                let mut has_parent = vec![false; nodes.len()];
+                for node in nodes {
+                    for &child in &node.children {
+                        has_parent[child] = true;
+                    }
+                }
+                (0..nodes.len()).filter(|&node| !has_parent[node]).collect()
+            }
+        };
+
+        if roots.iter().any(|&root| root >= nodes.len()) {
+            return Err(self.error("the scene has a missing node"));
+        }
+        Ok(roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn resources() -> LoadContext {
+        LoadContext::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/engine_tester/resources"
+        ))
+    }
+
+    // A triangle with positions, normals and texture coordinates, each in its own view
+    fn triangle(normal_count: u64, texture_coord_count: u64) -> Value {
+        let mut bytes = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        for _ in 0..normal_count {
+            for value in [0.0f32, 0.0, 1.0] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        for _ in 0..texture_coord_count * 2 {
+            bytes.extend(0.5f32.to_le_bytes());
+        }
+        let normals_length = normal_count * 12;
+
+        json!({
+            "asset": { "version": "2.0" },
+            "meshes": [{ "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 }
+            }] }],
+            "nodes": [{ "mesh": 0 }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5126, "count": normal_count, "type": "VEC3" },
+                {
+                    "bufferView": 2,
+                    "componentType": 5126,
+                    "count": texture_coord_count,
+                    "type": "VEC2"
+                }
+            ],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": normals_length },
+                {
+                    "buffer": 0,
+                    "byteOffset": 36 + normals_length,
+                    "byteLength": texture_coord_count * 8
+                }
+            ],
+            "buffers": [{
+                "byteLength": bytes.len(),
+                "uri": format!(
+                    "data:application/octet-stream;base64,{}",
+                    base64::engine::general_purpose::STANDARD.encode(&bytes)
+                )
+            }]
+        })
+    }
+
+    fn parse_json(name: &str, json: &Value) -> Result<ModelData, AssetError> {
+        let root = std::env::temp_dir().join("engine_gltf_tests");
+        std::fs::create_dir_all(&root).unwrap();
+        let file = format!("{}.gltf", name);
+        std::fs::write(root.join(&file), json.to_string()).unwrap();
+        parse_gltf(&file, &LoadContext::new(root))
+    }
+
+    fn assert_parse_error(result: Result<ModelData, AssetError>) {
+        match result {
+            Err(AssetError::Parse { .. }) => {}
+            Err(error) => panic!("expected a parse error, got {}", error),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn parses_tree() {
+        let model = parse_gltf("models/tree.gltf", &resources()).unwrap();
+
+        assert_eq!(model.meshes.len(), 2);
+        for mesh in &model.meshes {
+            // Boxes with a normal per face share no corners
+            assert_eq!(mesh.vertices.len(), 24);
+            assert_eq!(mesh.indices.len(), 36);
+        }
+        assert_eq!(model.meshes[0].material, Some(0));
+        assert_eq!(model.meshes[1].material, Some(1));
+
+        let names: Vec<&str> = model.materials.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Bark", "Leaves"]);
+        assert_eq!(model.materials[0].color, Vector4::new(0.45, 0.3, 0.18, 1.0));
+        assert!(model.materials[0].texture.is_none());
+    }
+
+    #[test]
+    fn keeps_node_hierarchy() {
+        let model = parse_gltf("models/tree.gltf", &resources()).unwrap();
+
+        assert_eq!(model.roots, [0]);
+        assert_eq!(model.nodes[0].name, "Trunk");
+        assert_eq!(model.nodes[0].children, [1]);
+        assert_eq!(model.nodes[0].meshes, [0]);
+        assert_eq!(model.nodes[1].name, "Crown");
+        assert_eq!(model.nodes[1].meshes, [1]);
+
+        let transforms = model.global_transforms();
+        assert_eq!(transforms[0], Matrix4::identity());
+
+        // The crown sits 1.4 above the trunk, turned 45 degrees around y
+        let corner = transforms[1].transform_point(Point3::new(0.5, 0.0, 0.0));
+        let expected = Point3::new(0.5 * 0.5f32.sqrt(), 1.4, -0.5 * 0.5f32.sqrt());
+        assert!((corner - expected).magnitude() < 1e-5, "{:?}", corner);
+    }
+
+    #[test]
+    fn parses_valid_triangle() {
+        let model = parse_json("triangle", &triangle(3, 3)).unwrap();
+        assert_eq!(model.meshes[0].vertices.len(), 3);
+        assert_eq!(model.meshes[0].indices, [0, 1, 2]);
+        assert_eq!(
+            model.meshes[0].vertices[0].texture_coord,
+            Vector2::new(0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn rejects_attributes_shorter_than_positions() {
+        assert_parse_error(parse_json("short_normals", &triangle(2, 3)));
+        assert_parse_error(parse_json("short_texture_coords", &triangle(3, 1)));
+    }
+
+    #[test]
+    fn rejects_accessor_counts_larger_than_views() {
+        let mut json = triangle(3, 3);
+        json["accessors"][0]["count"] = json!(u64::MAX / 2);
+        assert_parse_error(parse_json("huge_count", &json));
+
+        let mut json = triangle(3, 3);
+        json["accessors"][0]["byteOffset"] = json!(u64::MAX - 4);
+        assert_parse_error(parse_json("huge_accessor_offset", &json));
+
+        let mut json = triangle(3, 3);
+        json["bufferViews"][0]["byteStride"] = json!(0);
+        json["accessors"][0]["count"] = json!(1u64 << 40);
+        assert_parse_error(parse_json("zero_stride", &json));
+
+        let mut json = triangle(3, 3);
+        json["accessors"][1]
+            .as_object_mut()
+            .unwrap()
+            .remove("bufferView");
+        json["accessors"][1]["count"] = json!(1u64 << 40);
+        assert_parse_error(parse_json("huge_zero_accessor", &json));
+    }
+
+    #[test]
+    fn rejects_views_past_their_buffer() {
+        let mut json = triangle(3, 3);
+        json["bufferViews"][0]["byteOffset"] = json!(u64::MAX - 8);
+        assert_parse_error(parse_json("huge_view_offset", &json));
+
+        let mut json = triangle(3, 3);
+        json["bufferViews"][2]["byteLength"] = json!(1000);
+        assert_parse_error(parse_json("long_view", &json));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod gltf;
+pub mod model;
+pub mod model_data;
+pub mod obj;
+
+pub use self::gltf::*;
+pub use self::model::*;
+pub use self::model_data::*;
+pub use self::obj::*;
@@ -0,0 +1,91 @@
+use cgmath::*;
+
+use crate::asset::{Asset, AssetError, LoadContext};
+use crate::model::{parse_gltf, parse_obj, ModelData, ModelNode};
+use crate::renderer::{Material, Mesh, Texture2D, TextureSpec};
+
+/// A mesh of a model with the index of its material in `Model::materials`.
+pub struct ModelMesh {
+    pub name: String,
+    pub mesh: Mesh,
+    pub material: Option<usize>,
+}
+
+/// Meshes, materials and node hierarchy loaded from an OBJ (.obj) or glTF (.gltf, .glb)
+/// file, drawn with `Renderer3D::draw_model`.
+pub struct Model {
+    pub meshes: Vec<ModelMesh>,
+    pub materials: Vec<Material>,
+    /// Base color texture of every material.
+    pub textures: Vec<Option<Texture2D>>,
+    pub nodes: Vec<ModelNode>,
+    pub roots: Vec<usize>,
+    global_transforms: Vec<Matrix4<f32>>,
+}
+
+impl Model {
+    /// Uploads a model read with `parse_obj` or `parse_gltf`.
+    pub fn from_data(data: ModelData) -> Self {
+        let global_transforms = data.global_transforms();
+
+        let meshes = data
+            .meshes
+            .iter()
+            .map(|mesh| ModelMesh {
+                name: mesh.name.clone(),
+                mesh: mesh.create_mesh(),
+                material: mesh.material,
+            })
+            .collect();
+
+        let materials = data
+            .materials
+            .iter()
+            .map(|material| material.to_material())
+            .collect();
+        let textures = data
+            .materials
+            .iter()
+            .map(|material| {
+                material
+                    .texture
+                    .as_ref()
+                    .map(|image| Texture2D::from_image(image, TextureSpec::default()))
+            })
+            .collect();
+
+        Model {
+            meshes,
+            materials,
+            textures,
+            nodes: data.nodes,
+            roots: data.roots,
+            global_transforms,
+        }
+    }
+
+    /// Transform of every node from model space.
+    pub fn get_global_transforms(&self) -> &[Matrix4<f32>] {
+        &self.global_transforms
+    }
+}
+
+impl Asset for Model {
+    type Data = ModelData;
+
+    fn decode(path: &str, context: &LoadContext) -> Result<ModelData, AssetError> {
+        let extension = path.rsplit('.').next().unwrap_or("").to_lowercase();
+        match extension.as_str() {
+            "obj" => parse_obj(path, context),
+            "gltf" | "glb" => parse_gltf(path, context),
+            _ => Err(AssetError::UnsupportedFormat {
+                path: context.resolve(path),
+                kind: "model",
+            }),
+        }
+    }
+
+    fn create(_path: &str, data: ModelData, _context: &LoadContext) -> Result<Self, AssetError> {
+        Ok(Model::from_data(data))
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use cgmath::*;
+
+use crate::renderer::{Image, IndexBuffer, Material, Mesh, MeshVertex, Vertex, VertexBuffer};
+
+/// Triangles of one material, ready to be uploaded.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub name: String,
+    pub vertices: Vec<MeshVertex>,
+    /// Three per triangle, counter-clockwise seen from the front.
+    pub indices: Vec<u32>,
+    /// Index in `ModelData::materials`.
+    pub material: Option<usize>,
+}
+
+pub struct MaterialData {
+    pub name: String,
+    pub color: Vector4<f32>,
+    pub specular: Vector3<f32>,
+    pub shininess: f32,
+    /// Decoded base color texture, first row at the top.
+    pub texture: Option<Image>,
+}
+
+/// Element of the scene graph of a model. Nodes place their meshes and children relative to
+/// their parent.
+#[derive(Clone, Debug)]
+pub struct ModelNode {
+    pub name: String,
+    pub transform: Matrix4<f32>,
+    /// Indices in `ModelData::meshes`.
+    pub meshes: Vec<usize>,
+    /// Indices in `ModelData::nodes`.
+    pub children: Vec<usize>,
+}
+
+/// A model read from a file, without anything on the GPU. Produced by `parse_obj` and
+/// `parse_gltf`, turned into a `Model` on the main thread.
+#[derive(Default)]
+pub struct ModelData {
+    pub meshes: Vec<MeshData>,
+    pub materials: Vec<MaterialData>,
+    pub nodes: Vec<ModelNode>,
+    /// Nodes without a parent.
+    pub roots: Vec<usize>,
+}
+
+impl Default for MaterialData {
+    fn default() -> Self {
+        let material = Material::default();
+        MaterialData {
+            name: String::new(),
+            color: material.color,
+            specular: material.specular,
+            shininess: material.shininess,
+            texture: None,
+        }
+    }
+}
+
+impl MaterialData {
+    pub fn to_material(&self) -> Material {
+        Material {
+            color: self.color,
+            specular: self.specular,
+            shininess: self.shininess,
+        }
+    }
+}
+
+impl MeshData {
+    /// Vertex and index buffers holding the mesh, the vertex buffer laid out for the "mesh"
+    /// shader.
+    pub fn create_buffers(&self) -> (VertexBuffer, IndexBuffer) {
+        let mut vertex_buffer = VertexBuffer::new_static(&self.vertices);
+        vertex_buffer.set_layout(MeshVertex::layout());
+
+        (vertex_buffer, IndexBuffer::new(self.indices.clone()))
+    }
+
+    pub fn create_mesh(&self) -> Mesh {
+        let (vertex_buffer, index_buffer) = self.create_buffers();
+        Mesh::from_buffers(vertex_buffer, index_buffer, self.vertices.len() as u32)
+    }
+
+    /// Replaces the normals with the average of the faces around every vertex, for files
+    /// that do not have any.
+    pub fn compute_normals(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.normal = Vector3::zero();
+        }
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+            let (pa, pb, pc) = (
+                self.vertices[a].position,
+                self.vertices[b].position,
+                self.vertices[c].position,
+            );
+            // Not normalized, so bigger faces weigh more
+            let normal = (pb - pa).cross(pc - pa);
+            for index in [a, b, c] {
+                self.vertices[index].normal += normal;
+            }
+        }
+
+        for vertex in &mut self.vertices {
+            if vertex.normal.magnitude2() > 0.0 {
+                vertex.normal = vertex.normal.normalize();
+            }
+        }
+    }
+}
+
+impl ModelData {
+    /// Transform of every node from model space, following the parents' transforms.
+    pub fn global_transforms(&self) -> Vec<Matrix4<f32>> {
+        let mut transforms = vec![Matrix4::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, Matrix4<f32>)> = self
+            .roots
+            .iter()
+            .map(|&root| (root, Matrix4::identity()))
+            .collect();
+
+        // Visited nodes are skipped so a cycle in a broken file cannot hang the loader
+        let mut visited = vec![false; self.nodes.len()];
+        while let Some((index, parent)) = stack.pop() {
+            if visited[index] {
+                continue;
+            }
+            visited[index] = true;
+
+            let node = &self.nodes[index];
+            transforms[index] = parent * node.transform;
+            stack.extend(
+                node.children
+                    .iter()
+                    .map(|&child| (child, transforms[index])),
+            );
+        }
+
+        transforms
+    }
+}
+
+/// Builds an indexed vertex list, giving identical vertices the same index.
+#[derive(Default)]
+pub struct VertexDeduplicator {
+    vertices: Vec<MeshVertex>,
+    indices: Vec<u32>,
+    lookup: HashMap<[u32; 8], u32>,
+}
+
+impl VertexDeduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the index of `vertex`, adding it if no identical vertex was added before.
+    pub fn push(&mut self, vertex: MeshVertex) {
+        let key = [
+            vertex.position.x,
+            vertex.position.y,
+            vertex.position.z,
+            vertex.normal.x,
+            vertex.normal.y,
+            vertex.normal.z,
+            vertex.texture_coord.x,
+            vertex.texture_coord.y,
+        ]
+        // -0.0 and 0.0 are the same vertex
+        .map(|component| (component + 0.0).to_bits());
+
+        let vertices = &mut self.vertices;
+        let index = *self.lookup.entry(key).or_insert_with(|| {
+            vertices.push(vertex);
+            vertices.len() as u32 - 1
+        });
+        self.indices.push(index);
+    }
+
+    pub fn get_vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn get_index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn finish(self) -> (Vec<MeshVertex>, Vec<u32>) {
+        (self.vertices, self.indices)
+    }
+}
@@ -0,0 +1,376 @@
+//! Loader for Wavefront OBJ files and the MTL material libraries they reference. Polygons
+//! are triangulated as fans, lines, points, curves and smoothing groups are ignored.
+//!
+//! Every object (`o`) or group (`g`) becomes a node, split into one mesh per material.
+
+use std::collections::HashMap;
+
+use cgmath::*;
+
+use crate::asset::{relative_to, AssetError, LoadContext};
+use crate::model::{MaterialData, MeshData, ModelData, ModelNode, VertexDeduplicator};
+use crate::renderer::{Image, MeshVertex};
+
+struct ObjParser<'a> {
+    context: &'a LoadContext,
+    file: &'a str,
+
+    positions: Vec<Vector3<f32>>,
+    texture_coords: Vec<Vector2<f32>>,
+    normals: Vec<Vector3<f32>>,
+
+    model: ModelData,
+    material_lookup: HashMap<String, usize>,
+
+    // Mesh being built
+    builder: VertexDeduplicator,
+    material: Option<usize>,
+    missing_normals: bool,
+    node: ModelNode,
+}
+
+/// Reads an OBJ file and its material libraries.
+pub fn parse_obj(path: &str, context: &LoadContext) -> Result<ModelData, AssetError> {
+    let text = context.read_to_string(path)?;
+
+    let mut parser = ObjParser {
+        context,
+        file: path,
+
+        positions: Vec::new(),
+        texture_coords: Vec::new(),
+        normals: Vec::new(),
+
+        model: ModelData::default(),
+        material_lookup: HashMap::new(),
+
+        builder: VertexDeduplicator::new(),
+        material: None,
+        missing_normals: false,
+        node: new_node(""),
+    };
+
+    for (number, line) in text.lines().enumerate() {
+        parser
+            .parse_line(line)
+            .map_err(|message| parser.error(path, format!("line {}: {}", number + 1, message)))?;
+    }
+    parser.finish_node();
+
+    Ok(parser.model)
+}
+
+fn new_node(name: &str) -> ModelNode {
+    ModelNode {
+        name: name.to_string(),
+        transform: Matrix4::identity(),
+        meshes: Vec::new(),
+        children: Vec::new(),
+    }
+}
+
+fn parse_floats<const N: usize>(arguments: &[&str]) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+    for (index, value) in values.iter_mut().enumerate() {
+        let argument = arguments
+            .get(index)
+            .ok_or_else(|| format!("expected {} numbers", N))?;
+        *value = argument
+            .parse()
+            .map_err(|_| format!("{} is not a number", argument))?;
+    }
+    Ok(values)
+}
+
+// OBJ indices start at 1, negative ones count back from the last element
+fn resolve_index(text: &str, count: usize) -> Result<usize, String> {
+    let index: i64 = text
+        .parse()
+        .map_err(|_| format!("{} is not an index", text))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} is out of range", index));
+    }
+    Ok(resolved as usize)
+}
+
+impl<'a> ObjParser<'a> {
+    fn error(&self, file: &str, message: impl ToString) -> AssetError {
+        AssetError::Parse {
+            path: self.context.resolve(file),
+            message: message.to_string(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats(&arguments)?;
+                self.positions.push(Vector3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parse_floats(&arguments)?;
+                self.texture_coords.push(Vector2::new(u, v));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats(&arguments)?;
+                self.normals.push(Vector3::new(x, y, z));
+            }
+            "f" => self.parse_face(&arguments)?,
+            "o" | "g" => {
+                self.finish_node();
+                self.node = new_node(&arguments.join(" "));
+            }
+            "usemtl" => {
+                self.finish_mesh();
+                let name = arguments.join(" ");
+                self.material = Some(
+                    *self
+                        .material_lookup
+                        .get(&name)
+                        .ok_or_else(|| format!("unknown material {}", name))?,
+                );
+            }
+            "mtllib" => {
+                for library in arguments {
+                    let library = relative_to(self.file, library);
+                    self.parse_material_library(&library)
+                        .map_err(|error| error.to_string())?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_face(&mut self, arguments: &[&str]) -> Result<(), String> {
+        if arguments.len() < 3 {
+            return Err("faces need at least 3 vertices".to_string());
+        }
+
+        let corners = arguments
+            .iter()
+            .map(|corner| self.parse_corner(corner))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for index in 1..corners.len() - 1 {
+            for corner in [&corners[0], &corners[index], &corners[index + 1]] {
+                self.builder.push(*corner);
+            }
+        }
+        Ok(())
+    }
+
+    // A face vertex written as v, v/vt, v//vn or v/vt/vn
+    fn parse_corner(&mut self, text: &str) -> Result<MeshVertex, String> {
+        let mut parts = text.split('/');
+
+        let position = resolve_index(parts.next().unwrap_or(""), self.positions.len())?;
+        let texture_coord = match parts.next() {
+            Some(part) if !part.is_empty() => {
+                self.texture_coords[resolve_index(part, self.texture_coords.len())?]
+            }
+            _ => Vector2::zero(),
+        };
+        let normal = match parts.next() {
+            Some(part) if !part.is_empty() => {
+                self.normals[resolve_index(part, self.normals.len())?]
+            }
+            _ => {
+                self.missing_normals = true;
+                Vector3::zero()
+            }
+        };
+
+        Ok(MeshVertex {
+            position: self.positions[position],
+            normal,
+            texture_coord,
+        })
+    }
+
+    fn finish_mesh(&mut self) {
+        if self.builder.get_index_count() == 0 {
+            return;
+        }
+
+        let (vertices, indices) = std::mem::take(&mut self.builder).finish();
+        let mut mesh = MeshData {
+            name: self.node.name.clone(),
+            vertices,
+            indices,
+            material: self.material,
+        };
+        if self.missing_normals {
+            mesh.compute_normals();
+        }
+        self.missing_normals = false;
+
+        self.node.meshes.push(self.model.meshes.len());
+        self.model.meshes.push(mesh);
+    }
+
+    fn finish_node(&mut self) {
+        self.finish_mesh();
+
+        let node = std::mem::replace(&mut self.node, new_node(""));
+        if !node.meshes.is_empty() {
+            self.model.roots.push(self.model.nodes.len());
+            self.model.nodes.push(node);
+        }
+    }
+
+    fn parse_material_library(&mut self, file: &str) -> Result<(), AssetError> {
+        let text = self.context.read_to_string(file)?;
+
+        let mut current: Option<MaterialData> = None;
+        for (number, line) in text.lines().enumerate() {
+            let error =
+                |message: String| self.error(file, format!("line {}: {}", number + 1, message));
+
+            let line = line.split('#').next().unwrap_or("");
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let arguments: Vec<&str> = tokens.collect();
+
+            if keyword == "newmtl" {
+                if let Some(material) = current.take() {
+                    self.add_material(material);
+                }
+                current = Some(MaterialData {
+                    name: arguments.join(" "),
+                    ..MaterialData::default()
+                });
+                continue;
+            }
+
+            let material = match current.as_mut() {
+                Some(material) => material,
+                None => continue,
+            };
+            match keyword {
+                "Kd" => {
+                    let [r, g, b] = parse_floats(&arguments).map_err(error)?;
+                    material.color = Vector4::new(r, g, b, material.color.w);
+                }
+                "Ks" => {
+                    let [r, g, b] = parse_floats(&arguments).map_err(error)?;
+                    material.specular = Vector3::new(r, g, b);
+                }
+                "Ns" => material.shininess = parse_floats::<1>(&arguments).map_err(error)?[0],
+                "d" => material.color.w = parse_floats::<1>(&arguments).map_err(error)?[0],
+                "Tr" => material.color.w = 1.0 - parse_floats::<1>(&arguments).map_err(error)?[0],
+                "map_Kd" => {
+                    // Options like -s come before the file name
+                    let texture = arguments
+                        .last()
+                        .ok_or_else(|| error("missing file".into()))?;
+                    material.texture = Some(self.load_image(&relative_to(file, texture))?);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(material) = current {
+            self.add_material(material);
+        }
+        Ok(())
+    }
+
+    fn add_material(&mut self, material: MaterialData) {
+        self.material_lookup
+            .insert(material.name.clone(), self.model.materials.len());
+        self.model.materials.push(material);
+    }
+
+    fn load_image(&self, file: &str) -> Result<Image, AssetError> {
+        let bytes = self.context.read(file)?;
+        Image::from_memory(&bytes).map_err(|source| AssetError::Image {
+            path: self.context.resolve(file),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_pyramid() -> ModelData {
+        let context = LoadContext::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/engine_tester/resources"
+        ));
+        parse_obj("models/pyramid.obj", &context).unwrap()
+    }
+
+    #[test]
+    fn parses_pyramid() {
+        let model = parse_pyramid();
+
+        assert_eq!(model.meshes.len(), 2);
+        // The sides share the apex position but not its normal
+        let sides = &model.meshes[0];
+        assert_eq!(sides.vertices.len(), 12);
+        assert_eq!(sides.indices.len(), 12);
+        // The base quad is two triangles sharing a diagonal
+        let base = &model.meshes[1];
+        assert_eq!(base.vertices.len(), 4);
+        assert_eq!(base.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(base.vertices[0].normal, Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn parses_materials() {
+        let model = parse_pyramid();
+
+        let names: Vec<&str> = model.materials.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Stone", "Base"]);
+        assert_eq!(model.meshes[0].material, Some(0));
+        assert_eq!(model.meshes[1].material, Some(1));
+
+        let stone = &model.materials[0];
+        assert_eq!(stone.color, Vector4::new(1.0, 0.9, 0.8, 1.0));
+        assert_eq!(stone.specular, Vector3::new(0.3, 0.3, 0.3));
+        assert_eq!(stone.shininess, 24.0);
+        assert!(stone.texture.is_some());
+        assert!(model.materials[1].texture.is_none());
+    }
+
+    #[test]
+    fn makes_a_node_per_object() {
+        let model = parse_pyramid();
+
+        assert_eq!(model.nodes.len(), 1);
+        assert_eq!(model.nodes[0].name, "Pyramid");
+        assert_eq!(model.nodes[0].meshes, [0, 1]);
+        assert_eq!(model.roots, [0]);
+        assert_eq!(model.global_transforms(), [Matrix4::identity()]);
+    }
+
+    #[test]
+    fn resolves_negative_indices() {
+        assert_eq!(resolve_index("1", 3), Ok(0));
+        assert_eq!(resolve_index("-1", 3), Ok(2));
+        assert!(resolve_index("0", 3).is_err());
+        assert!(resolve_index("4", 3).is_err());
+        assert!(resolve_index("-4", 3).is_err());
+    }
+}
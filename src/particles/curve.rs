@@ -0,0 +1,213 @@
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+/// Values a `Curve` can blend between.
+pub trait Interpolate: Copy {
+    fn interpolate(from: Self, to: Self, amount: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(from: f32, to: f32, amount: f32) -> f32 {
+        from + (to - from) * amount
+    }
+}
+
+impl Interpolate for Vector2<f32> {
+    fn interpolate(from: Self, to: Self, amount: f32) -> Self {
+        from.lerp(to, amount)
+    }
+}
+
+impl Interpolate for Vector4<f32> {
+    fn interpolate(from: Self, to: Self, amount: f32) -> Self {
+        from.lerp(to, amount)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurveKey<T> {
+    pub time: f32,
+    pub value: T,
+}
+
+/// Value changing over normalized time, 0 to 1, made of linearly interpolated keys. Before
+/// the first key and after the last one the curve holds their value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "CurveKeys<T>",
+    bound(deserialize = "T: Interpolate + Deserialize<'de>")
+)]
+pub struct Curve<T> {
+    keys: Vec<CurveKey<T>>,
+}
+
+// Keys as written in a file, which may be out of order
+#[derive(Deserialize)]
+struct CurveKeys<T> {
+    keys: Vec<CurveKey<T>>,
+}
+
+impl<T: Interpolate> From<CurveKeys<T>> for Curve<T> {
+    fn from(curve: CurveKeys<T>) -> Self {
+        Curve::from_keys(curve.keys)
+    }
+}
+
+impl<T: Interpolate> Curve<T> {
+    pub fn constant(value: T) -> Self {
+        Curve {
+            keys: vec![CurveKey { time: 0.0, value }],
+        }
+    }
+
+    pub fn linear(from: T, to: T) -> Self {
+        Curve {
+            keys: vec![
+                CurveKey {
+                    time: 0.0,
+                    value: from,
+                },
+                CurveKey {
+                    time: 1.0,
+                    value: to,
+                },
+            ],
+        }
+    }
+
+    /// Keys are sorted by time, they do not have to be given in order.
+    pub fn from_keys(mut keys: Vec<CurveKey<T>>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Curve { keys }
+    }
+
+    pub fn add_key(&mut self, time: f32, value: T) {
+        let index = self.keys.partition_point(|key| key.time <= time);
+        self.keys.insert(index, CurveKey { time, value });
+    }
+
+    pub fn get_keys(&self) -> &[CurveKey<T>] {
+        &self.keys
+    }
+
+    /// # Panics
+    ///
+    /// If the curve has no keys, which only a hand written definition can produce.
+    pub fn evaluate(&self, time: f32) -> T {
+        let next = self.keys.partition_point(|key| key.time <= time);
+
+        if next == 0 {
+            return self.keys[0].value;
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].value;
+        }
+
+        let from = &self.keys[next - 1];
+        let to = &self.keys[next];
+        let amount = (time - from.time) / (to.time - from.time);
+
+        T::interpolate(from.value, to.value, amount)
+    }
+}
+
+/// Value picked at random between `min` and `max` for every particle.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RandomRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl RandomRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        RandomRange { min, max }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn sample(&self, rng: &mut fastrand::Rng) -> f32 {
+        self.min + (self.max - self.min) * rng.f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_between_keys() {
+        let curve = Curve::from_keys(vec![
+            CurveKey {
+                time: 0.2,
+                value: 1.0,
+            },
+            CurveKey {
+                time: 0.6,
+                value: 3.0,
+            },
+            CurveKey {
+                time: 1.0,
+                value: -1.0,
+            },
+        ]);
+
+        assert_eq!(curve.evaluate(0.4), 2.0);
+        assert_eq!(curve.evaluate(0.6), 3.0);
+        assert_eq!(curve.evaluate(0.8), 1.0);
+    }
+
+    #[test]
+    fn holds_the_end_values() {
+        let curve = Curve::linear(2.0, 4.0);
+        assert_eq!(curve.evaluate(-1.0), 2.0);
+        assert_eq!(curve.evaluate(0.0), 2.0);
+        assert_eq!(curve.evaluate(1.0), 4.0);
+        assert_eq!(curve.evaluate(5.0), 4.0);
+
+        assert_eq!(Curve::constant(7.0).evaluate(0.5), 7.0);
+    }
+
+    #[test]
+    fn interpolates_vectors() {
+        let curve = Curve::linear(Vector2::new(0.0, 1.0), Vector2::new(2.0, 3.0));
+        assert_eq!(curve.evaluate(0.5), Vector2::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn keeps_keys_sorted() {
+        let mut curve = Curve::from_keys(vec![
+            CurveKey {
+                time: 1.0,
+                value: 1.0,
+            },
+            CurveKey {
+                time: 0.0,
+                value: 0.0,
+            },
+        ]);
+        curve.add_key(0.5, 4.0);
+
+        let times: Vec<f32> = curve.get_keys().iter().map(|key| key.time).collect();
+        assert_eq!(times, [0.0, 0.5, 1.0]);
+        assert_eq!(curve.evaluate(0.25), 2.0);
+    }
+
+    #[test]
+    fn sorts_keys_read_from_json() {
+        let json = r#"{ "keys": [
+            { "time": 1.0, "value": 10.0 },
+            { "time": 0.0, "value": 0.0 },
+            { "time": 0.5, "value": 2.0 }
+        ] }"#;
+        let curve: Curve<f32> = serde_json::from_str(json).unwrap();
+
+        let times: Vec<f32> = curve.get_keys().iter().map(|key| key.time).collect();
+        assert_eq!(times, [0.0, 0.5, 1.0]);
+        assert_eq!(curve.evaluate(0.25), 1.0);
+
+        let written = serde_json::to_string(&curve).unwrap();
+        assert_eq!(serde_json::from_str::<Curve<f32>>(&written).unwrap(), curve);
+    }
+}
@@ -0,0 +1,192 @@
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+use crate::asset::{Asset, AssetError, LoadContext};
+use crate::particles::{Curve, RandomRange};
+use crate::renderer::{BlendMode, SubTexture};
+
+/// Where particles live once spawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimulationSpace {
+    /// Particles follow the emitter when it moves or rotates.
+    Local,
+    /// Particles stay where they were spawned, leaving a trail behind a moving emitter.
+    World,
+}
+
+/// Area new particles appear in, centered on the emitter.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EmitterShape {
+    Point,
+    Circle { radius: f32 },
+    Rectangle { size: Vector2<f32> },
+}
+
+/// A number of particles spawned at once, `time` seconds into every cycle of the emitter.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Burst {
+    pub time: f32,
+    pub count: u32,
+}
+
+/// Describes how an emitter spawns and animates its particles. Definitions are usually kept
+/// in JSON files loaded through the `AssetServer`, fields missing from a file keep their
+/// default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmitterConfig {
+    /// Particles alive at the same time, spawning stops while the limit is reached.
+    pub max_particles: u32,
+    /// Length of one cycle in seconds.
+    pub duration: f32,
+    /// Starts a new cycle when one ends, otherwise the emitter stops.
+    pub looping: bool,
+    /// Particles per second.
+    pub spawn_rate: f32,
+    pub bursts: Vec<Burst>,
+    pub shape: EmitterShape,
+    pub space: SimulationSpace,
+
+    /// Seconds a particle lives.
+    pub lifetime: RandomRange,
+    /// Emission direction in degrees, counter-clockwise from the x axis and relative to the
+    /// emitter's rotation.
+    pub direction: f32,
+    /// Particles leave within `spread` degrees either side of `direction`.
+    pub spread: f32,
+    pub speed: RandomRange,
+    /// Added to the velocity every second, e.g. gravity.
+    pub acceleration: Vector2<f32>,
+    /// Fraction of the velocity lost every second.
+    pub drag: f32,
+
+    /// Starting rotation in degrees.
+    pub rotation: RandomRange,
+    /// Degrees per second.
+    pub angular_velocity: RandomRange,
+
+    /// Multiplies `size_over_lifetime`.
+    pub start_size: RandomRange,
+    pub size_over_lifetime: Curve<Vector2<f32>>,
+    pub color_over_lifetime: Curve<Vector4<f32>>,
+
+    /// Path of the particle texture. The emitter only records it, drawing uses the texture
+    /// passed to `Renderer::draw_particles`.
+    pub texture: Option<String>,
+    /// Part of the texture to draw, the whole texture if none.
+    pub sub_texture: Option<SubTexture>,
+    /// How particles combine with what is behind them, e.g. `Additive` for fire and sparks.
+    pub blend_mode: BlendMode,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        EmitterConfig {
+            max_particles: 1000,
+            duration: 1.0,
+            looping: true,
+            spawn_rate: 10.0,
+            bursts: Vec::new(),
+            shape: EmitterShape::Point,
+            space: SimulationSpace::World,
+
+            lifetime: RandomRange::constant(1.0),
+            direction: 90.0,
+            spread: 0.0,
+            speed: RandomRange::constant(1.0),
+            acceleration: Vector2::zero(),
+            drag: 0.0,
+
+            rotation: RandomRange::constant(0.0),
+            angular_velocity: RandomRange::constant(0.0),
+
+            start_size: RandomRange::constant(1.0),
+            size_over_lifetime: Curve::constant(Vector2::new(0.1, 0.1)),
+            color_over_lifetime: Curve::constant(Vector4::new(1.0, 1.0, 1.0, 1.0)),
+
+            texture: None,
+            sub_texture: None,
+            blend_mode: BlendMode::Alpha,
+        }
+    }
+}
+
+impl EmitterConfig {
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let config: EmitterConfig =
+            serde_json::from_str(text).map_err(|error| error.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("emitter configs always serialize")
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.size_over_lifetime.get_keys().is_empty() {
+            return Err("size_over_lifetime has no keys".to_string());
+        }
+        if self.color_over_lifetime.get_keys().is_empty() {
+            return Err("color_over_lifetime has no keys".to_string());
+        }
+        if self.duration <= 0.0 {
+            return Err("duration must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Asset for EmitterConfig {
+    type Data = EmitterConfig;
+
+    fn decode(path: &str, context: &LoadContext) -> Result<Self, AssetError> {
+        let text = context.read_to_string(path)?;
+        EmitterConfig::from_json(&text).map_err(|message| AssetError::Parse {
+            path: context.resolve(path),
+            message,
+        })
+    }
+
+    fn create(_path: &str, config: Self, _context: &LoadContext) -> Result<Self, AssetError> {
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRE: &str = include_str!("../../engine_tester/resources/fire.json");
+
+    #[test]
+    fn reads_fire() {
+        let config = EmitterConfig::from_json(FIRE).unwrap();
+        assert_eq!(config.max_particles, 500);
+        assert_eq!(config.shape, EmitterShape::Circle { radius: 0.2 });
+        assert_eq!(config.blend_mode, BlendMode::Additive);
+        assert_eq!(config.color_over_lifetime.get_keys().len(), 3);
+        // Missing fields keep their default
+        assert_eq!(config.texture, None);
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let config = EmitterConfig::from_json(FIRE).unwrap();
+        assert_eq!(EmitterConfig::from_json(&config.to_json()).unwrap(), config);
+        assert_eq!(
+            EmitterConfig::from_json("{}").unwrap(),
+            EmitterConfig::default()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        assert!(EmitterConfig::from_json(r#"{ "duration": 0.0 }"#).is_err());
+        assert!(EmitterConfig::from_json(r#"{ "duration": -2.0 }"#).is_err());
+        assert!(EmitterConfig::from_json(r#"{ "size_over_lifetime": { "keys": [] } }"#).is_err());
+        assert!(EmitterConfig::from_json(r#"{ "color_over_lifetime": { "keys": [] } }"#).is_err());
+        assert!(EmitterConfig::from_json(r#"{ "max_particles": "many" }"#).is_err());
+        assert!(EmitterConfig::from_json("[").is_err());
+    }
+}
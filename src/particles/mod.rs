@@ -0,0 +1,7 @@
+pub mod curve;
+pub mod emitter_config;
+pub mod particle_emitter;
+
+pub use self::curve::*;
+pub use self::emitter_config::*;
+pub use self::particle_emitter::*;
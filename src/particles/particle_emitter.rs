@@ -0,0 +1,282 @@
+use cgmath::*;
+
+use crate::particles::{EmitterConfig, EmitterShape, SimulationSpace};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    /// In world space, or relative to the emitter for `SimulationSpace::Local`.
+    pub position: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    /// Multiplier of the emitter's size curve, picked at spawn.
+    pub size: f32,
+}
+
+impl Particle {
+    /// Fraction of its lifetime the particle has lived, 0 to 1.
+    pub fn get_progress(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Spawns and simulates particles as described by an `EmitterConfig`. Call `update` once a
+/// frame with `GameEngine::timestep` and draw it with `Renderer::draw_particles`.
+pub struct ParticleEmitter {
+    /// Changes apply to particles spawned afterwards, and to the color and size of live ones.
+    pub config: EmitterConfig,
+    pub position: Vector2<f32>,
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+
+    particles: Vec<Particle>,
+    time: f32, // into the current cycle
+    spawn_accumulator: f32,
+    playing: bool,
+    rng: fastrand::Rng,
+}
+
+impl ParticleEmitter {
+    pub fn new(config: EmitterConfig) -> Self {
+        ParticleEmitter {
+            config,
+            position: Vector2::zero(),
+            rotation: 0.0,
+
+            particles: Vec::new(),
+            time: 0.0,
+            spawn_accumulator: 0.0,
+            playing: true,
+            rng: fastrand::Rng::new(),
+        }
+    }
+
+    /// Makes the emitter spawn the same particles every run, e.g. for replays.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    /// Starts a new cycle. Live particles are kept.
+    pub fn play(&mut self) {
+        self.playing = true;
+        self.time = 0.0;
+        self.spawn_accumulator = 0.0;
+    }
+
+    /// Stops spawning, live particles finish their lifetime.
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Stopped, and every particle is gone.
+    pub fn is_finished(&self) -> bool {
+        !self.playing && self.particles.is_empty()
+    }
+
+    pub fn get_particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn get_particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Spawns `count` particles right away, up to `max_particles`, whether the emitter is
+    /// playing or not.
+    pub fn emit(&mut self, count: u32) {
+        for _ in 0..count {
+            if self.particles.len() >= self.config.max_particles as usize {
+                break;
+            }
+            let particle = self.spawn_particle();
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn update(&mut self, timestep: f32) {
+        self.simulate(timestep);
+
+        if self.playing {
+            self.spawn(timestep);
+        }
+    }
+
+    fn simulate(&mut self, timestep: f32) {
+        let acceleration = self.config.acceleration * timestep;
+        let damping = (1.0 - self.config.drag * timestep).max(0.0);
+
+        self.particles.retain_mut(|particle| {
+            particle.age += timestep;
+            if particle.age >= particle.lifetime {
+                return false;
+            }
+
+            particle.velocity = (particle.velocity + acceleration) * damping;
+            particle.position += particle.velocity * timestep;
+            particle.rotation += particle.angular_velocity * timestep;
+            true
+        });
+    }
+
+    fn spawn(&mut self, timestep: f32) {
+        self.spawn_accumulator += self.config.spawn_rate * timestep;
+        let count = self.spawn_accumulator.floor();
+        self.spawn_accumulator -= count;
+        self.emit(count as u32);
+
+        let mut from = self.time;
+        self.time += timestep;
+
+        // A long frame can span several cycles
+        loop {
+            let to = self.time.min(self.config.duration);
+            self.emit_bursts(from, to);
+
+            if self.time < self.config.duration {
+                break;
+            }
+            // Configs built in code are not validated, a cycle that takes no time would
+            // otherwise repeat forever
+            let duration = self.config.duration;
+            if !self.config.looping || duration <= 0.0 || duration.is_nan() {
+                self.playing = false;
+                break;
+            }
+
+            self.time -= self.config.duration;
+            from = 0.0;
+        }
+    }
+
+    fn emit_bursts(&mut self, from: f32, to: f32) {
+        let counts: Vec<u32> = self
+            .config
+            .bursts
+            .iter()
+            .filter(|burst| burst.time >= from && burst.time < to)
+            .map(|burst| burst.count)
+            .collect();
+
+        for count in counts {
+            self.emit(count);
+        }
+    }
+
+    fn spawn_particle(&mut self) -> Particle {
+        let config = &self.config;
+        let rng = &mut self.rng;
+
+        let offset = match config.shape {
+            EmitterShape::Point => Vector2::zero(),
+            EmitterShape::Circle { radius } => {
+                // sqrt keeps the points evenly spread over the area
+                let distance = radius * rng.f32().sqrt();
+                let angle = rng.f32() * std::f32::consts::TAU;
+                Vector2::new(angle.cos(), angle.sin()) * distance
+            }
+            EmitterShape::Rectangle { size } => {
+                Vector2::new(rng.f32() - 0.5, rng.f32() - 0.5).mul_element_wise(size)
+            }
+        };
+
+        let angle = (config.direction + config.spread * (rng.f32() * 2.0 - 1.0)).to_radians();
+        let velocity = Vector2::new(angle.cos(), angle.sin()) * config.speed.sample(rng);
+
+        let mut particle = Particle {
+            position: offset,
+            velocity,
+            rotation: config.rotation.sample(rng).to_radians(),
+            angular_velocity: config.angular_velocity.sample(rng).to_radians(),
+            age: 0.0,
+            lifetime: config.lifetime.sample(rng).max(f32::EPSILON),
+            size: config.start_size.sample(rng),
+        };
+
+        if config.space == SimulationSpace::World {
+            let rotation = Basis2::from_angle(Rad(self.rotation));
+            particle.position = self.position + rotation.rotate_vector(particle.position);
+            particle.velocity = rotation.rotate_vector(particle.velocity);
+            particle.rotation += self.rotation;
+        }
+
+        particle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particles::{Burst, RandomRange};
+
+    fn burst_emitter(looping: bool) -> ParticleEmitter {
+        ParticleEmitter::new(EmitterConfig {
+            duration: 1.0,
+            looping,
+            spawn_rate: 0.0,
+            bursts: vec![
+                Burst {
+                    time: 0.0,
+                    count: 3,
+                },
+                Burst {
+                    time: 0.5,
+                    count: 2,
+                },
+            ],
+            lifetime: RandomRange::constant(100.0),
+            ..EmitterConfig::default()
+        })
+    }
+
+    #[test]
+    fn emits_bursts_once_per_cycle() {
+        let mut emitter = burst_emitter(true);
+        emitter.update(0.25);
+        assert_eq!(emitter.get_particle_count(), 3);
+        emitter.update(0.5);
+        assert_eq!(emitter.get_particle_count(), 5);
+
+        // Wraps into the next cycle, whose first burst is at its start
+        emitter.update(0.5);
+        assert_eq!(emitter.get_particle_count(), 8);
+
+        // From 0.25 into a cycle to 0.25 into the one after the next
+        emitter.update(2.0);
+        assert_eq!(emitter.get_particle_count(), 18);
+        assert!(emitter.is_playing());
+    }
+
+    #[test]
+    fn stops_at_the_end_of_a_single_cycle() {
+        let mut emitter = burst_emitter(false);
+        emitter.update(3.0);
+        assert_eq!(emitter.get_particle_count(), 5);
+        assert!(!emitter.is_playing());
+
+        emitter.update(1.0);
+        assert_eq!(emitter.get_particle_count(), 5);
+    }
+
+    #[test]
+    fn stops_after_a_cycle_without_duration() {
+        for duration in [0.0, -1.0, f32::NAN] {
+            let mut emitter = ParticleEmitter::new(EmitterConfig {
+                duration,
+                ..EmitterConfig::default()
+            });
+            emitter.update(0.5);
+            assert!(!emitter.is_playing());
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use gl::types::GLenum;
+use serde::{Deserialize, Serialize};
+
+/// How drawn colors combine with what is already on the render target. Colors are expected
+/// straight, i.e. not multiplied by their alpha, except with `Premultiplied`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Covers what is behind by the alpha of the color.
+    #[default]
+    Alpha,
+    /// Like `Alpha` for colors already multiplied by their alpha, e.g. textures exported
+    /// premultiplied.
+    Premultiplied,
+    /// Adds the color weighted by its alpha, brightening what is behind. For fire, sparks and
+    /// glows.
+    Additive,
+    /// Multiplies what is behind by the color, darkening it. Partly transparent pixels only
+    /// blend right with premultiplied colors.
+    Multiply,
+    /// Inverse of `Multiply`, brightening what is behind without going over white. Ignores
+    /// alpha, black leaves what is behind unchanged.
+    Screen,
+    /// Replaces what is behind, alpha included.
+    Opaque,
+}
+
+impl BlendMode {
+    /// Source and destination factors of the color, then of the alpha. Alpha is accumulated
+    /// like a premultiplied color so offscreen targets can be composited over others.
+    pub(crate) fn get_factors(&self) -> [GLenum; 4] {
+        match self {
+            BlendMode::Alpha => [
+                gl::SRC_ALPHA,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+            ],
+            BlendMode::Premultiplied => [
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+            ],
+            BlendMode::Additive => [gl::SRC_ALPHA, gl::ONE, gl::ZERO, gl::ONE],
+            BlendMode::Multiply => [gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE],
+            BlendMode::Screen => [gl::ONE, gl::ONE_MINUS_SRC_COLOR, gl::ZERO, gl::ONE],
+            BlendMode::Opaque => [gl::ONE, gl::ZERO, gl::ONE, gl::ZERO],
+        }
+    }
+}
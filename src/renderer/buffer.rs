@@ -2,6 +2,9 @@ use gl::types::*;
 use std::ffi::c_void;
 use std::mem;
 use std::ptr::null;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use crate::renderer::{track_create, track_delete, GpuResourceKind};
 
 #[derive(Clone, Copy, Debug)]
 pub enum ShaderDataType {
@@ -18,19 +21,33 @@ pub enum ShaderDataType {
     Bool,
 }
 
-fn shader_data_type_size(data_type: &ShaderDataType) -> u32 {
-    match data_type {
-        ShaderDataType::Float => 4,
-        ShaderDataType::Float2 => 4 * 2,
-        ShaderDataType::Float3 => 4 * 3,
-        ShaderDataType::Float4 => 4 * 4,
-        ShaderDataType::Mat3 => 4 * 3 * 3,
-        ShaderDataType::Mat4 => 4 * 4 * 4,
-        ShaderDataType::Int => 4,
-        ShaderDataType::Int2 => 4 * 2,
-        ShaderDataType::Int3 => 4 * 3,
-        ShaderDataType::Int4 => 4 * 4,
-        ShaderDataType::Bool => 1,
+impl ShaderDataType {
+    /// Bytes the value takes in a vertex.
+    pub const fn get_size(self) -> u32 {
+        match self {
+            ShaderDataType::Float => 4,
+            ShaderDataType::Float2 => 4 * 2,
+            ShaderDataType::Float3 => 4 * 3,
+            ShaderDataType::Float4 => 4 * 4,
+            ShaderDataType::Mat3 => 4 * 3 * 3,
+            ShaderDataType::Mat4 => 4 * 4 * 4,
+            ShaderDataType::Int => 4,
+            ShaderDataType::Int2 => 4 * 2,
+            ShaderDataType::Int3 => 4 * 3,
+            ShaderDataType::Int4 => 4 * 4,
+            ShaderDataType::Bool => 1,
+        }
+    }
+
+    pub const fn is_integer(self) -> bool {
+        matches!(
+            self,
+            ShaderDataType::Int
+                | ShaderDataType::Int2
+                | ShaderDataType::Int3
+                | ShaderDataType::Int4
+                | ShaderDataType::Bool
+        )
     }
 }
 
@@ -40,20 +57,28 @@ pub struct BufferElement {
     pub size: u32,
     pub offset: u32,
     pub normalized: bool,
+    /// Read as integers by the shader instead of being converted to floats.
+    pub integer: bool,
 }
 
 impl BufferElement {
     pub fn new(name: String, data_type: ShaderDataType, normalized: Option<bool>) -> Self {
-        let size = shader_data_type_size(&data_type);
         BufferElement {
             name,
             data_type,
-            size,
+            size: data_type.get_size(),
             offset: 0,
             normalized: normalized.unwrap_or(false),
+            integer: false,
         }
     }
 
+    /// Passes the values to the shader as integers, for `int` and `ivec` inputs.
+    pub fn as_integer(mut self) -> Self {
+        self.integer = true;
+        self
+    }
+
     pub fn get_component_count(&self) -> u32 {
         match self.data_type {
             ShaderDataType::Float => 1,
@@ -110,25 +135,40 @@ impl BufferLayout {
 pub struct VertexBuffer {
     id: u32,
     layout: BufferLayout,
+    // Buffers are only used on the render thread, atomics just keep them shareable in an `Arc`
+    size: AtomicUsize,          // bytes of storage
+    stream_offset: AtomicUsize, // where `stream` writes next
 }
 
 impl VertexBuffer {
+    /// Buffer with room for `size` bytes, filled with `set_data` or `stream`. It grows when
+    /// more data is written.
     pub fn new(size: usize) -> Self {
+        Self::with_data(size, null(), gl::DYNAMIC_DRAW)
+    }
+
+    /// Buffer filled once with `data`, for geometry that rarely changes.
+    pub fn new_static<T: Copy>(data: &[T]) -> Self {
+        Self::with_data(
+            mem::size_of_val(data),
+            data.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        )
+    }
+
+    fn with_data(size: usize, data: *const c_void, usage: GLenum) -> Self {
         let mut id = 0;
         unsafe {
-            gl::GenBuffers(1, &mut id);
-            gl::BindBuffer(gl::ARRAY_BUFFER, id);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                size.try_into().unwrap(),
-                null(),
-                gl::DYNAMIC_DRAW,
-            )
+            gl::CreateBuffers(1, &mut id);
+            gl::NamedBufferData(id, size as GLsizeiptr, data, usage);
         }
+        track_create(GpuResourceKind::Buffer, id);
 
         VertexBuffer {
             id,
             layout: BufferLayout::new(Vec::new()),
+            size: AtomicUsize::new(size),
+            stream_offset: AtomicUsize::new(0),
         }
     }
 
@@ -140,6 +180,11 @@ impl VertexBuffer {
         &self.layout
     }
 
+    /// Bytes of storage, which grows with the data written.
+    pub fn get_size(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
@@ -152,44 +197,147 @@ impl VertexBuffer {
         }
     }
 
-    pub fn set_data(&self, data: *const std::ffi::c_void, size: usize) {
+    /// Writes `data` starting `offset` elements of `T` into the buffer, growing it if needed.
+    /// Waits for the draws still reading the buffer, `stream` avoids that for data rewritten
+    /// every frame.
+    pub fn set_data<T: Copy>(&self, data: &[T], offset: usize) {
+        let offset = offset * mem::size_of::<T>();
+        let data_size = mem::size_of_val(data);
+        self.reserve(offset + data_size);
+
         unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
-            gl::BufferSubData(gl::ARRAY_BUFFER, 0 as GLintptr, size as GLsizeiptr, data);
+            gl::NamedBufferSubData(
+                self.id,
+                offset as GLintptr,
+                data_size as GLsizeiptr,
+                data.as_ptr() as *const c_void,
+            );
+        }
+    }
+
+    /// Makes room for at least `size` bytes, keeping the contents.
+    pub fn reserve(&self, size: usize) {
+        let old_size = self.get_size();
+        if size <= old_size {
+            return;
+        }
+
+        let new_size = size.next_power_of_two();
+        unsafe {
+            // Moves the contents aside while the storage is replaced, the id stays the same so
+            // vertex arrays keep pointing at the buffer
+            let mut copy = 0;
+            if old_size > 0 {
+                gl::CreateBuffers(1, &mut copy);
+                gl::NamedBufferData(copy, old_size as GLsizeiptr, null(), gl::STREAM_COPY);
+                gl::CopyNamedBufferSubData(self.id, copy, 0, 0, old_size as GLsizeiptr);
+            }
+
+            gl::NamedBufferData(self.id, new_size as GLsizeiptr, null(), gl::DYNAMIC_DRAW);
+
+            if copy != 0 {
+                gl::CopyNamedBufferSubData(copy, self.id, 0, 0, old_size as GLsizeiptr);
+                gl::DeleteBuffers(1, &copy);
+            }
+        }
+        self.size.store(new_size, Ordering::Relaxed);
+    }
+
+    /// Writes `data` after the data streamed before and returns the index of its first
+    /// element, to draw it with a base vertex. When the buffer is full its storage is
+    /// orphaned: the driver hands out fresh storage while the draws in flight keep the old
+    /// one, so writes never wait for the GPU. Grows the buffer if `data` does not fit at all.
+    pub fn stream<T: Copy>(&self, data: &[T]) -> u32 {
+        let stride = mem::size_of::<T>().max(1);
+        let data_size = mem::size_of_val(data);
+        let mut offset = self
+            .stream_offset
+            .load(Ordering::Relaxed)
+            .next_multiple_of(stride);
+
+        let size = self.get_size();
+        if offset + data_size > size {
+            let new_size = if data_size > size {
+                data_size.next_power_of_two()
+            } else {
+                size
+            };
+            unsafe {
+                gl::NamedBufferData(self.id, new_size as GLsizeiptr, null(), gl::STREAM_DRAW);
+            }
+            self.size.store(new_size, Ordering::Relaxed);
+            offset = 0;
+        }
+
+        if data_size > 0 {
+            unsafe {
+                // Nothing reads this range since the last orphaning, no need to synchronize
+                let mapped = gl::MapNamedBufferRange(
+                    self.id,
+                    offset as GLintptr,
+                    data_size as GLsizeiptr,
+                    gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT | gl::MAP_UNSYNCHRONIZED_BIT,
+                );
+                if mapped.is_null() {
+                    gl::NamedBufferSubData(
+                        self.id,
+                        offset as GLintptr,
+                        data_size as GLsizeiptr,
+                        data.as_ptr() as *const c_void,
+                    );
+                } else {
+                    std::ptr::copy_nonoverlapping(
+                        data.as_ptr() as *const u8,
+                        mapped as *mut u8,
+                        data_size,
+                    );
+                    gl::UnmapNamedBuffer(self.id);
+                }
+            }
         }
+
+        self.stream_offset
+            .store(offset + data_size, Ordering::Relaxed);
+        (offset / stride) as u32
     }
 }
 
 pub struct IndexBuffer {
     id: u32,
-    count: u32,
+    count: AtomicU32,
 }
 
 impl IndexBuffer {
     pub fn new(indices: Vec<u32>) -> Self {
         let mut id = 0;
         unsafe {
-            gl::GenBuffers(1, &mut id);
-
-            // GL_ELEMENT_ARRAY_BUFFER is not valid without an actively bound VAO
-            // Binding with GL_ARRAY_BUFFER allows the data to be loaded regardless of VAO state.
-            gl::BindBuffer(gl::ARRAY_BUFFER, id);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (indices.len() * mem::size_of::<u32>()).try_into().unwrap(),
-                indices.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
+            gl::CreateBuffers(1, &mut id);
         }
+        track_create(GpuResourceKind::Buffer, id);
 
-        IndexBuffer {
+        let index_buffer = IndexBuffer {
             id,
-            count: indices.len() as u32,
+            count: AtomicU32::new(0),
+        };
+        index_buffer.set_indices(&indices);
+        index_buffer
+    }
+
+    /// Replaces the indices, resizing the buffer to fit them.
+    pub fn set_indices(&self, indices: &[u32]) {
+        unsafe {
+            gl::NamedBufferData(
+                self.id,
+                mem::size_of_val(indices) as GLsizeiptr,
+                indices.as_ptr() as *const c_void,
+                gl::STATIC_DRAW,
+            );
         }
+        self.count.store(indices.len() as u32, Ordering::Relaxed);
     }
 
     pub fn get_count(&self) -> u32 {
-        self.count
+        self.count.load(Ordering::Relaxed)
     }
 
     pub fn bind(&self) {
@@ -213,6 +361,7 @@ impl Clone for BufferElement {
             size: self.size,
             offset: self.offset,
             normalized: self.normalized,
+            integer: self.integer,
         }
     }
 }
@@ -226,20 +375,20 @@ impl Clone for BufferLayout {
     }
 }
 
-impl Clone for VertexBuffer {
-    fn clone(&self) -> Self {
-        VertexBuffer {
-            id: self.id,
-            layout: self.layout.clone(),
+impl Drop for VertexBuffer {
+    fn drop(&mut self) {
+        track_delete(GpuResourceKind::Buffer, self.id);
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
         }
     }
 }
 
-impl Clone for IndexBuffer {
-    fn clone(&self) -> Self {
-        IndexBuffer {
-            id: self.id,
-            count: self.count,
+impl Drop for IndexBuffer {
+    fn drop(&mut self) {
+        track_delete(GpuResourceKind::Buffer, self.id);
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
         }
     }
 }
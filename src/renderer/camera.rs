@@ -1,5 +1,8 @@
 use cgmath::*;
 
+use crate::math::AABB;
+use crate::renderer::Viewport;
+
 pub struct Camera {
     pub size: f32,
     pub near: f32,
@@ -8,7 +11,20 @@ pub struct Camera {
     pub position: Vector2<f32>,
     pub rotation: f32,
 
+    /// Snaps the view to multiples of `1 / pixels_per_unit` when set, so pixel art does not
+    /// shimmer while the camera moves. `position` itself is left untouched.
+    pub pixels_per_unit: Option<f32>,
+
+    /// Part of the window the camera draws to, applied by `set_window_size`.
+    pub viewport: Viewport,
+    /// Fills the viewport before the scene is drawn when set, e.g. behind a picture in
+    /// picture view. Without it the scene draws over the window's clear color.
+    pub clear_color: Option<Vector4<f32>>,
+
     aspect_ratio: f32,
+    viewport_size: Vector2<f32>,
+    viewport_offset: Vector2<f32>, // top left corner in window pixels
+    viewport_rect: (i32, i32, u32, u32),
 
     projection: Matrix4<f32>,
     view: Matrix4<f32>,
@@ -24,18 +40,56 @@ impl Camera {
             position,
             rotation: 0.0,
 
+            pixels_per_unit: None,
+
+            viewport: Viewport::full(),
+            clear_color: None,
+
             aspect_ratio: 1.0,
+            viewport_size: Vector2::new(1.0, 1.0),
+            viewport_offset: Vector2::zero(),
+            viewport_rect: (0, 0, 1, 1),
 
             projection: Matrix4::identity(),
             view: Matrix4::identity(),
         }
     }
 
+    /// Makes the camera cover a `width` by `height` render target, ignoring `viewport`.
     pub fn set_viewport_size(&mut self, width: u32, height: u32) {
-        self.aspect_ratio = width as f32 / height as f32;
+        self.set_viewport_rect((0, 0, width, height), Vector2::zero());
+    }
+
+    /// Places the camera's `viewport` in a window of `width` by `height` pixels and takes
+    /// the aspect ratio from it. Call when the window or the viewport changes.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        let rect = self.viewport.to_pixels(width, height);
+        let top = height as i32 - rect.1 - rect.3 as i32;
+        self.set_viewport_rect(rect, Vector2::new(rect.0 as f32, top as f32));
+    }
+
+    fn set_viewport_rect(&mut self, rect: (i32, i32, u32, u32), offset: Vector2<f32>) {
+        let (_, _, width, height) = rect;
+        self.aspect_ratio = width as f32 / height.max(1) as f32;
+        self.viewport_size = Vector2::new(width as f32, height as f32);
+        self.viewport_offset = offset;
+        self.viewport_rect = rect;
         self.recalculate_projection();
     }
 
+    /// Pixel rectangle the camera draws to as x, y, width and height, y from the bottom.
+    pub fn get_viewport_rect(&self) -> (i32, i32, u32, u32) {
+        self.viewport_rect
+    }
+
+    pub fn get_viewport_size(&self) -> Vector2<f32> {
+        self.viewport_size
+    }
+
+    pub fn get_aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
     pub fn recalculate_projection(&mut self) {
         let ortho_left = -self.size * self.aspect_ratio * 0.5;
         let ortho_right = self.size * self.aspect_ratio * 0.5;
@@ -58,7 +112,14 @@ impl Camera {
     }
 
     pub fn recalculate_view(&mut self) {
-        let translation = Vector3::new(-self.position.x, -self.position.y, 0.0);
+        let position = match self.pixels_per_unit {
+            Some(pixels_per_unit) if pixels_per_unit > 0.0 => {
+                (self.position * pixels_per_unit).map(f32::round) / pixels_per_unit
+            }
+            _ => self.position,
+        };
+
+        let translation = Vector3::new(-position.x, -position.y, 0.0);
         let rotation = Quaternion::from_axis_angle(Vector3::unit_z(), Rad(self.rotation));
 
         let translation_matrix = Matrix4::from_translation(translation);
@@ -74,4 +135,196 @@ impl Camera {
     pub fn get_view(&self) -> Matrix4<f32> {
         self.view
     }
+
+    pub fn get_view_projection(&self) -> Matrix4<f32> {
+        self.projection * self.view
+    }
+
+    /// World position under a point of the window given in pixels from its top left corner,
+    /// e.g. `GameEngine::get_cursor_position`. Uses the matrices of the last recalculation.
+    pub fn screen_to_world(&self, screen_position: Vector2<f32>) -> Vector2<f32> {
+        let screen_position = screen_position - self.viewport_offset;
+        let ndc = Vector2::new(
+            screen_position.x / self.viewport_size.x * 2.0 - 1.0,
+            1.0 - screen_position.y / self.viewport_size.y * 2.0,
+        );
+        self.ndc_to_world(ndc)
+    }
+
+    /// Inverse of `screen_to_world`.
+    pub fn world_to_screen(&self, world_position: Vector2<f32>) -> Vector2<f32> {
+        let clip = self.get_view_projection() * world_position.extend(0.0).extend(1.0);
+        let ndc = Vector2::new(clip.x / clip.w, clip.y / clip.w);
+
+        Vector2::new(
+            (ndc.x + 1.0) * 0.5 * self.viewport_size.x,
+            (1.0 - ndc.y) * 0.5 * self.viewport_size.y,
+        ) + self.viewport_offset
+    }
+
+    /// Area of the world the camera sees, grown to stay axis aligned when it is rotated.
+    pub fn get_visible_bounds(&self) -> AABB {
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(x, y)| self.ndc_to_world(Vector2::new(x, y)));
+
+        corners[1..]
+            .iter()
+            .fold(AABB::new(corners[0], corners[0]), |bounds, corner| {
+                bounds.merge(&AABB::new(*corner, *corner))
+            })
+    }
+
+    /// Zooms in by `factor` (out if below 1) keeping the world point under `screen_position`
+    /// in place, the way maps zoom towards the cursor.
+    pub fn zoom_at(&mut self, screen_position: Vector2<f32>, factor: f32) {
+        let before = self.screen_to_world(screen_position);
+
+        self.size /= factor;
+        self.recalculate_projection();
+
+        let after = self.screen_to_world(screen_position);
+        self.position += before - after;
+        self.recalculate_view();
+    }
+
+    fn ndc_to_world(&self, ndc: Vector2<f32>) -> Vector2<f32> {
+        let inverse = self
+            .get_view_projection()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        let world = inverse * Vector4::new(ndc.x, ndc.y, 0.0, 1.0);
+
+        Vector2::new(world.x / world.w, world.y / world.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_4;
+
+    fn assert_near(value: Vector2<f32>, expected: Vector2<f32>) {
+        assert!(
+            (value - expected).magnitude() < 1e-3,
+            "{:?} is not {:?}",
+            value,
+            expected
+        );
+    }
+
+    fn camera(window_width: u32, window_height: u32) -> Camera {
+        let mut camera = Camera::new(Vector2::new(3.0, 2.0), 10.0);
+        camera.set_viewport_size(window_width, window_height);
+        camera.update();
+        camera
+    }
+
+    #[test]
+    fn converts_screen_to_world() {
+        let camera = camera(800, 600);
+
+        assert_near(
+            camera.screen_to_world(Vector2::new(400.0, 300.0)),
+            Vector2::new(3.0, 2.0),
+        );
+        // Screen y goes down, world y goes up
+        assert_near(
+            camera.screen_to_world(Vector2::new(0.0, 0.0)),
+            Vector2::new(3.0 - 10.0 * 4.0 / 3.0 / 2.0, 7.0),
+        );
+        assert_near(
+            camera.screen_to_world(Vector2::new(800.0, 600.0)),
+            Vector2::new(3.0 + 10.0 * 4.0 / 3.0 / 2.0, -3.0),
+        );
+    }
+
+    #[test]
+    fn round_trips_between_screen_and_world() {
+        let mut camera = camera(800, 600);
+        camera.rotation = 0.7;
+        camera.update();
+
+        for screen in [(0.0, 0.0), (123.0, 456.0), (800.0, 600.0), (-50.0, 700.0)] {
+            let screen = Vector2::new(screen.0, screen.1);
+            assert_near(
+                camera.world_to_screen(camera.screen_to_world(screen)),
+                screen,
+            );
+        }
+    }
+
+    #[test]
+    fn converts_inside_offset_viewports() {
+        let mut camera = Camera::new(Vector2::new(3.0, 2.0), 10.0);
+        // Bottom right quarter of the window
+        camera.viewport = Viewport::normalized(0.5, 0.5, 0.5, 0.5);
+        camera.set_window_size(800, 600);
+        camera.update();
+
+        assert_eq!(camera.get_viewport_rect(), (400, 0, 400, 300));
+        assert_eq!(camera.get_aspect_ratio(), 4.0 / 3.0);
+
+        let center = Vector2::new(600.0, 450.0);
+        assert_near(camera.screen_to_world(center), Vector2::new(3.0, 2.0));
+        assert_near(camera.world_to_screen(Vector2::new(3.0, 2.0)), center);
+        // The top left corner of the viewport, not of the window
+        assert_near(
+            camera.screen_to_world(Vector2::new(400.0, 300.0)),
+            Vector2::new(3.0 - 10.0 * 4.0 / 3.0 / 2.0, 7.0),
+        );
+
+        for screen in [(400.0, 300.0), (750.0, 310.0), (10.0, 20.0)] {
+            let screen = Vector2::new(screen.0, screen.1);
+            assert_near(
+                camera.world_to_screen(camera.screen_to_world(screen)),
+                screen,
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_what_it_sees() {
+        let mut camera = camera(800, 600);
+        let bounds = camera.get_visible_bounds();
+        assert_near(bounds.min(), Vector2::new(3.0 - 20.0 / 3.0, -3.0));
+        assert_near(bounds.max(), Vector2::new(3.0 + 20.0 / 3.0, 7.0));
+
+        // A square view turned by 45 degrees touches a square √2 times bigger
+        camera.set_viewport_size(600, 600);
+        camera.rotation = FRAC_PI_4;
+        camera.update();
+        let half = 5.0 * 2.0f32.sqrt();
+        let bounds = camera.get_visible_bounds();
+        assert_near(bounds.min(), Vector2::new(3.0 - half, 2.0 - half));
+        assert_near(bounds.max(), Vector2::new(3.0 + half, 2.0 + half));
+    }
+
+    #[test]
+    fn zooms_towards_the_cursor() {
+        let mut camera = camera(800, 600);
+        let cursor = Vector2::new(100.0, 500.0);
+        let under_cursor = camera.screen_to_world(cursor);
+
+        camera.zoom_at(cursor, 2.0);
+        assert_eq!(camera.size, 5.0);
+        assert_near(camera.screen_to_world(cursor), under_cursor);
+
+        camera.zoom_at(cursor, 0.25);
+        assert_eq!(camera.size, 20.0);
+        assert_near(camera.screen_to_world(cursor), under_cursor);
+    }
+
+    #[test]
+    fn snaps_the_view_to_pixels() {
+        let mut camera = camera(800, 600);
+        camera.position = Vector2::new(1.23, -4.56);
+        camera.pixels_per_unit = Some(10.0);
+        camera.update();
+
+        assert_near(
+            camera.screen_to_world(Vector2::new(400.0, 300.0)),
+            Vector2::new(1.2, -4.6),
+        );
+        assert_eq!(camera.position, Vector2::new(1.23, -4.56));
+    }
 }
@@ -0,0 +1,293 @@
+use cgmath::*;
+
+use crate::math::AABB;
+use crate::renderer::Camera;
+
+/// Moves a `Camera` after a target every frame: smooth follow with a dead zone, clamping to
+/// the level bounds and trauma based screen shake. Everything is plain math on the camera,
+/// the controller does not touch the renderer.
+#[derive(Clone, Debug)]
+pub struct CameraController {
+    /// How fast the camera catches up, the fraction of the distance left after one second is
+    /// `exp(-smoothing)`. 0 snaps to the target.
+    pub smoothing: f32,
+    /// Half extents of the area around the camera center the target moves in freely.
+    pub dead_zone: Vector2<f32>,
+    /// Area the view stays inside of, centered on it when the view is larger.
+    pub bounds: Option<AABB>,
+
+    /// Trauma lost every second.
+    pub trauma_decay: f32,
+    /// Offset in world units and rotation in radians at full trauma.
+    pub max_shake_offset: Vector2<f32>,
+    pub max_shake_rotation: f32,
+    /// How fast the shake changes direction.
+    pub shake_frequency: f32,
+
+    focus: Vector2<f32>,
+    trauma: f32,
+    shake_time: f32,
+}
+
+impl CameraController {
+    pub fn new(position: Vector2<f32>) -> Self {
+        CameraController {
+            smoothing: 5.0,
+            dead_zone: Vector2::zero(),
+            bounds: None,
+
+            trauma_decay: 1.0,
+            max_shake_offset: Vector2::new(0.3, 0.3),
+            max_shake_rotation: 0.05,
+            shake_frequency: 15.0,
+
+            focus: position,
+            trauma: 0.0,
+            shake_time: 0.0,
+        }
+    }
+
+    /// Adds to the trauma, kept between 0 and 1. The shake grows with its square so small
+    /// hits barely move the camera while big ones add up quickly.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn get_trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Center of the view before the shake is applied.
+    pub fn get_focus(&self) -> Vector2<f32> {
+        self.focus
+    }
+
+    /// Jumps to `position` without smoothing, e.g. after a level change.
+    pub fn teleport(&mut self, position: Vector2<f32>) {
+        self.focus = position;
+    }
+
+    /// Moves towards `target` by `timestep` seconds and writes the result to `camera`,
+    /// replacing its position and rotation.
+    pub fn update(&mut self, camera: &mut Camera, target: Vector2<f32>, timestep: f32) {
+        let desired = dead_zone_target(self.focus, target, self.dead_zone);
+        self.focus = smooth_towards(self.focus, desired, self.smoothing, timestep);
+
+        if let Some(bounds) = &self.bounds {
+            let half_view =
+                Vector2::new(camera.size * camera.get_aspect_ratio(), camera.size) / 2.0;
+            self.focus = clamp_to_bounds(self.focus, half_view, bounds);
+        }
+
+        self.trauma = (self.trauma - self.trauma_decay * timestep).max(0.0);
+        self.shake_time += timestep;
+
+        let (offset, rotation) = shake(
+            self.trauma,
+            self.shake_time * self.shake_frequency,
+            self.max_shake_offset,
+            self.max_shake_rotation,
+        );
+
+        camera.position = self.focus + offset;
+        camera.rotation = rotation;
+        camera.recalculate_view();
+    }
+}
+
+/// Point the camera should move to so `target` ends up inside the dead zone around `focus`.
+pub fn dead_zone_target(
+    focus: Vector2<f32>,
+    target: Vector2<f32>,
+    dead_zone: Vector2<f32>,
+) -> Vector2<f32> {
+    let axis = |focus: f32, target: f32, half: f32| {
+        let distance = target - focus;
+        if distance > half {
+            target - half
+        } else if distance < -half {
+            target + half
+        } else {
+            focus
+        }
+    };
+
+    Vector2::new(
+        axis(focus.x, target.x, dead_zone.x),
+        axis(focus.y, target.y, dead_zone.y),
+    )
+}
+
+/// Exponential smoothing that does not depend on the frame rate.
+pub fn smooth_towards(
+    current: Vector2<f32>,
+    target: Vector2<f32>,
+    smoothing: f32,
+    timestep: f32,
+) -> Vector2<f32> {
+    if smoothing <= 0.0 {
+        return target;
+    }
+
+    let blend = 1.0 - (-smoothing * timestep).exp();
+    current + (target - current) * blend
+}
+
+/// Keeps a view of `half_size` centered on `center` inside `bounds`.
+pub fn clamp_to_bounds(
+    center: Vector2<f32>,
+    half_size: Vector2<f32>,
+    bounds: &AABB,
+) -> Vector2<f32> {
+    let (min, max) = (bounds.min(), bounds.max());
+    let axis = |center: f32, half: f32, min: f32, max: f32| {
+        if max - min <= half * 2.0 {
+            (min + max) / 2.0
+        } else {
+            center.clamp(min + half, max - half)
+        }
+    };
+
+    Vector2::new(
+        axis(center.x, half_size.x, min.x, max.x),
+        axis(center.y, half_size.y, min.y, max.y),
+    )
+}
+
+/// Offset and rotation of the camera shake for `trauma` at `time`, scaled by trauma squared.
+pub fn shake(
+    trauma: f32,
+    time: f32,
+    max_offset: Vector2<f32>,
+    max_rotation: f32,
+) -> (Vector2<f32>, f32) {
+    let amount = trauma * trauma;
+    if amount <= 0.0 {
+        return (Vector2::zero(), 0.0);
+    }
+
+    let offset = Vector2::new(
+        max_offset.x * amount * noise(time, 0.0),
+        max_offset.y * amount * noise(time, 31.7),
+    );
+    (offset, max_rotation * amount * noise(time, 73.1))
+}
+
+// Smooth value between -1 and 1, a few sines with unrelated frequencies look random enough
+// and keep the shake deterministic
+fn noise(time: f32, seed: f32) -> f32 {
+    let t = time + seed;
+    (t.sin() * 0.5 + (t * 2.31 + 1.7).sin() * 0.3 + (t * 4.73 + 4.1).sin() * 0.2).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(value: Vector2<f32>, expected: Vector2<f32>) {
+        assert!(
+            (value - expected).magnitude() < 1e-4,
+            "{:?} is not {:?}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn moves_only_when_leaving_the_dead_zone() {
+        let focus = Vector2::new(10.0, 10.0);
+        let dead_zone = Vector2::new(2.0, 1.0);
+
+        let inside = Vector2::new(11.5, 9.2);
+        assert_eq!(dead_zone_target(focus, inside, dead_zone), focus);
+
+        // Just far enough for the target to be on the edge of the zone
+        let outside = Vector2::new(15.0, 8.0);
+        assert_eq!(
+            dead_zone_target(focus, outside, dead_zone),
+            Vector2::new(13.0, 9.0)
+        );
+        assert_eq!(dead_zone_target(focus, outside, Vector2::zero()), outside);
+    }
+
+    #[test]
+    fn smooths_independently_of_the_frame_rate() {
+        let (from, to) = (Vector2::new(0.0, 0.0), Vector2::new(10.0, -4.0));
+
+        let mut stepped = from;
+        for _ in 0..10 {
+            stepped = smooth_towards(stepped, to, 5.0, 0.01);
+        }
+        assert_near(stepped, smooth_towards(from, to, 5.0, 0.1));
+
+        // exp(-smoothing) of the distance is left after a second
+        assert_near(
+            smooth_towards(from, to, 2.0, 1.0),
+            to * (1.0 - (-2.0f32).exp()),
+        );
+        assert_eq!(smooth_towards(from, to, 0.0, 0.01), to);
+        assert_eq!(smooth_towards(from, to, 5.0, 0.0), from);
+    }
+
+    #[test]
+    fn clamps_the_view_to_bounds() {
+        let bounds = AABB::from_min_max(0.0, 0.0, 100.0, 50.0);
+        let half_view = Vector2::new(10.0, 5.0);
+
+        let inside = Vector2::new(40.0, 20.0);
+        assert_eq!(clamp_to_bounds(inside, half_view, &bounds), inside);
+        assert_eq!(
+            clamp_to_bounds(Vector2::new(-30.0, 60.0), half_view, &bounds),
+            Vector2::new(10.0, 45.0)
+        );
+
+        // Views larger than the bounds are centered on them, per axis
+        assert_eq!(
+            clamp_to_bounds(Vector2::new(-30.0, 60.0), Vector2::new(60.0, 5.0), &bounds),
+            Vector2::new(50.0, 45.0)
+        );
+        assert_eq!(
+            clamp_to_bounds(inside, Vector2::new(60.0, 30.0), &bounds),
+            Vector2::new(50.0, 25.0)
+        );
+    }
+
+    #[test]
+    fn shakes_only_with_trauma() {
+        let max_offset = Vector2::new(0.3, 0.2);
+        for time in [0.0, 1.3, 57.9] {
+            assert_eq!(shake(0.0, time, max_offset, 0.05), (Vector2::zero(), 0.0));
+
+            let (offset, rotation) = shake(1.0, time, max_offset, 0.05);
+            assert!(offset.x.abs() <= 0.3 && offset.y.abs() <= 0.2);
+            assert!(rotation.abs() <= 0.05);
+
+            // Half the trauma, a quarter of the shake
+            let (half_offset, half_rotation) = shake(0.5, time, max_offset, 0.05);
+            assert_near(half_offset * 4.0, offset);
+            assert!((half_rotation * 4.0 - rotation).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn follows_the_target() {
+        let mut camera = Camera::new(Vector2::zero(), 10.0);
+        camera.set_viewport_size(200, 100);
+        let mut controller = CameraController::new(Vector2::zero());
+        controller.smoothing = 0.0;
+        controller.bounds = Some(AABB::from_min_max(-20.0, -20.0, 20.0, 20.0));
+
+        controller.update(&mut camera, Vector2::new(3.0, 4.0), 0.1);
+        assert_eq!(camera.position, Vector2::new(3.0, 4.0));
+
+        // Half the view is 10 by 5 units
+        controller.update(&mut camera, Vector2::new(100.0, -100.0), 0.1);
+        assert_eq!(camera.position, Vector2::new(10.0, -15.0));
+
+        controller.add_trauma(0.5);
+        controller.update(&mut camera, Vector2::new(100.0, -100.0), 0.1);
+        assert!((controller.get_trauma() - 0.4).abs() < 1e-6);
+        assert_eq!(controller.get_focus(), Vector2::new(10.0, -15.0));
+        assert_ne!(camera.position, controller.get_focus());
+    }
+}
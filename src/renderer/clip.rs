@@ -0,0 +1,230 @@
+use cgmath::*;
+
+use crate::math::AABB;
+use crate::renderer::RenderCommand;
+
+/// Rectangle of the render target in pixels, y from the bottom like viewports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScissorRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        ScissorRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Pixels of `viewport` covered by `bounds` once transformed by `matrix` to clip space,
+    /// partly covered pixels included.
+    pub fn from_bounds(
+        bounds: &AABB,
+        matrix: &Matrix4<f32>,
+        viewport: (i32, i32, u32, u32),
+    ) -> Self {
+        let (min, max) = (bounds.min(), bounds.max());
+        let corners = [
+            min,
+            Vector2::new(max.x, min.y),
+            max,
+            Vector2::new(min.x, max.y),
+        ]
+        .map(|corner| {
+            let clip = matrix * corner.extend(0.0).extend(1.0);
+            let ndc = clip.truncate().truncate() / clip.w;
+            let (x, y, width, height) = viewport;
+            Vector2::new(
+                x as f32 + (ndc.x + 1.0) / 2.0 * width as f32,
+                y as f32 + (ndc.y + 1.0) / 2.0 * height as f32,
+            )
+        });
+
+        let (min, max) = corners[1..]
+            .iter()
+            .fold((corners[0], corners[0]), |(min, max), c| {
+                (
+                    Vector2::new(min.x.min(c.x), min.y.min(c.y)),
+                    Vector2::new(max.x.max(c.x), max.y.max(c.y)),
+                )
+            });
+
+        // Ignores rounding errors, which would add a column or row of pixels
+        const EPSILON: f32 = 1e-3;
+        let (x, y) = (
+            (min.x + EPSILON).floor() as i32,
+            (min.y + EPSILON).floor() as i32,
+        );
+        ScissorRect::new(
+            x,
+            y,
+            ((max.x - EPSILON).ceil() as i32 - x).max(0) as u32,
+            ((max.y - EPSILON).ceil() as i32 - y).max(0) as u32,
+        )
+    }
+
+    /// Area inside both rectangles, empty if they do not overlap.
+    pub fn intersect(&self, other: &ScissorRect) -> ScissorRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width as i32).min(other.x + other.width as i32);
+        let top = (self.y + self.height as i32).min(other.y + other.height as i32);
+        ScissorRect::new(x, y, (right - x).max(0) as u32, (top - y).max(0) as u32)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// Nested clip rects and stencil masks of a renderer, applied to the GL state right away.
+/// The renderer flushes its batch before every change, so the batch drawn last is clipped by
+/// the state it was built under.
+///
+/// Masks count up in the stencil buffer: drawing mask n + 1 increments the pixels at n, and
+/// content is only drawn where the stencil is the depth of the innermost mask.
+#[derive(Debug, Default)]
+pub(crate) struct ClipStack {
+    rects: Vec<ScissorRect>,
+    outer_rect: Option<ScissorRect>, // scissor before the first push, none if the test was off
+    mask_depth: u32,
+    writing_mask: bool,
+    popped_scissor: Option<ScissorRect>, // restored once a mask is popped
+}
+
+impl ClipStack {
+    pub(crate) fn push_rect(&mut self, rect: ScissorRect) {
+        if self.rects.is_empty() {
+            self.outer_rect = current_scissor();
+        }
+
+        let rect = match self.rects.last().or(self.outer_rect.as_ref()) {
+            Some(current) => rect.intersect(current),
+            None => rect,
+        };
+        self.rects.push(rect);
+        apply_scissor(Some(rect));
+    }
+
+    /// Returns false if there was no rect to pop.
+    pub(crate) fn pop_rect(&mut self) -> bool {
+        if self.rects.pop().is_none() {
+            return false;
+        }
+        apply_scissor(self.rects.last().copied().or(self.outer_rect));
+        true
+    }
+
+    /// Redirects the next draws to the stencil buffer, until `end_mask`.
+    pub(crate) fn begin_mask(&mut self) {
+        if self.mask_depth == 0 {
+            RenderCommand::clear_stencil();
+            RenderCommand::set_stencil_test(true);
+        }
+
+        self.writing_mask = true;
+        unsafe {
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            // Pixels only go up once, however many shapes of the mask overlap
+            gl::StencilFunc(gl::EQUAL, self.mask_depth as i32, 0xff);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::INCR);
+        }
+    }
+
+    /// Limits the next draws to the mask drawn since `begin_mask`, inside the outer masks.
+    pub(crate) fn end_mask(&mut self) {
+        self.writing_mask = false;
+        self.mask_depth += 1;
+        unsafe {
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        }
+        test_mask_depth(self.mask_depth);
+    }
+
+    /// Starts removing the innermost mask, the renderer then draws anything covering the
+    /// viewport and calls `end_pop_mask`. Returns false if there was no mask to pop.
+    pub(crate) fn begin_pop_mask(&mut self) -> bool {
+        if self.mask_depth == 0 || self.writing_mask {
+            return false;
+        }
+
+        self.mask_depth -= 1;
+        unsafe {
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            // Brings every pixel above the new depth back to it
+            gl::StencilFunc(gl::LESS, self.mask_depth as i32, 0xff);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+        }
+        // The reset must reach the whole mask, which may be outside of the current scissor
+        self.popped_scissor = current_scissor();
+        apply_scissor(None);
+        true
+    }
+
+    pub(crate) fn end_pop_mask(&mut self) {
+        unsafe {
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        }
+        apply_scissor(self.popped_scissor.take());
+
+        if self.mask_depth == 0 {
+            RenderCommand::set_stencil_test(false);
+        } else {
+            test_mask_depth(self.mask_depth);
+        }
+    }
+
+    pub(crate) fn get_mask_depth(&self) -> u32 {
+        self.mask_depth
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rects.is_empty() && self.mask_depth == 0 && !self.writing_mask
+    }
+
+    /// Drops every rect and mask left, restoring the state from before the first push.
+    pub(crate) fn clear(&mut self) {
+        if !self.rects.is_empty() {
+            self.rects.clear();
+            apply_scissor(self.outer_rect);
+        }
+        if self.mask_depth > 0 || self.writing_mask {
+            unsafe {
+                gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            }
+            RenderCommand::set_stencil_test(false);
+        }
+        self.mask_depth = 0;
+        self.writing_mask = false;
+    }
+}
+
+fn current_scissor() -> Option<ScissorRect> {
+    RenderCommand::is_scissor_test_enabled().then(|| {
+        let (x, y, width, height) = RenderCommand::get_scissor();
+        ScissorRect::new(x, y, width, height)
+    })
+}
+
+fn apply_scissor(rect: Option<ScissorRect>) {
+    match rect {
+        Some(rect) => {
+            RenderCommand::set_scissor(rect.x, rect.y, rect.width, rect.height);
+            RenderCommand::set_scissor_test(true);
+        }
+        None => RenderCommand::set_scissor_test(false),
+    }
+}
+
+fn test_mask_depth(depth: u32) {
+    unsafe {
+        gl::StencilFunc(gl::EQUAL, depth as i32, 0xff);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+    }
+}
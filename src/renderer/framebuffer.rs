@@ -0,0 +1,198 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use cgmath::*;
+
+use crate::renderer::{
+    track_create, track_delete, GpuResourceKind, RenderCommand, Texture2D, TextureFilter,
+    TextureFormat, TextureSpec, TextureWrap,
+};
+
+#[derive(Clone, Debug)]
+pub struct FramebufferSpec {
+    pub width: u32,
+    pub height: u32,
+    /// One texture is created per format, bound to color attachment 0, 1, ...
+    pub color_attachments: Vec<TextureFormat>,
+    /// Adds a 24 bit depth and 8 bit stencil buffer, which cannot be sampled.
+    pub depth_stencil: bool,
+}
+
+// What `unbind` goes back to, 0 for the window. Headless runs draw frames off-screen.
+static SCREEN_FRAMEBUFFER: AtomicU32 = AtomicU32::new(0);
+
+/// Off-screen render target. Draws go to it between `bind` and `unbind`.
+pub struct Framebuffer {
+    id: u32,
+    spec: FramebufferSpec,
+    color_attachments: Vec<Texture2D>,
+    depth_stencil_id: u32, // renderbuffer, 0 if none
+}
+
+impl Framebuffer {
+    pub fn new(spec: FramebufferSpec) -> Self {
+        let mut framebuffer = Framebuffer {
+            id: 0,
+            spec,
+            color_attachments: Vec::new(),
+            depth_stencil_id: 0,
+        };
+        framebuffer.create();
+        framebuffer
+    }
+
+    fn create(&mut self) {
+        let (width, height) = (self.spec.width.max(1), self.spec.height.max(1));
+
+        unsafe {
+            gl::CreateFramebuffers(1, &mut self.id);
+            track_create(GpuResourceKind::Framebuffer, self.id);
+        }
+
+        self.color_attachments = self
+            .spec
+            .color_attachments
+            .iter()
+            .map(|format| {
+                Texture2D::from_spec(
+                    width,
+                    height,
+                    TextureSpec {
+                        format: *format,
+                        min_filter: TextureFilter::Linear,
+                        mag_filter: TextureFilter::Linear,
+                        wrap_s: TextureWrap::ClampToEdge,
+                        wrap_t: TextureWrap::ClampToEdge,
+                        ..TextureSpec::default()
+                    },
+                )
+            })
+            .collect();
+
+        let draw_buffers: Vec<u32> = (0..self.color_attachments.len() as u32)
+            .map(|index| gl::COLOR_ATTACHMENT0 + index)
+            .collect();
+
+        unsafe {
+            for (index, texture) in self.color_attachments.iter().enumerate() {
+                gl::NamedFramebufferTexture(
+                    self.id,
+                    gl::COLOR_ATTACHMENT0 + index as u32,
+                    texture.id,
+                    0,
+                );
+            }
+            gl::NamedFramebufferDrawBuffers(
+                self.id,
+                draw_buffers.len() as i32,
+                draw_buffers.as_ptr(),
+            );
+
+            if self.spec.depth_stencil {
+                gl::CreateRenderbuffers(1, &mut self.depth_stencil_id);
+                track_create(GpuResourceKind::Renderbuffer, self.depth_stencil_id);
+                gl::NamedRenderbufferStorage(
+                    self.depth_stencil_id,
+                    gl::DEPTH24_STENCIL8,
+                    width as i32,
+                    height as i32,
+                );
+                gl::NamedFramebufferRenderbuffer(
+                    self.id,
+                    gl::DEPTH_STENCIL_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    self.depth_stencil_id,
+                );
+            }
+
+            let status = gl::CheckNamedFramebufferStatus(self.id, gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                panic!("Framebuffer is incomplete: 0x{:x}", status);
+            }
+        }
+    }
+
+    fn release(&mut self) {
+        unsafe {
+            if self.depth_stencil_id != 0 {
+                track_delete(GpuResourceKind::Renderbuffer, self.depth_stencil_id);
+                gl::DeleteRenderbuffers(1, &self.depth_stencil_id);
+                self.depth_stencil_id = 0;
+            }
+            track_delete(GpuResourceKind::Framebuffer, self.id);
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+        self.color_attachments.clear();
+    }
+
+    /// Recreates the attachments at the new size, their contents are lost.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == self.spec.width && height == self.spec.height {
+            return;
+        }
+
+        self.release();
+        self.spec.width = width;
+        self.spec.height = height;
+        self.create();
+    }
+
+    /// Makes the framebuffer the render target and covers it with the viewport.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        }
+        RenderCommand::set_viewport(0, 0, self.spec.width, self.spec.height);
+    }
+
+    /// Goes back to drawing to the window, or to the frame of a headless run. The caller
+    /// restores its viewport.
+    pub fn unbind() {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, SCREEN_FRAMEBUFFER.load(Ordering::Relaxed));
+        }
+    }
+
+    /// Makes `framebuffer` stand in for the window, so `unbind` goes back to it, or the
+    /// window again with `None`.
+    pub(crate) fn set_screen(framebuffer: Option<&Framebuffer>) {
+        let id = framebuffer.map_or(0, |framebuffer| framebuffer.id);
+        SCREEN_FRAMEBUFFER.store(id, Ordering::Relaxed);
+    }
+
+    /// Clears one color attachment, leaving the global clear color alone.
+    pub fn clear_color_attachment(&self, index: usize, color: Vector4<f32>) {
+        unsafe {
+            gl::ClearNamedFramebufferfv(self.id, gl::COLOR, index as i32, color.as_ptr());
+        }
+    }
+
+    pub fn clear_depth_stencil(&self) {
+        if self.depth_stencil_id != 0 {
+            unsafe {
+                gl::ClearNamedFramebufferfi(self.id, gl::DEPTH_STENCIL, 0, 1.0, 0);
+            }
+        }
+    }
+
+    pub fn get_color_attachment(&self, index: usize) -> &Texture2D {
+        &self.color_attachments[index]
+    }
+
+    pub fn get_spec(&self) -> &FramebufferSpec {
+        &self.spec
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.spec.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.spec.height
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+
+use gl::types::*;
+
+use crate::renderer::{track_create, track_delete, GpuResourceKind};
+
+// Timer results are read back a few frames late so that polling never stalls the pipeline.
+const MAX_FRAMES_IN_FLIGHT: usize = 4;
+
+pub struct GpuTimer {
+    free_queries: Vec<u32>,
+    pending_frames: VecDeque<Vec<u32>>,
+    current_frame: Vec<u32>,
+    active_query: Option<u32>,
+    last_frame_time: f32,
+}
+
+impl GpuTimer {
+    pub fn new() -> Self {
+        GpuTimer {
+            free_queries: Vec::new(),
+            pending_frames: VecDeque::with_capacity(MAX_FRAMES_IN_FLIGHT + 1),
+            current_frame: Vec::new(),
+            active_query: None,
+            last_frame_time: 0.0,
+        }
+    }
+
+    pub fn begin(&mut self) {
+        assert!(
+            self.active_query.is_none(),
+            "GPU timer queries cannot be nested!"
+        );
+
+        let query = self.free_queries.pop().unwrap_or_else(|| {
+            let mut id = 0;
+            unsafe {
+                gl::GenQueries(1, &mut id);
+            }
+            track_create(GpuResourceKind::Query, id);
+            id
+        });
+
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, query);
+        }
+        self.active_query = Some(query);
+    }
+
+    pub fn end(&mut self) {
+        if let Some(query) = self.active_query.take() {
+            unsafe {
+                gl::EndQuery(gl::TIME_ELAPSED);
+            }
+            self.current_frame.push(query);
+        }
+    }
+
+    /// Closes the queries recorded since the last call and collects every finished frame.
+    pub fn end_frame(&mut self) {
+        let frame = std::mem::take(&mut self.current_frame);
+        self.pending_frames.push_back(frame);
+
+        while let Some(frame) = self.pending_frames.front() {
+            let must_wait = self.pending_frames.len() > MAX_FRAMES_IN_FLIGHT;
+            if !must_wait && !Self::is_frame_available(frame) {
+                break;
+            }
+
+            let frame = self.pending_frames.pop_front().unwrap();
+            let mut elapsed_ns: u64 = 0;
+            for &query in &frame {
+                let mut result: GLuint64 = 0;
+                unsafe {
+                    gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut result);
+                }
+                elapsed_ns += result;
+            }
+            self.free_queries.extend(frame);
+
+            self.last_frame_time = elapsed_ns as f32 / 1_000_000.0;
+        }
+    }
+
+    fn is_frame_available(frame: &[u32]) -> bool {
+        frame.iter().all(|&query| {
+            let mut available: GLint = 0;
+            unsafe {
+                gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            }
+            available != 0
+        })
+    }
+
+    /// GPU time in milliseconds of the most recent frame whose results are available.
+    pub fn get_last_frame_time(&self) -> f32 {
+        self.last_frame_time
+    }
+}
+
+impl Default for GpuTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        let queries = self
+            .free_queries
+            .drain(..)
+            .chain(self.pending_frames.drain(..).flatten())
+            .chain(self.current_frame.drain(..))
+            .chain(self.active_query.take());
+
+        for query in queries {
+            track_delete(GpuResourceKind::Query, query);
+            unsafe {
+                gl::DeleteQueries(1, &query);
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use cgmath::*;
+
+/// Cells per side a quad is split into for gradients that are not linear, since colors are
+/// only interpolated linearly between vertices.
+pub const GRADIENT_SUBDIVISIONS: u32 = 12;
+
+/// Colors varying over a quad, e.g. for health bars or sky backdrops. Points of the quad go
+/// from (0, 0) at the bottom-left to (1, 1) at the top-right, whatever its size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gradient {
+    /// One color per corner: bottom-left, bottom-right, top-right and top-left.
+    Corners([Vector4<f32>; 4]),
+    /// From `start` on one side to `end` on the other, going `angle` radians
+    /// counter-clockwise from the right.
+    Linear {
+        start: Vector4<f32>,
+        end: Vector4<f32>,
+        angle: f32,
+    },
+    /// From `inner` at `center` to `outer` at `radius` and beyond. A radius of 0.5 around
+    /// (0.5, 0.5) touches the middle of the sides.
+    Radial {
+        inner: Vector4<f32>,
+        outer: Vector4<f32>,
+        center: Vector2<f32>,
+        radius: f32,
+    },
+}
+
+impl Gradient {
+    /// `bottom` below, `top` above.
+    pub fn vertical(bottom: Vector4<f32>, top: Vector4<f32>) -> Self {
+        Gradient::Linear {
+            start: bottom,
+            end: top,
+            angle: std::f32::consts::FRAC_PI_2,
+        }
+    }
+
+    /// `left` on the left, `right` on the right.
+    pub fn horizontal(left: Vector4<f32>, right: Vector4<f32>) -> Self {
+        Gradient::Linear {
+            start: left,
+            end: right,
+            angle: 0.0,
+        }
+    }
+
+    /// `inner` in the middle fading to `outer` at the middle of the sides.
+    pub fn radial(inner: Vector4<f32>, outer: Vector4<f32>) -> Self {
+        Gradient::Radial {
+            inner,
+            outer,
+            center: Vector2::new(0.5, 0.5),
+            radius: 0.5,
+        }
+    }
+
+    pub fn color_at(&self, point: Vector2<f32>) -> Vector4<f32> {
+        match *self {
+            Gradient::Corners([bottom_left, bottom_right, top_right, top_left]) => {
+                let bottom = bottom_left.lerp(bottom_right, point.x);
+                let top = top_left.lerp(top_right, point.x);
+                bottom.lerp(top, point.y)
+            }
+            Gradient::Linear { start, end, angle } => {
+                let direction = Vector2::new(angle.cos(), angle.sin());
+                // Half the length of the unit square along the direction
+                let extent = (direction.x.abs() + direction.y.abs()) / 2.0;
+                let distance = (point - Vector2::new(0.5, 0.5)).dot(direction);
+                start.lerp(end, (distance / extent + 1.0) / 2.0)
+            }
+            Gradient::Radial {
+                inner,
+                outer,
+                center,
+                radius,
+            } => {
+                let t = if radius > 0.0 {
+                    ((point - center).magnitude() / radius).min(1.0)
+                } else {
+                    1.0
+                };
+                inner.lerp(outer, t)
+            }
+        }
+    }
+
+    /// Whether the colors of the corners are enough to draw the gradient, the others are
+    /// drawn as `GRADIENT_SUBDIVISIONS` squared cells.
+    pub fn is_linear(&self) -> bool {
+        !matches!(self, Gradient::Radial { .. })
+    }
+
+    /// Number of cells per side to draw the gradient with.
+    pub fn get_subdivisions(&self) -> u32 {
+        if self.is_linear() {
+            1
+        } else {
+            GRADIENT_SUBDIVISIONS
+        }
+    }
+}
+
+impl From<Vector4<f32>> for Gradient {
+    fn from(color: Vector4<f32>) -> Self {
+        Gradient::Corners([color; 4])
+    }
+}
+
+/// Part of a quad drawn with a gradient, in the coordinates of the quad.
+#[derive(Clone, Copy, Debug)]
+pub struct GradientCell {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+    /// Bottom-left, bottom-right, top-right and top-left.
+    pub colors: [Vector4<f32>; 4],
+}
+
+/// Cells a gradient quad is drawn as, a single one for linear gradients.
+pub fn gradient_cells(gradient: &Gradient) -> Vec<GradientCell> {
+    let subdivisions = gradient.get_subdivisions();
+    let step = 1.0 / subdivisions as f32;
+
+    let mut cells = Vec::with_capacity((subdivisions * subdivisions) as usize);
+    for y in 0..subdivisions {
+        for x in 0..subdivisions {
+            let min = Vector2::new(x as f32, y as f32) * step;
+            let max = min + Vector2::new(step, step);
+            let colors = [
+                min,
+                Vector2::new(max.x, min.y),
+                max,
+                Vector2::new(min.x, max.y),
+            ]
+            .map(|point| gradient.color_at(point));
+            cells.push(GradientCell { min, max, colors });
+        }
+    }
+    cells
+}
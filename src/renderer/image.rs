@@ -0,0 +1,434 @@
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::asset::vfs;
+
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error("failed to decode image: {0}")]
+    Decode(String),
+    #[error("failed to encode image: {0}")]
+    Encode(String),
+    #[error("image data is {actual} bytes but {expected} were expected")]
+    InvalidSize { expected: usize, actual: usize },
+    #[error("images with {0} channels are not supported")]
+    UnsupportedChannels(u32),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// CPU-side 8 bit image with rows stored from top to bottom.
+#[derive(Clone, Debug)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    channels: u32,
+    data: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, channels: u32) -> Self {
+        assert!(
+            (1..=4).contains(&channels),
+            "Images must have between 1 and 4 channels!"
+        );
+        Image {
+            width,
+            height,
+            channels,
+            data: vec![0; (width * height * channels) as usize],
+        }
+    }
+
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        channels: u32,
+        data: Vec<u8>,
+    ) -> Result<Self, ImageError> {
+        if !(1..=4).contains(&channels) {
+            return Err(ImageError::UnsupportedChannels(channels));
+        }
+
+        // Sizes too big to count cannot match the data either
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(channels as usize))
+            .unwrap_or(usize::MAX);
+        if data.len() != expected {
+            return Err(ImageError::InvalidSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        Ok(Image {
+            width,
+            height,
+            channels,
+            data,
+        })
+    }
+
+    /// Reads and decodes an image through the virtual filesystem.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        let bytes = vfs::read(path)?;
+        Self::from_memory(&bytes)
+    }
+
+    /// Decodes any format supported by stb_image (PNG, JPEG, BMP, TGA, ...).
+    pub fn from_memory(bytes: &[u8]) -> Result<Self, ImageError> {
+        let mut width: i32 = 0;
+        let mut height: i32 = 0;
+        let mut channels: i32 = 0;
+
+        unsafe {
+            let data = stb_image::stb_image::stbi_load_from_memory(
+                bytes.as_ptr(),
+                bytes.len() as i32,
+                &mut width,
+                &mut height,
+                &mut channels,
+                0,
+            );
+            if data.is_null() {
+                let reason = stb_image::stb_image::stbi_failure_reason();
+                let reason = if reason.is_null() {
+                    String::from("unknown error")
+                } else {
+                    CStr::from_ptr(reason).to_string_lossy().into_owned()
+                };
+                return Err(ImageError::Decode(reason));
+            }
+
+            let size = (width * height * channels) as usize;
+            let pixels = std::slice::from_raw_parts(data, size).to_vec();
+            stb_image::stb_image::stbi_image_free(data as *mut std::ffi::c_void);
+
+            Self::from_raw(width as u32, height as u32, channels as u32, pixels)
+        }
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn get_data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn pixel_offset(&self, x: u32, y: u32) -> usize {
+        ((y * self.width + x) * self.channels) as usize
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> &[u8] {
+        let offset = self.pixel_offset(x, y);
+        &self.data[offset..offset + self.channels as usize]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: &[u8]) {
+        let offset = self.pixel_offset(x, y);
+        self.data[offset..offset + self.channels as usize].copy_from_slice(pixel);
+    }
+
+    /// Converts to 4 channels, expanding grey to RGB and filling missing alpha with 255.
+    pub fn to_rgba(&self) -> Image {
+        if self.channels == 4 {
+            return self.clone();
+        }
+
+        let mut data = Vec::with_capacity((self.width * self.height * 4) as usize);
+        for pixel in self.data.chunks_exact(self.channels as usize) {
+            let rgba = match *pixel {
+                [grey] => [grey, grey, grey, 255],
+                [grey, alpha] => [grey, grey, grey, alpha],
+                [r, g, b] => [r, g, b, 255],
+                _ => unreachable!(),
+            };
+            data.extend_from_slice(&rgba);
+        }
+
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: 4,
+            data,
+        }
+    }
+
+    /// Bilinear resize.
+    pub fn resize(&self, width: u32, height: u32) -> Image {
+        let mut resized = Image::new(width, height, self.channels);
+        if self.width == 0 || self.height == 0 {
+            return resized;
+        }
+
+        let scale_x = self.width as f32 / width as f32;
+        let scale_y = self.height as f32 / height as f32;
+        let channels = self.channels as usize;
+
+        for y in 0..height {
+            let src_y = ((y as f32 + 0.5) * scale_y - 0.5).clamp(0.0, (self.height - 1) as f32);
+            let y0 = src_y.floor() as u32;
+            let y1 = (y0 + 1).min(self.height - 1);
+            let ty = src_y - y0 as f32;
+
+            for x in 0..width {
+                let src_x = ((x as f32 + 0.5) * scale_x - 0.5).clamp(0.0, (self.width - 1) as f32);
+                let x0 = src_x.floor() as u32;
+                let x1 = (x0 + 1).min(self.width - 1);
+                let tx = src_x - x0 as f32;
+
+                let offset = resized.pixel_offset(x, y);
+                for c in 0..channels {
+                    let p00 = self.get_pixel(x0, y0)[c] as f32;
+                    let p10 = self.get_pixel(x1, y0)[c] as f32;
+                    let p01 = self.get_pixel(x0, y1)[c] as f32;
+                    let p11 = self.get_pixel(x1, y1)[c] as f32;
+
+                    let top = p00 + (p10 - p00) * tx;
+                    let bottom = p01 + (p11 - p01) * tx;
+                    resized.data[offset + c] = (top + (bottom - top) * ty).round() as u8;
+                }
+            }
+        }
+
+        resized
+    }
+
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Image {
+        assert!(
+            x + width <= self.width && y + height <= self.height,
+            "Crop rectangle must be inside the image!"
+        );
+
+        let mut cropped = Image::new(width, height, self.channels);
+        let row_size = (width * self.channels) as usize;
+        for row in 0..height {
+            let src = self.pixel_offset(x, y + row);
+            let dst = cropped.pixel_offset(0, row);
+            cropped.data[dst..dst + row_size].copy_from_slice(&self.data[src..src + row_size]);
+        }
+
+        cropped
+    }
+
+    pub fn flip_vertical(&mut self) {
+        let row_size = (self.width * self.channels) as usize;
+        if row_size == 0 {
+            return;
+        }
+
+        let height = self.height as usize;
+        for row in 0..height / 2 {
+            let (top, bottom) = self.data.split_at_mut((height - row - 1) * row_size);
+            top[row * row_size..(row + 1) * row_size].swap_with_slice(&mut bottom[..row_size]);
+        }
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        let channels = self.channels as usize;
+        let row_size = self.width as usize * channels;
+        if row_size == 0 {
+            return;
+        }
+
+        for row in self.data.chunks_exact_mut(row_size) {
+            let width = row.len() / channels;
+            for x in 0..width / 2 {
+                for c in 0..channels {
+                    row.swap(x * channels + c, (width - x - 1) * channels + c);
+                }
+            }
+        }
+    }
+
+    /// Multiplies the color channels by alpha. Images without an alpha channel are unchanged.
+    pub fn premultiply_alpha(&mut self) {
+        let channels = self.channels as usize;
+        if channels != 2 && channels != 4 {
+            return;
+        }
+
+        for pixel in self.data.chunks_exact_mut(channels) {
+            let alpha = pixel[channels - 1] as u32;
+            for value in &mut pixel[..channels - 1] {
+                *value = ((*value as u32 * alpha + 127) / 255) as u8;
+            }
+        }
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>, ImageError> {
+        let mut bytes = Vec::new();
+        self.write_png(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        let file = File::create(path)?;
+        self.write_png(BufWriter::new(file))
+    }
+
+    fn write_png<W: std::io::Write>(&self, writer: W) -> Result<(), ImageError> {
+        let color_type = match self.channels {
+            1 => png::ColorType::Grayscale,
+            2 => png::ColorType::GrayscaleAlpha,
+            3 => png::ColorType::Rgb,
+            4 => png::ColorType::Rgba,
+            channels => return Err(ImageError::UnsupportedChannels(channels)),
+        };
+
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(|error| ImageError::Encode(error.to_string()))?;
+        writer
+            .write_image_data(&self.data)
+            .map_err(|error| ImageError::Encode(error.to_string()))?;
+        writer
+            .finish()
+            .map_err(|error| ImageError::Encode(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every pixel different, so flips and crops show where pixels went
+    fn gradient(width: u32, height: u32, channels: u32) -> Image {
+        let data = (0..width * height * channels)
+            .map(|value| value as u8)
+            .collect();
+        Image::from_raw(width, height, channels, data).unwrap()
+    }
+
+    #[test]
+    fn validates_raw_data() {
+        assert!(Image::from_raw(2, 3, 4, vec![0; 24]).is_ok());
+        assert!(matches!(
+            Image::from_raw(2, 3, 4, vec![0; 23]),
+            Err(ImageError::InvalidSize {
+                expected: 24,
+                actual: 23
+            })
+        ));
+        assert!(matches!(
+            Image::from_raw(1, 1, 0, Vec::new()),
+            Err(ImageError::UnsupportedChannels(0))
+        ));
+        assert!(matches!(
+            Image::from_raw(1, 1, 5, vec![0; 5]),
+            Err(ImageError::UnsupportedChannels(5))
+        ));
+        assert!(matches!(
+            Image::from_raw(u32::MAX, u32::MAX, 4, vec![0; 4]),
+            Err(ImageError::InvalidSize { .. })
+        ));
+        assert!(Image::from_raw(0, 5, 3, Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn flips_rows_and_columns() {
+        for height in [1, 4, 5] {
+            let original = gradient(3, height, 2);
+            let mut flipped = original.clone();
+            flipped.flip_vertical();
+            for y in 0..height {
+                for x in 0..3 {
+                    assert_eq!(
+                        flipped.get_pixel(x, y),
+                        original.get_pixel(x, height - y - 1)
+                    );
+                }
+            }
+            flipped.flip_vertical();
+            assert_eq!(flipped.get_data(), original.get_data());
+        }
+
+        let original = gradient(5, 2, 3);
+        let mut flipped = original.clone();
+        flipped.flip_horizontal();
+        assert_eq!(flipped.get_pixel(0, 1), original.get_pixel(4, 1));
+        assert_eq!(flipped.get_pixel(2, 0), original.get_pixel(2, 0));
+    }
+
+    #[test]
+    fn crops_and_resizes() {
+        let original = gradient(4, 4, 1);
+        let cropped = original.crop(1, 2, 2, 2);
+        assert_eq!(cropped.get_data(), [9, 10, 13, 14]);
+
+        assert_eq!(original.resize(4, 4).get_data(), original.get_data());
+        let mut flat = Image::new(8, 8, 3);
+        flat.get_data_mut().fill(77);
+        assert!(flat
+            .resize(3, 5)
+            .get_data()
+            .iter()
+            .all(|&value| value == 77));
+    }
+
+    #[test]
+    fn converts_channels_and_alpha() {
+        let grey = Image::from_raw(1, 1, 2, vec![200, 100]).unwrap();
+        assert_eq!(grey.to_rgba().get_data(), [200, 200, 200, 100]);
+
+        let mut rgba = Image::from_raw(2, 1, 4, vec![255, 128, 0, 128, 10, 20, 30, 0]).unwrap();
+        rgba.premultiply_alpha();
+        assert_eq!(rgba.get_data(), [128, 64, 0, 128, 0, 0, 0, 0]);
+
+        let mut rgb = gradient(2, 2, 3);
+        rgb.premultiply_alpha();
+        assert_eq!(rgb.get_data(), gradient(2, 2, 3).get_data());
+    }
+
+    #[test]
+    fn round_trips_through_png() {
+        for channels in 1..=4 {
+            let original = gradient(7, 5, channels);
+            let decoded = Image::from_memory(&original.encode_png().unwrap()).unwrap();
+            assert_eq!(decoded.get_width(), 7);
+            assert_eq!(decoded.get_height(), 5);
+            assert_eq!(decoded.get_channels(), channels);
+            assert_eq!(decoded.get_data(), original.get_data());
+        }
+
+        let path = std::env::temp_dir().join("engine_image_round_trip.png");
+        let original = gradient(16, 9, 4);
+        original.save_png(&path).unwrap();
+        let loaded = Image::load(&path).unwrap();
+        assert_eq!(loaded.get_data(), original.get_data());
+    }
+
+    #[test]
+    fn rejects_invalid_encoded_data() {
+        assert!(matches!(
+            Image::from_memory(b"not an image"),
+            Err(ImageError::Decode(_))
+        ));
+    }
+}
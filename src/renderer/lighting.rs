@@ -0,0 +1,394 @@
+use std::mem;
+use std::sync::Arc;
+
+use cgmath::*;
+
+use crate::asset::{AssetError, AssetServer};
+use crate::math::AABB;
+use crate::renderer::{
+    quad_indices, reserve_quad_indices, BlendMode, BufferElement, BufferLayout, Framebuffer,
+    FramebufferSpec, IndexBuffer, RenderCommand, RenderStats, ShaderDataType, ShaderProgram,
+    TextureFormat, VertexArray, VertexBuffer,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// Shines in every direction up to `radius`.
+    Point,
+    /// Cone of `angle` radians either side of `direction`, fading out over the last
+    /// `softness` radians.
+    Spot {
+        direction: Vector2<f32>,
+        angle: f32,
+        softness: f32,
+    },
+    /// Lights the whole scene from far away, e.g. the moon. `direction` is where the light
+    /// travels, position and radius are ignored.
+    Directional { direction: Vector2<f32> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Vector2<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    pub radius: f32,
+    /// Exponent of the fade towards `radius`, 1 is linear and higher values fade sooner.
+    pub falloff: f32,
+    /// Distance above the scene, how grazing the light hits normal mapped sprites.
+    pub height: f32,
+    pub casts_shadows: bool,
+}
+
+impl Light {
+    pub fn point(position: Vector2<f32>, color: Vector3<f32>, radius: f32) -> Self {
+        Light {
+            kind: LightKind::Point,
+            position,
+            color,
+            intensity: 1.0,
+            radius,
+            falloff: 2.0,
+            height: 1.0,
+            casts_shadows: true,
+        }
+    }
+
+    pub fn spot(
+        position: Vector2<f32>,
+        direction: Vector2<f32>,
+        angle: f32,
+        color: Vector3<f32>,
+        radius: f32,
+    ) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                direction: direction.normalize(),
+                angle,
+                softness: angle * 0.2,
+            },
+            ..Self::point(position, color, radius)
+        }
+    }
+
+    pub fn directional(direction: Vector2<f32>, color: Vector3<f32>) -> Self {
+        Light {
+            kind: LightKind::Directional {
+                direction: direction.normalize(),
+            },
+            ..Self::point(Vector2::zero(), color, 0.0)
+        }
+    }
+}
+
+/// Closed polygon blocking light, e.g. a wall or a pillar. The polygon itself is left in
+/// shadow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occluder {
+    pub points: Vec<Vector2<f32>>,
+}
+
+impl Occluder {
+    pub fn new(points: Vec<Vector2<f32>>) -> Self {
+        Occluder { points }
+    }
+
+    pub fn rectangle(position: Vector2<f32>, size: Vector2<f32>) -> Self {
+        let half = size / 2.0;
+        Self::new(vec![
+            position + Vector2::new(-half.x, -half.y),
+            position + Vector2::new(half.x, -half.y),
+            position + Vector2::new(half.x, half.y),
+            position + Vector2::new(-half.x, half.y),
+        ])
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>)> + '_ {
+        let next = self.points.iter().cycle().skip(1);
+        self.points.iter().copied().zip(next.copied())
+    }
+}
+
+// Room for this many shadow and light quads at first, the buffers grow when needed
+const INITIAL_GEOMETRY_QUADS: u32 = 1024;
+
+const KIND_POINT: i32 = 0;
+const KIND_SPOT: i32 = 1;
+const KIND_DIRECTIONAL: i32 = 2;
+
+// Light accumulation with stencil shadows. Sprites are drawn to a color and a normal
+// buffer, every light is added to a light buffer and the two are multiplied onto the screen.
+pub(crate) struct Lighting {
+    pub(crate) ambient: Vector3<f32>,
+    pub(crate) lights: Vec<Light>,
+    pub(crate) occluders: Vec<Occluder>,
+
+    scene_buffer: Framebuffer, // color and normals, with the stencil for the renderer's masks
+    light_buffer: Framebuffer, // accumulated light, with the stencil for shadows
+    window_viewport: (i32, i32, u32, u32),
+    scissor_test: bool, // restored with the window viewport
+
+    geometry_vertex_array: VertexArray,
+    geometry_vertex_buffer: Arc<VertexBuffer>,
+    light_shader: ShaderProgram,
+    composite_shader: ShaderProgram,
+}
+
+impl Lighting {
+    pub(crate) fn new(assets: &AssetServer) -> Result<Self, AssetError> {
+        let mut light_shader = assets.load_owned::<ShaderProgram>("light")?;
+        for uniform in [
+            "u_view_projection",
+            "u_screen_size",
+            "u_light_kind",
+            "u_light_position",
+            "u_light_height",
+            "u_light_color",
+            "u_light_radius",
+            "u_light_falloff",
+            "u_light_direction",
+            "u_spot_cos_outer",
+            "u_spot_cos_inner",
+        ] {
+            light_shader.create_uniform(uniform);
+        }
+
+        let mut composite_shader = assets.load_owned::<ShaderProgram>("composite")?;
+        composite_shader.create_uniform("u_ambient");
+
+        let mut geometry_vertex_buffer =
+            VertexBuffer::new(INITIAL_GEOMETRY_QUADS as usize * 4 * mem::size_of::<Vector2<f32>>());
+        geometry_vertex_buffer.set_layout(BufferLayout::new(vec![BufferElement::new(
+            String::from("a_position"),
+            ShaderDataType::Float2,
+            None,
+        )]));
+        let geometry_vertex_buffer = Arc::new(geometry_vertex_buffer);
+
+        let mut geometry_vertex_array = VertexArray::new();
+        geometry_vertex_array.add_vertex_buffer(geometry_vertex_buffer.clone());
+        geometry_vertex_array.set_index_buffer(Arc::new(IndexBuffer::new(quad_indices(
+            INITIAL_GEOMETRY_QUADS,
+        ))));
+
+        let (_, _, width, height) = RenderCommand::get_viewport();
+
+        Ok(Lighting {
+            ambient: Vector3::new(0.1, 0.1, 0.1),
+            lights: Vec::new(),
+            occluders: Vec::new(),
+
+            scene_buffer: Framebuffer::new(FramebufferSpec {
+                width,
+                height,
+                color_attachments: vec![TextureFormat::RGBA8, TextureFormat::RGBA8],
+                depth_stencil: true,
+            }),
+            light_buffer: Framebuffer::new(FramebufferSpec {
+                width,
+                height,
+                color_attachments: vec![TextureFormat::RGBA16F],
+                depth_stencil: true,
+            }),
+            window_viewport: (0, 0, width, height),
+            scissor_test: false,
+
+            geometry_vertex_array,
+            geometry_vertex_buffer,
+            light_shader,
+            composite_shader,
+        })
+    }
+
+    /// Redirects the scene's draws to the scene buffer.
+    pub(crate) fn begin(&mut self) {
+        self.window_viewport = RenderCommand::get_viewport();
+        let (_, _, width, height) = self.window_viewport;
+
+        // The scissor of a camera's viewport is in window pixels, the buffers start at 0
+        self.scissor_test = RenderCommand::is_scissor_test_enabled();
+        RenderCommand::set_scissor_test(false);
+
+        self.scene_buffer.resize(width, height);
+        self.light_buffer.resize(width, height);
+
+        self.scene_buffer.bind();
+        self.scene_buffer
+            .clear_color_attachment(0, Vector4::new(0.0, 0.0, 0.0, 0.0));
+        // Surfaces without a normal map face the camera
+        self.scene_buffer
+            .clear_color_attachment(1, Vector4::new(0.5, 0.5, 1.0, 0.0));
+        self.scene_buffer.clear_depth_stencil();
+
+        self.lights.clear();
+        self.occluders.clear();
+    }
+
+    /// Lights the scene drawn since `begin` and draws it to the window.
+    pub(crate) fn end(
+        &mut self,
+        view_projection: &Matrix4<f32>,
+        visible_bounds: &AABB,
+        stats: &mut RenderStats,
+    ) {
+        self.light_buffer.bind();
+        self.light_buffer
+            .clear_color_attachment(0, Vector4::new(0.0, 0.0, 0.0, 0.0));
+
+        self.scene_buffer.get_color_attachment(1).bind(0);
+        self.light_shader.bind();
+        self.light_shader
+            .set_matrix4fv_uniform("u_view_projection", view_projection);
+        self.light_shader.set_vector2f_uniform(
+            "u_screen_size",
+            &Vector2::new(
+                self.light_buffer.get_width() as f32,
+                self.light_buffer.get_height() as f32,
+            ),
+        );
+
+        RenderCommand::set_blend_mode(BlendMode::Additive);
+
+        for light in &self.lights {
+            self.draw_light(light, visible_bounds, stats);
+        }
+
+        Framebuffer::unbind();
+        let (x, y, width, height) = self.window_viewport;
+        RenderCommand::set_viewport(x, y, width, height);
+        RenderCommand::set_scissor_test(self.scissor_test);
+
+        self.scene_buffer.get_color_attachment(0).bind(0);
+        self.light_buffer.get_color_attachment(0).bind(1);
+        self.composite_shader.bind();
+        self.composite_shader
+            .set_vector3f_uniform("u_ambient", &self.ambient);
+
+        let screen =
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| Vector2::new(x, y));
+        // The scene buffer holds premultiplied colors, see `BlendMode::Alpha`
+        RenderCommand::set_blend_mode(BlendMode::Premultiplied);
+        self.draw_geometry(&screen, stats);
+        RenderCommand::set_blend_mode(BlendMode::Alpha);
+        stats.texture_binds += 3;
+    }
+
+    fn draw_light(&self, light: &Light, visible_bounds: &AABB, stats: &mut RenderStats) {
+        let shadowed = light.casts_shadows && !self.occluders.is_empty();
+        if shadowed {
+            self.draw_shadows(light, visible_bounds, stats);
+        }
+
+        let shader = &self.light_shader;
+        let (kind, direction, cos_outer, cos_inner) = match light.kind {
+            LightKind::Point => (KIND_POINT, Vector2::zero(), -1.0, -1.0),
+            LightKind::Spot {
+                direction,
+                angle,
+                softness,
+            } => (
+                KIND_SPOT,
+                direction,
+                angle.cos(),
+                (angle - softness).max(0.0).cos(),
+            ),
+            LightKind::Directional { direction } => (KIND_DIRECTIONAL, direction, -1.0, -1.0),
+        };
+
+        shader.set_int_uniform("u_light_kind", kind);
+        shader.set_vector2f_uniform("u_light_position", &light.position);
+        shader.set_float_uniform("u_light_height", light.height);
+        shader.set_vector3f_uniform("u_light_color", &(light.color * light.intensity));
+        shader.set_float_uniform("u_light_radius", light.radius);
+        shader.set_float_uniform("u_light_falloff", light.falloff);
+        shader.set_vector2f_uniform("u_light_direction", &direction);
+        shader.set_float_uniform("u_spot_cos_outer", cos_outer);
+        shader.set_float_uniform("u_spot_cos_inner", cos_inner);
+
+        let bounds = match light.kind {
+            LightKind::Directional { .. } => *visible_bounds,
+            _ => {
+                let extent = Vector2::new(light.radius, light.radius);
+                AABB::new(light.position - extent, light.position + extent)
+            }
+        };
+        let (min, max) = (bounds.min(), bounds.max());
+        let quad = [
+            min,
+            Vector2::new(max.x, min.y),
+            max,
+            Vector2::new(min.x, max.y),
+        ];
+        self.draw_geometry(&quad, stats);
+
+        if shadowed {
+            unsafe {
+                gl::Disable(gl::STENCIL_TEST);
+            }
+        }
+        stats.lights += 1;
+    }
+
+    // Marks the areas occluders hide from the light in the stencil buffer and leaves the
+    // stencil test on so the light skips them
+    fn draw_shadows(&self, light: &Light, visible_bounds: &AABB, stats: &mut RenderStats) {
+        let view_size = visible_bounds.max() - visible_bounds.min();
+        // Far enough to leave whatever the light or the camera covers
+        let length = (light.radius + view_size.magnitude()) * 2.0;
+
+        let mut vertices = Vec::new();
+        for occluder in &self.occluders {
+            for (a, b) in occluder.edges() {
+                let extrude = |point: Vector2<f32>| match light.kind {
+                    LightKind::Directional { direction } => Some(point + direction * length),
+                    _ => {
+                        let away = point - light.position;
+                        (away.magnitude2() > f32::EPSILON)
+                            .then(|| point + away.normalize() * length)
+                    }
+                };
+
+                if let (Some(far_a), Some(far_b)) = (extrude(a), extrude(b)) {
+                    vertices.extend_from_slice(&[a, b, far_b, far_a]);
+                }
+            }
+        }
+
+        self.light_buffer.clear_depth_stencil();
+        unsafe {
+            gl::Enable(gl::STENCIL_TEST);
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl::StencilFunc(gl::ALWAYS, 1, 0xff);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+        }
+
+        self.draw_geometry(&vertices, stats);
+
+        unsafe {
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::StencilFunc(gl::EQUAL, 0, 0xff);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+        }
+    }
+
+    // Draws quads given as 4 corners each with whatever shader is bound
+    fn draw_geometry(&self, vertices: &[Vector2<f32>], stats: &mut RenderStats) {
+        if vertices.is_empty() {
+            return;
+        }
+
+        let base_vertex = self.geometry_vertex_buffer.stream(vertices);
+        let quad_count = vertices.len() as u32 / 4;
+        reserve_quad_indices(self.geometry_vertex_array.get_index_buffer(), quad_count);
+        RenderCommand::draw_indexed_base_vertex(
+            &self.geometry_vertex_array,
+            quad_count * 6,
+            base_vertex,
+        );
+
+        stats.draw_calls += 1;
+        stats.vertices += vertices.len() as u32;
+        stats.bytes_uploaded += mem::size_of_val(vertices);
+    }
+}
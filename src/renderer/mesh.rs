@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use cgmath::*;
+
+use crate::renderer::{IndexBuffer, Vertex, VertexArray, VertexBuffer};
+
+/// Vertex as the "mesh" shader reads it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+pub struct MeshVertex {
+    pub position: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    pub texture_coord: Vector2<f32>,
+}
+
+/// Indexed triangles uploaded once, drawn with `Renderer3D::draw_mesh`.
+pub struct Mesh {
+    vertex_array: VertexArray,
+    vertex_count: u32,
+}
+
+impl Mesh {
+    /// Triangle list, three indices per triangle, counter-clockwise when seen from the front.
+    pub fn new(vertices: &[MeshVertex], indices: Vec<u32>) -> Self {
+        let mut vertex_buffer = VertexBuffer::new_static(vertices);
+        vertex_buffer.set_layout(MeshVertex::layout());
+
+        Self::from_buffers(
+            vertex_buffer,
+            IndexBuffer::new(indices),
+            vertices.len() as u32,
+        )
+    }
+
+    /// Mesh drawing `index_buffer` from `vertex_buffer`, whose layout has to match
+    /// `MeshVertex::layout`.
+    pub fn from_buffers(
+        vertex_buffer: VertexBuffer,
+        index_buffer: IndexBuffer,
+        vertex_count: u32,
+    ) -> Self {
+        let mut vertex_array = VertexArray::new();
+        vertex_array.add_vertex_buffer(Arc::new(vertex_buffer));
+        vertex_array.set_index_buffer(Arc::new(index_buffer));
+
+        Mesh {
+            vertex_array,
+            vertex_count,
+        }
+    }
+
+    /// Cube of `size` centered on the origin, each face showing the whole texture.
+    pub fn cube(size: f32) -> Self {
+        let half = size / 2.0;
+        let faces = [
+            (Vector3::unit_x(), Vector3::unit_y()),
+            (-Vector3::unit_x(), Vector3::unit_y()),
+            (Vector3::unit_y(), -Vector3::unit_z()),
+            (-Vector3::unit_y(), Vector3::unit_z()),
+            (Vector3::unit_z(), Vector3::unit_y()),
+            (-Vector3::unit_z(), Vector3::unit_y()),
+        ];
+
+        let mut vertices = Vec::with_capacity(24);
+        for (normal, up) in faces {
+            let right = up.cross(normal);
+            for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                vertices.push(MeshVertex {
+                    position: (normal + right * x + up * y) * half,
+                    normal,
+                    texture_coord: Vector2::new((x + 1.0) / 2.0, (y + 1.0) / 2.0),
+                });
+            }
+        }
+
+        Self::new(&vertices, quad_triangles(6))
+    }
+
+    /// Square of `size` on the xz plane facing up, e.g. a floor.
+    pub fn plane(size: f32) -> Self {
+        let half = size / 2.0;
+        let vertices =
+            [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)].map(|(x, z)| MeshVertex {
+                position: Vector3::new(x * half, 0.0, z * half),
+                normal: Vector3::unit_y(),
+                texture_coord: Vector2::new((x + 1.0) / 2.0, (1.0 - z) / 2.0),
+            });
+
+        Self::new(&vertices, quad_triangles(1))
+    }
+
+    pub fn get_vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    pub fn get_vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    pub fn get_index_count(&self) -> u32 {
+        self.vertex_array.get_index_buffer().get_count()
+    }
+}
+
+// Two triangles for every 4 vertices going counter-clockwise
+fn quad_triangles(quad_count: u32) -> Vec<u32> {
+    (0..quad_count)
+        .flat_map(|quad| [0, 1, 2, 2, 3, 0].map(|index| quad * 4 + index))
+        .collect()
+}
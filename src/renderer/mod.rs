@@ -1,15 +1,53 @@
+pub mod blend_mode;
 pub mod buffer;
 pub mod camera;
+pub mod camera_controller;
+pub mod clip;
+pub mod framebuffer;
+pub mod gpu_timer;
+pub mod gradient;
+pub mod image;
+pub mod lighting;
+pub mod mesh;
+pub mod nine_slice;
+pub mod path;
+pub mod perspective_camera;
 pub mod render_command;
+pub mod render_stats;
 pub mod renderer;
+pub mod renderer_3d;
+pub mod resource_tracker;
 pub mod shader;
+pub mod sub_texture;
+pub mod tessellator;
 pub mod texture_2d;
+pub mod vertex;
 pub mod vertex_array;
+pub mod viewport;
 
+pub use self::blend_mode::*;
 pub use self::buffer::*;
 pub use self::camera::*;
+pub use self::camera_controller::*;
+pub use self::clip::*;
+pub use self::framebuffer::*;
+pub use self::gpu_timer::*;
+pub use self::gradient::*;
+pub use self::image::*;
+pub use self::lighting::*;
+pub use self::mesh::*;
+pub use self::nine_slice::*;
+pub use self::path::*;
+pub use self::perspective_camera::*;
 pub use self::render_command::*;
+pub use self::render_stats::*;
 pub use self::renderer::*;
+pub use self::renderer_3d::*;
+pub use self::resource_tracker::*;
 pub use self::shader::*;
+pub use self::sub_texture::*;
+pub use self::tessellator::*;
 pub use self::texture_2d::*;
+pub use self::vertex::*;
 pub use self::vertex_array::*;
+pub use self::viewport::*;
@@ -0,0 +1,397 @@
+use cgmath::*;
+
+use crate::renderer::SubTexture;
+
+/// How the parts of a sliced sprite between its borders fill their area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceFill {
+    Stretch,
+    /// Repeats the middle of the image, cutting the last copy short.
+    Tile,
+}
+
+/// Splits a sprite into a 3x3 grid whose corners keep their size while the edges and the
+/// center fill the rest, so one panel texture can frame any size. A three-slice, e.g. a
+/// button stretching only horizontally, is a nine-slice with two borders at zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NineSlice {
+    /// Border widths in texture pixels.
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+    /// Part of the texture holding the image, e.g. one panel of an atlas.
+    pub sub_texture: SubTexture,
+    pub fill: SliceFill,
+    /// Size a texture pixel is drawn at: world units with `Renderer`, pixels in the UI.
+    pub scale: f32,
+}
+
+// Part of a sliced sprite, in the space the sprite is drawn in with y up
+pub(crate) struct SlicePatch {
+    pub(crate) min: Vector2<f32>,
+    pub(crate) max: Vector2<f32>,
+    pub(crate) sub_texture: SubTexture,
+}
+
+// Start, end and texture coordinates of a band along one axis
+struct Segment {
+    start: f32,
+    end: f32,
+    texture_start: f32,
+    texture_end: f32,
+}
+
+// More copies than that are stretched instead, the area is far too big for the image
+const MAX_TILES: f32 = 1024.0;
+
+impl NineSlice {
+    pub fn new(left: f32, right: f32, bottom: f32, top: f32) -> Self {
+        NineSlice {
+            left,
+            right,
+            bottom,
+            top,
+            sub_texture: SubTexture::full(),
+            fill: SliceFill::Stretch,
+            scale: 1.0,
+        }
+    }
+
+    pub fn uniform(border: f32) -> Self {
+        Self::new(border, border, border, border)
+    }
+
+    /// Three-slice whose left and right ends keep their width.
+    pub fn horizontal(left: f32, right: f32) -> Self {
+        Self::new(left, right, 0.0, 0.0)
+    }
+
+    /// Three-slice whose bottom and top ends keep their height.
+    pub fn vertical(bottom: f32, top: f32) -> Self {
+        Self::new(0.0, 0.0, bottom, top)
+    }
+
+    /// Quads covering `size` around `position` for a texture of `texture_size` pixels.
+    /// Borders are shrunk when they do not fit.
+    pub(crate) fn patches(
+        &self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        texture_size: Vector2<u32>,
+    ) -> Vec<SlicePatch> {
+        let min = position - size / 2.0;
+        let region = self.sub_texture.max - self.sub_texture.min;
+
+        let columns = self.segments(
+            min.x,
+            size.x,
+            (self.left, self.right),
+            (self.sub_texture.min.x, self.sub_texture.max.x),
+            region.x * texture_size.x as f32,
+        );
+        let rows = self.segments(
+            min.y,
+            size.y,
+            (self.bottom, self.top),
+            (self.sub_texture.min.y, self.sub_texture.max.y),
+            region.y * texture_size.y as f32,
+        );
+
+        let mut patches = Vec::with_capacity(columns.len() * rows.len());
+        for row in &rows {
+            for column in &columns {
+                patches.push(SlicePatch {
+                    min: Vector2::new(column.start, row.start),
+                    max: Vector2::new(column.end, row.end),
+                    sub_texture: SubTexture::new(
+                        Vector2::new(column.texture_start, row.texture_start),
+                        Vector2::new(column.texture_end, row.texture_end),
+                    ),
+                });
+            }
+        }
+        patches
+    }
+
+    fn segments(
+        &self,
+        start: f32,
+        length: f32,
+        borders: (f32, f32),
+        texture_range: (f32, f32),
+        pixels: f32,
+    ) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        if length <= 0.0 || pixels <= 0.0 {
+            return segments;
+        }
+
+        let texture_per_pixel = (texture_range.1 - texture_range.0) / pixels;
+        let inner_start = texture_range.0 + borders.0 * texture_per_pixel;
+        let inner_end = texture_range.1 - borders.1 * texture_per_pixel;
+
+        let mut first = borders.0 * self.scale;
+        let mut last = borders.1 * self.scale;
+        if first + last > length {
+            let shrink = length / (first + last);
+            first *= shrink;
+            last *= shrink;
+        }
+
+        let end = start + length;
+        let (middle_start, middle_end) = (start + first, end - last);
+
+        if first > 0.0 {
+            segments.push(Segment {
+                start,
+                end: middle_start,
+                texture_start: texture_range.0,
+                texture_end: inner_start,
+            });
+        }
+
+        if middle_end > middle_start {
+            let tile = (pixels - borders.0 - borders.1) * self.scale;
+            let tiled = self.fill == SliceFill::Tile
+                && tile > 0.0
+                && (middle_end - middle_start) / tile <= MAX_TILES;
+
+            if tiled {
+                // Counted up front, far from the origin adding a tile to a position may not
+                // move it at all
+                let middle = middle_end - middle_start;
+                let count = ((middle / tile).ceil() as u32).max(1);
+                for index in 0..count {
+                    let tile_start = middle_start + tile * index as f32;
+                    let (tile_end, fraction) = if index + 1 == count {
+                        let remainder = middle - tile * index as f32;
+                        (middle_end, (remainder / tile).clamp(0.0, 1.0))
+                    } else {
+                        (middle_start + tile * (index + 1) as f32, 1.0)
+                    };
+                    segments.push(Segment {
+                        start: tile_start,
+                        end: tile_end,
+                        texture_start: inner_start,
+                        texture_end: inner_start + (inner_end - inner_start) * fraction,
+                    });
+                }
+            } else {
+                segments.push(Segment {
+                    start: middle_start,
+                    end: middle_end,
+                    texture_start: inner_start,
+                    texture_end: inner_end,
+                });
+            }
+        }
+
+        if last > 0.0 {
+            segments.push(Segment {
+                start: middle_end,
+                end,
+                texture_start: inner_end,
+                texture_end: texture_range.1,
+            });
+        }
+
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Left edges and texture coordinates of the columns of the bottom row
+    fn columns(patches: &[SlicePatch]) -> Vec<(f32, f32, f32, f32)> {
+        let bottom = patches[0].min.y;
+        patches
+            .iter()
+            .filter(|patch| patch.min.y == bottom)
+            .map(|patch| {
+                (
+                    patch.min.x,
+                    patch.max.x,
+                    patch.sub_texture.min.x,
+                    patch.sub_texture.max.x,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_borders_and_stretches_the_middle() {
+        let slice = NineSlice::uniform(4.0);
+        let patches = slice.patches(
+            Vector2::zero(),
+            Vector2::new(32.0, 32.0),
+            Vector2::new(16, 16),
+        );
+
+        assert_eq!(patches.len(), 9);
+        assert_eq!(
+            columns(&patches),
+            [
+                (-16.0, -12.0, 0.0, 0.25),
+                (-12.0, 12.0, 0.25, 0.75),
+                (12.0, 16.0, 0.75, 1.0)
+            ]
+        );
+        assert_eq!(patches[0].min, Vector2::new(-16.0, -16.0));
+        assert_eq!(patches[8].max, Vector2::new(16.0, 16.0));
+    }
+
+    #[test]
+    fn scales_borders() {
+        let slice = NineSlice {
+            scale: 0.5,
+            ..NineSlice::uniform(4.0)
+        };
+        let patches = slice.patches(
+            Vector2::zero(),
+            Vector2::new(8.0, 8.0),
+            Vector2::new(16, 16),
+        );
+        assert_eq!(
+            columns(&patches),
+            [
+                (-4.0, -2.0, 0.0, 0.25),
+                (-2.0, 2.0, 0.25, 0.75),
+                (2.0, 4.0, 0.75, 1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn shrinks_borders_that_do_not_fit() {
+        let slice = NineSlice::new(4.0, 12.0, 4.0, 4.0);
+        let patches = slice.patches(
+            Vector2::zero(),
+            Vector2::new(4.0, 32.0),
+            Vector2::new(32, 16),
+        );
+
+        // The borders keep their proportions and leave no room for the middle
+        assert_eq!(patches.len(), 6);
+        assert_eq!(
+            columns(&patches),
+            [(-2.0, -1.0, 0.0, 0.125), (-1.0, 2.0, 0.625, 1.0)]
+        );
+    }
+
+    #[test]
+    fn three_slices_have_three_patches() {
+        let slice = NineSlice::horizontal(4.0, 4.0);
+        let patches = slice.patches(
+            Vector2::zero(),
+            Vector2::new(32.0, 8.0),
+            Vector2::new(16, 8),
+        );
+        assert_eq!(patches.len(), 3);
+        assert!(patches
+            .iter()
+            .all(|patch| patch.sub_texture.min.y == 0.0 && patch.sub_texture.max.y == 1.0));
+    }
+
+    #[test]
+    fn tiles_the_middle_and_cuts_the_last_copy() {
+        let slice = NineSlice {
+            fill: SliceFill::Tile,
+            ..NineSlice::horizontal(4.0, 4.0)
+        };
+        let patches = slice.patches(
+            Vector2::new(14.0, 8.0),
+            Vector2::new(28.0, 16.0),
+            Vector2::new(16, 16),
+        );
+
+        assert_eq!(
+            columns(&patches),
+            [
+                (0.0, 4.0, 0.0, 0.25),
+                (4.0, 12.0, 0.25, 0.75),
+                (12.0, 20.0, 0.25, 0.75),
+                (20.0, 24.0, 0.25, 0.5),
+                (24.0, 28.0, 0.75, 1.0)
+            ]
+        );
+        // A middle exactly one tile tall is a single copy
+        assert_eq!(patches.len(), 5);
+    }
+
+    #[test]
+    fn tiles_inside_the_sub_texture() {
+        let slice = NineSlice {
+            fill: SliceFill::Tile,
+            sub_texture: SubTexture::new(Vector2::new(0.5, 0.0), Vector2::new(1.0, 1.0)),
+            ..NineSlice::horizontal(4.0, 4.0)
+        };
+        let patches = slice.patches(
+            Vector2::new(10.0, 8.0),
+            Vector2::new(20.0, 16.0),
+            Vector2::new(32, 16),
+        );
+
+        assert_eq!(
+            columns(&patches),
+            [
+                (0.0, 4.0, 0.5, 0.625),
+                (4.0, 12.0, 0.625, 0.875),
+                (12.0, 16.0, 0.625, 0.75),
+                (16.0, 20.0, 0.875, 1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn tiles_far_from_the_origin() {
+        let slice = NineSlice {
+            fill: SliceFill::Tile,
+            ..NineSlice::horizontal(4.0, 4.0)
+        };
+        let patches = slice.patches(
+            Vector2::new(1.0e9, 0.0),
+            Vector2::new(1000.0, 16.0),
+            Vector2::new(16, 16),
+        );
+
+        // Rounding changes the number of copies out there, but they still end and leave no gaps
+        assert!(patches.len() > 100);
+        for pair in patches.windows(2) {
+            assert_eq!(pair[0].max.x, pair[1].min.x);
+        }
+        assert_eq!(patches[0].min.x, 1.0e9 - 500.0);
+        assert_eq!(patches.last().unwrap().max.x, 1.0e9 + 500.0);
+    }
+
+    #[test]
+    fn stretches_instead_of_tiling_too_many_copies() {
+        let slice = NineSlice {
+            fill: SliceFill::Tile,
+            ..NineSlice::horizontal(4.0, 4.0)
+        };
+        let patches = slice.patches(
+            Vector2::zero(),
+            Vector2::new(8.0 * 2000.0, 16.0),
+            Vector2::new(16, 16),
+        );
+        assert_eq!(patches.len(), 3);
+    }
+
+    #[test]
+    fn skips_empty_areas() {
+        let slice = NineSlice::uniform(4.0);
+        assert!(slice
+            .patches(
+                Vector2::zero(),
+                Vector2::new(0.0, 8.0),
+                Vector2::new(16, 16)
+            )
+            .is_empty());
+        assert!(slice
+            .patches(Vector2::zero(), Vector2::new(8.0, 8.0), Vector2::new(0, 16))
+            .is_empty());
+    }
+}
@@ -0,0 +1,412 @@
+use cgmath::*;
+
+// Limits the segments of a single curve, e.g. a huge curve seen from very close
+const MAX_CURVE_SEGMENTS: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(Vector2<f32>),
+    LineTo(Vector2<f32>),
+    /// Control point and end point.
+    QuadTo(Vector2<f32>, Vector2<f32>),
+    /// Two control points and end point.
+    CubicTo(Vector2<f32>, Vector2<f32>, Vector2<f32>),
+    Close,
+}
+
+/// Outline made of lines and curves, filled or stroked with `Renderer::fill_path` and
+/// `Renderer::stroke_path`. Every `move_to` starts a new contour, so a path can have holes.
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+    current: Option<Vector2<f32>>,
+    contour_start: Option<Vector2<f32>>,
+}
+
+/// A contour of a path flattened into points.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Vector2<f32>>,
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(&mut self, point: Vector2<f32>) -> &mut Self {
+        self.commands.push(PathCommand::MoveTo(point));
+        self.current = Some(point);
+        self.contour_start = Some(point);
+        self
+    }
+
+    /// Starts a contour at `point` if there is no current point.
+    pub fn line_to(&mut self, point: Vector2<f32>) -> &mut Self {
+        if self.current.is_none() {
+            return self.move_to(point);
+        }
+        self.commands.push(PathCommand::LineTo(point));
+        self.current = Some(point);
+        self
+    }
+
+    /// Quadratic Bézier curve to `point`.
+    pub fn quad_to(&mut self, control: Vector2<f32>, point: Vector2<f32>) -> &mut Self {
+        self.ensure_current(control);
+        self.commands.push(PathCommand::QuadTo(control, point));
+        self.current = Some(point);
+        self
+    }
+
+    /// Cubic Bézier curve to `point`.
+    pub fn cubic_to(
+        &mut self,
+        control_1: Vector2<f32>,
+        control_2: Vector2<f32>,
+        point: Vector2<f32>,
+    ) -> &mut Self {
+        self.ensure_current(control_1);
+        self.commands
+            .push(PathCommand::CubicTo(control_1, control_2, point));
+        self.current = Some(point);
+        self
+    }
+
+    /// Circular arc around `center` from `start_angle` to `end_angle` in radians, counter-
+    /// clockwise when the end is bigger. A line joins the current point to the start of the
+    /// arc, if there is one.
+    pub fn arc(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    ) -> &mut Self {
+        let point_at = |angle: f32| center + Vector2::new(angle.cos(), angle.sin()) * radius;
+        self.line_to(point_at(start_angle));
+
+        // Cubic curves are close enough to arcs of up to a quarter turn
+        let sweep = end_angle - start_angle;
+        let parts = (sweep.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.0) as u32;
+        let step = sweep / parts as f32;
+        let handle = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+
+        for part in 0..parts {
+            let from = start_angle + step * part as f32;
+            let to = from + step;
+            let tangent = |angle: f32| Vector2::new(-angle.sin(), angle.cos());
+            self.cubic_to(
+                point_at(from) + tangent(from) * handle,
+                point_at(to) - tangent(to) * handle,
+                point_at(to),
+            );
+        }
+        self
+    }
+
+    /// Connects the current point back to the start of the contour.
+    pub fn close(&mut self) -> &mut Self {
+        if self.current.is_some() {
+            self.commands.push(PathCommand::Close);
+            self.current = self.contour_start;
+        }
+        self
+    }
+
+    /// Closed rectangle contour between two opposite corners.
+    pub fn rect(&mut self, min: Vector2<f32>, max: Vector2<f32>) -> &mut Self {
+        self.move_to(min)
+            .line_to(Vector2::new(max.x, min.y))
+            .line_to(max)
+            .line_to(Vector2::new(min.x, max.y))
+            .close()
+    }
+
+    /// Closed rectangle contour with rounded corners, the radius being at most half of the
+    /// shortest side.
+    pub fn rounded_rect(&mut self, min: Vector2<f32>, max: Vector2<f32>, radius: f32) -> &mut Self {
+        let radius = radius
+            .min((max.x - min.x).abs() / 2.0)
+            .min((max.y - min.y).abs() / 2.0);
+        if radius <= 0.0 {
+            return self.rect(min, max);
+        }
+
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let (near, far) = (min.add_element_wise(radius), max.sub_element_wise(radius));
+        self.move_to(Vector2::new(near.x, min.y))
+            .arc(Vector2::new(far.x, near.y), radius, -FRAC_PI_2, 0.0)
+            .arc(far, radius, 0.0, FRAC_PI_2)
+            .arc(Vector2::new(near.x, far.y), radius, FRAC_PI_2, PI)
+            .arc(near, radius, PI, PI + FRAC_PI_2)
+            .close()
+    }
+
+    /// Closed circle contour.
+    pub fn circle(&mut self, center: Vector2<f32>, radius: f32) -> &mut Self {
+        self.move_to(center + Vector2::new(radius, 0.0))
+            .arc(center, radius, 0.0, std::f32::consts::TAU)
+            .close()
+    }
+
+    /// Copy of the path with every point moved by `map`. Curves stay exact for affine maps,
+    /// i.e. any mix of translation, rotation and scale.
+    pub fn map_points(&self, map: impl Fn(Vector2<f32>) -> Vector2<f32>) -> Path {
+        let commands = self
+            .commands
+            .iter()
+            .map(|command| match *command {
+                PathCommand::MoveTo(point) => PathCommand::MoveTo(map(point)),
+                PathCommand::LineTo(point) => PathCommand::LineTo(map(point)),
+                PathCommand::QuadTo(control, point) => {
+                    PathCommand::QuadTo(map(control), map(point))
+                }
+                PathCommand::CubicTo(control_1, control_2, point) => {
+                    PathCommand::CubicTo(map(control_1), map(control_2), map(point))
+                }
+                PathCommand::Close => PathCommand::Close,
+            })
+            .collect();
+
+        Path {
+            commands,
+            current: self.current.map(&map),
+            contour_start: self.contour_start.map(&map),
+        }
+    }
+
+    pub fn get_commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Replaces the curves with lines, straying at most `tolerance` from them.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(1e-6);
+        let mut polylines = Vec::new();
+        let mut polyline = Polyline::default();
+        let mut current = Vector2::zero();
+
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(point) => {
+                    finish_polyline(&mut polylines, &mut polyline, false);
+                    push_point(&mut polyline, point);
+                    current = point;
+                }
+                PathCommand::LineTo(point) => {
+                    push_point(&mut polyline, point);
+                    current = point;
+                }
+                PathCommand::QuadTo(control, point) => {
+                    let segments = quad_segments(current, control, point, tolerance);
+                    for segment in 1..=segments {
+                        let t = segment as f32 / segments as f32;
+                        push_point(&mut polyline, quad_point(current, control, point, t));
+                    }
+                    current = point;
+                }
+                PathCommand::CubicTo(control_1, control_2, point) => {
+                    let segments = cubic_segments(current, control_1, control_2, point, tolerance);
+                    for segment in 1..=segments {
+                        let t = segment as f32 / segments as f32;
+                        push_point(
+                            &mut polyline,
+                            cubic_point(current, control_1, control_2, point, t),
+                        );
+                    }
+                    current = point;
+                }
+                PathCommand::Close => {
+                    let start = polyline.points.first().copied();
+                    finish_polyline(&mut polylines, &mut polyline, true);
+                    // Drawing on without a move starts from the start of the closed contour
+                    if let Some(start) = start {
+                        push_point(&mut polyline, start);
+                        current = start;
+                    }
+                }
+            }
+        }
+        finish_polyline(&mut polylines, &mut polyline, false);
+
+        polylines
+    }
+
+    fn ensure_current(&mut self, fallback: Vector2<f32>) {
+        if self.current.is_none() {
+            self.move_to(fallback);
+        }
+    }
+}
+
+fn push_point(polyline: &mut Polyline, point: Vector2<f32>) {
+    if polyline.points.last() != Some(&point) {
+        polyline.points.push(point);
+    }
+}
+
+fn finish_polyline(polylines: &mut Vec<Polyline>, polyline: &mut Polyline, closed: bool) {
+    let mut finished = std::mem::take(polyline);
+    if closed && finished.points.len() > 1 && finished.points.first() == finished.points.last() {
+        finished.points.pop();
+    }
+    finished.closed = closed;
+
+    if finished.points.len() > 1 {
+        polylines.push(finished);
+    }
+}
+
+fn quad_point(from: Vector2<f32>, control: Vector2<f32>, to: Vector2<f32>, t: f32) -> Vector2<f32> {
+    let u = 1.0 - t;
+    from * (u * u) + control * (2.0 * u * t) + to * (t * t)
+}
+
+fn cubic_point(
+    from: Vector2<f32>,
+    control_1: Vector2<f32>,
+    control_2: Vector2<f32>,
+    to: Vector2<f32>,
+    t: f32,
+) -> Vector2<f32> {
+    let u = 1.0 - t;
+    from * (u * u * u)
+        + control_1 * (3.0 * u * u * t)
+        + control_2 * (3.0 * u * t * t)
+        + to * (t * t * t)
+}
+
+// A curve split in n parts strays from its chords by at most its second derivative / (8 n²)
+fn quad_segments(
+    from: Vector2<f32>,
+    control: Vector2<f32>,
+    to: Vector2<f32>,
+    tolerance: f32,
+) -> u32 {
+    let curvature = (from - control * 2.0 + to).magnitude();
+    segment_count(curvature / (4.0 * tolerance))
+}
+
+fn cubic_segments(
+    from: Vector2<f32>,
+    control_1: Vector2<f32>,
+    control_2: Vector2<f32>,
+    to: Vector2<f32>,
+    tolerance: f32,
+) -> u32 {
+    let curvature = (from - control_1 * 2.0 + control_2)
+        .magnitude()
+        .max((control_1 - control_2 * 2.0 + to).magnitude());
+    segment_count(3.0 * curvature / (4.0 * tolerance))
+}
+
+fn segment_count(squared: f32) -> u32 {
+    (squared.sqrt().ceil() as u32).clamp(1, MAX_CURVE_SEGMENTS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Distance from `point` to the closest segment of the polyline
+    fn distance_to(polyline: &Polyline, point: Vector2<f32>) -> f32 {
+        let points = &polyline.points;
+        let count = if polyline.closed {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        (0..count)
+            .map(|index| {
+                let (a, b) = (points[index], points[(index + 1) % points.len()]);
+                let t = ((point - a).dot(b - a) / (b - a).magnitude2()).clamp(0.0, 1.0);
+                (a + (b - a) * t - point).magnitude()
+            })
+            .fold(f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn flattens_lines_as_they_are() {
+        let mut path = Path::new();
+        path.rect(Vector2::new(0.0, 0.0), Vector2::new(2.0, 1.0));
+        path.move_to(Vector2::new(5.0, 5.0))
+            .line_to(Vector2::new(6.0, 5.0))
+            .line_to(Vector2::new(6.0, 5.0));
+
+        let polylines = path.flatten(0.1);
+        assert_eq!(polylines.len(), 2);
+        assert!(polylines[0].closed);
+        assert_eq!(polylines[0].points.len(), 4);
+        assert!(!polylines[1].closed);
+        // Repeated points are dropped
+        assert_eq!(polylines[1].points.len(), 2);
+    }
+
+    #[test]
+    fn flattens_curves_within_tolerance() {
+        let (from, control_1, control_2, to) = (
+            Vector2::new(0.0, 0.0),
+            Vector2::new(10.0, 30.0),
+            Vector2::new(40.0, -20.0),
+            Vector2::new(50.0, 10.0),
+        );
+        let mut quad = Path::new();
+        quad.move_to(from).quad_to(control_1, to);
+        let mut cubic = Path::new();
+        cubic.move_to(from).cubic_to(control_1, control_2, to);
+
+        let mut previous_counts = (0, 0);
+        for tolerance in [1.0, 0.25, 0.01] {
+            let quad_polyline = &quad.flatten(tolerance)[0];
+            let cubic_polyline = &cubic.flatten(tolerance)[0];
+            for sample in 0..=1000 {
+                let t = sample as f32 / 1000.0;
+                let on_quad = quad_point(from, control_1, to, t);
+                let on_cubic = cubic_point(from, control_1, control_2, to, t);
+                assert!(distance_to(quad_polyline, on_quad) <= tolerance * 1.01);
+                assert!(distance_to(cubic_polyline, on_cubic) <= tolerance * 1.01);
+            }
+
+            // Tighter tolerances take more points
+            let counts = (quad_polyline.points.len(), cubic_polyline.points.len());
+            assert!(counts.0 > previous_counts.0 && counts.1 > previous_counts.1);
+            previous_counts = counts;
+        }
+    }
+
+    #[test]
+    fn flattens_circles_within_tolerance() {
+        let mut path = Path::new();
+        path.circle(Vector2::new(0.0, 0.0), 100.0);
+
+        for tolerance in [2.0, 0.5, 0.1] {
+            let polylines = path.flatten(tolerance);
+            assert_eq!(polylines.len(), 1);
+            let circle = &polylines[0];
+            assert!(circle.closed);
+
+            for sample in 0..1000 {
+                let angle = sample as f32 / 1000.0 * std::f32::consts::TAU;
+                let point = Vector2::new(angle.cos(), angle.sin()) * 100.0;
+                // Plus the error of the cubic curves approximating the arcs
+                assert!(distance_to(circle, point) <= tolerance + 0.03);
+            }
+        }
+    }
+
+    #[test]
+    fn limits_curve_segments() {
+        let mut path = Path::new();
+        path.move_to(Vector2::new(0.0, 0.0))
+            .quad_to(Vector2::new(1e6, 1e6), Vector2::new(2e6, 0.0));
+        let polylines = path.flatten(0.0);
+        assert_eq!(polylines[0].points.len(), MAX_CURVE_SEGMENTS as usize + 1);
+    }
+}
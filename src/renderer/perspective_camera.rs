@@ -0,0 +1,141 @@
+use cgmath::*;
+
+use crate::renderer::Viewport;
+
+/// Camera for 3D scenes. Looks along -z when `yaw` and `pitch` are 0, y is up.
+pub struct PerspectiveCamera {
+    /// Vertical field of view in radians.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+
+    pub position: Vector3<f32>,
+    /// Radians turned to the right around the y axis.
+    pub yaw: f32,
+    /// Radians looking up, kept short of straight up or down by `recalculate_view`.
+    pub pitch: f32,
+
+    /// Part of the window the camera draws to, applied by `set_window_size`.
+    pub viewport: Viewport,
+    /// Fills the viewport before the scene is drawn when set.
+    pub clear_color: Option<Vector4<f32>>,
+
+    aspect_ratio: f32,
+    viewport_rect: (i32, i32, u32, u32),
+
+    projection: Matrix4<f32>,
+    view: Matrix4<f32>,
+}
+
+// Looking straight up or down leaves no way to tell where the right of the view is
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.001;
+
+impl PerspectiveCamera {
+    pub fn new(position: Vector3<f32>, fov: f32) -> Self {
+        Self {
+            fov,
+            near: 0.1,
+            far: 1000.0,
+
+            position,
+            yaw: 0.0,
+            pitch: 0.0,
+
+            viewport: Viewport::full(),
+            clear_color: None,
+
+            aspect_ratio: 1.0,
+            viewport_rect: (0, 0, 1, 1),
+
+            projection: Matrix4::identity(),
+            view: Matrix4::identity(),
+        }
+    }
+
+    /// Makes the camera cover a `width` by `height` render target, ignoring `viewport`.
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        self.set_viewport_rect((0, 0, width, height));
+    }
+
+    /// Places the camera's `viewport` in a window of `width` by `height` pixels and takes
+    /// the aspect ratio from it.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.set_viewport_rect(self.viewport.to_pixels(width, height));
+    }
+
+    fn set_viewport_rect(&mut self, rect: (i32, i32, u32, u32)) {
+        let (_, _, width, height) = rect;
+        self.aspect_ratio = width as f32 / height.max(1) as f32;
+        self.viewport_rect = rect;
+        self.recalculate_projection();
+    }
+
+    /// Pixel rectangle the camera draws to as x, y, width and height, y from the bottom.
+    pub fn get_viewport_rect(&self) -> (i32, i32, u32, u32) {
+        self.viewport_rect
+    }
+
+    pub fn get_aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    /// Turns the camera towards `target`.
+    pub fn look_at(&mut self, target: Vector3<f32>) {
+        let direction = target - self.position;
+        if direction.magnitude2() == 0.0 {
+            return;
+        }
+
+        let direction = direction.normalize();
+        self.yaw = direction.x.atan2(-direction.z);
+        self.pitch = direction.y.clamp(-1.0, 1.0).asin();
+        self.recalculate_view();
+    }
+
+    /// Unit vector the camera looks along.
+    pub fn get_forward(&self) -> Vector3<f32> {
+        let pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        Vector3::new(
+            pitch.cos() * self.yaw.sin(),
+            pitch.sin(),
+            -pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    pub fn get_right(&self) -> Vector3<f32> {
+        self.get_forward().cross(Vector3::unit_y()).normalize()
+    }
+
+    pub fn get_up(&self) -> Vector3<f32> {
+        self.get_right().cross(self.get_forward())
+    }
+
+    pub fn recalculate_projection(&mut self) {
+        self.projection = perspective(Rad(self.fov), self.aspect_ratio, self.near, self.far);
+    }
+
+    pub fn recalculate_view(&mut self) {
+        self.view = Matrix4::look_to_rh(
+            Point3::from_vec(self.position),
+            self.get_forward(),
+            Vector3::unit_y(),
+        );
+    }
+
+    pub fn update(&mut self) {
+        self.recalculate_view();
+        self.recalculate_projection();
+    }
+
+    pub fn get_projection(&self) -> Matrix4<f32> {
+        self.projection
+    }
+
+    pub fn get_view(&self) -> Matrix4<f32> {
+        self.view
+    }
+
+    pub fn get_view_projection(&self) -> Matrix4<f32> {
+        self.projection * self.view
+    }
+}
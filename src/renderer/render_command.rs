@@ -1,4 +1,6 @@
-use crate::renderer::VertexArray;
+use cgmath::*;
+
+use crate::renderer::{BlendMode, Image, VertexArray};
 
 pub struct RenderCommand;
 
@@ -9,9 +11,155 @@ impl RenderCommand {
         }
     }
 
+    /// Clears the color and the depth of the window.
     pub fn clear() {
         unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    /// Resets the depth to the far plane, inside the scissor if enabled.
+    pub fn clear_depth() {
+        unsafe {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    /// How the next draws combine with the render target.
+    pub fn set_blend_mode(mode: BlendMode) {
+        let [source_color, destination_color, source_alpha, destination_alpha] = mode.get_factors();
+        unsafe {
+            gl::BlendFuncSeparate(
+                source_color,
+                destination_color,
+                source_alpha,
+                destination_alpha,
+            );
+        }
+    }
+
+    /// Hides what is behind already drawn geometry. Off for 2D, which is layered in draw order.
+    pub fn set_depth_test(enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::LESS);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        }
+    }
+
+    /// Clears the color of the window to `color` without changing the clear color. Respects
+    /// the scissor, so it can clear a single viewport.
+    pub fn clear_to_color(color: Vector4<f32>) {
+        unsafe {
+            gl::ClearBufferfv(gl::COLOR, 0, color.as_ptr());
+        }
+    }
+
+    pub fn set_viewport(x: i32, y: i32, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(x, y, width as i32, height as i32);
+        }
+    }
+
+    /// Limits clears and draws to a rectangle of the render target, y from the bottom.
+    pub fn set_scissor(x: i32, y: i32, width: u32, height: u32) {
+        unsafe {
+            gl::Scissor(x, y, width as i32, height as i32);
+        }
+    }
+
+    pub fn set_scissor_test(enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::SCISSOR_TEST);
+            } else {
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+        }
+    }
+
+    pub fn is_scissor_test_enabled() -> bool {
+        unsafe { gl::IsEnabled(gl::SCISSOR_TEST) == gl::TRUE }
+    }
+
+    /// Current scissor rectangle as x, y, width and height.
+    pub fn get_scissor() -> (i32, i32, u32, u32) {
+        let mut scissor = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::SCISSOR_BOX, scissor.as_mut_ptr());
+        }
+        (scissor[0], scissor[1], scissor[2] as u32, scissor[3] as u32)
+    }
+
+    /// Resets the stencil to 0, inside the scissor if enabled.
+    pub fn clear_stencil() {
+        unsafe {
+            gl::ClearBufferiv(gl::STENCIL, 0, &0);
+        }
+    }
+
+    pub fn set_stencil_test(enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::STENCIL_TEST);
+            } else {
+                gl::Disable(gl::STENCIL_TEST);
+            }
+        }
+    }
+
+    /// Current viewport as x, y, width and height.
+    pub fn get_viewport() -> (i32, i32, u32, u32) {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        (
+            viewport[0],
+            viewport[1],
+            viewport[2] as u32,
+            viewport[3] as u32,
+        )
+    }
+
+    /// Reads a rectangle of the bound read framebuffer, y from the bottom, into an RGB image
+    /// with the first row at the top. The window's pixels are only defined while it is shown,
+    /// hidden windows have to be drawn through a `Framebuffer` to be read.
+    pub fn read_pixels(x: i32, y: i32, width: u32, height: u32) -> Image {
+        let mut data = vec![0u8; (width * height * 3) as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                x,
+                y,
+                width as i32,
+                height as i32,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                data.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+        }
+
+        let mut image = Image::from_raw(width, height, 3, data).unwrap();
+        image.flip_vertical();
+        image
+    }
+
+    /// Draws indexed triangles whose indices are offset by `base_vertex`, e.g. for vertices
+    /// written with `VertexBuffer::stream`.
+    pub fn draw_indexed_base_vertex(vertex_array: &VertexArray, count: u32, base_vertex: u32) {
+        vertex_array.bind();
+        unsafe {
+            gl::DrawElementsBaseVertex(
+                gl::TRIANGLES,
+                count as i32,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+                base_vertex as i32,
+            );
         }
     }
 
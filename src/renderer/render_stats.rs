@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// What the renderer did during a frame. The engine resets the stats once per frame rather
+/// than in `Renderer::begin_scene`, so frames drawn as several scenes, e.g. split-screen or
+/// a minimap, count all of them. Read during `Game::update` they describe the last frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub quads: u32,
+    pub vertices: u32,
+    pub batches: u32,
+    pub texture_binds: u32,
+    pub texture_slots_used: u32, // highest slot count used by a single batch
+    pub bytes_uploaded: usize,
+    pub lights: u32,
+    pub culled_quads: u32,
+    pub culled_chunks: u32, // tilemap chunks outside of the view
+
+    pub cpu_time: f32, // milliseconds spent flushing batches on the CPU
+    pub gpu_time: f32, // milliseconds, lags a few frames behind because of the timer queries
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Draw calls:    {}", self.draw_calls)?;
+        writeln!(f, "Batches:       {}", self.batches)?;
+        writeln!(f, "Quads:         {}", self.quads)?;
+        writeln!(f, "Vertices:      {}", self.vertices)?;
+        writeln!(
+            f,
+            "Texture binds: {} ({} slots)",
+            self.texture_binds, self.texture_slots_used
+        )?;
+        writeln!(
+            f,
+            "Uploaded:      {:.1} KiB",
+            self.bytes_uploaded as f32 / 1024.0
+        )?;
+        writeln!(f, "Lights:        {}", self.lights)?;
+        writeln!(
+            f,
+            "Culled:        {} quads, {} chunks",
+            self.culled_quads, self.culled_chunks
+        )?;
+        writeln!(f, "CPU flush:     {:.3} ms", self.cpu_time)?;
+        write!(f, "GPU flush:     {:.3} ms", self.gpu_time)
+    }
+}
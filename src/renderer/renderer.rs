@@ -1,24 +1,61 @@
+use crate::asset::{AssetError, AssetServer};
+use crate::math::AABB;
+use crate::particles::{ParticleEmitter, SimulationSpace};
 use crate::renderer::{
-    BufferElement, BufferLayout, Camera, IndexBuffer, RenderCommand, ShaderDataType, ShaderProgram,
-    Texture2D, VertexArray, VertexBuffer,
+    fill_polylines, gradient_cells, stroke_polylines, BlendMode, Camera, ClipStack, FillRule,
+    GpuTimer, Gradient, GradientCell, IndexBuffer, Light, Lighting, NineSlice, Occluder, Path,
+    RenderCommand, RenderStats, ScissorRect, ShaderProgram, StrokeStyle, SubTexture, Texture2D,
+    VectorMesh, Vertex, VertexArray, VertexBuffer,
 };
+use crate::tilemap::Tilemap;
 
 use cgmath::*;
-use std::ffi::c_void;
 use std::mem;
 use std::sync::Arc;
+use std::time::Instant;
 
 #[repr(C)]
-struct QuadVertex {
-    position: Vector3<f32>,
-    color: Vector4<f32>,
-    texture_coord: Vector2<f32>,
-    texture_index: f32,
+#[derive(Clone, Copy, Vertex)]
+pub(crate) struct QuadVertex {
+    pub(crate) position: Vector3<f32>,
+    pub(crate) color: Vector4<f32>,
+    pub(crate) texture_coord: Vector2<f32>,
+    pub(crate) texture_index: f32,
+    pub(crate) normal_index: f32, // 0 for a surface facing the camera
 }
 
-const MAX_QUADS: u32 = 10000;
-const MAX_VERTICES: u32 = MAX_QUADS * 4;
-const MAX_INDICES: u32 = MAX_QUADS * 6;
+/// Indices for `quad_count` quads of 4 vertices each.
+pub(crate) fn quad_indices(quad_count: u32) -> Vec<u32> {
+    let mut indices: Vec<u32> = Vec::with_capacity(quad_count as usize * 6);
+
+    let mut offset = 0;
+    for _ in 0..quad_count {
+        indices.push(offset + 0);
+        indices.push(offset + 1);
+        indices.push(offset + 2);
+
+        indices.push(offset + 2);
+        indices.push(offset + 3);
+        indices.push(offset + 0);
+
+        offset += 4;
+    }
+
+    indices
+}
+
+/// Grows `index_buffer` to hold the indices of at least `quad_count` quads.
+pub(crate) fn reserve_quad_indices(index_buffer: &IndexBuffer, quad_count: u32) {
+    if index_buffer.get_count() < quad_count * 6 {
+        index_buffer.set_indices(&quad_indices(quad_count.next_power_of_two()));
+    }
+}
+
+// Room for this many quads at first, the buffers grow when a batch needs more
+const INITIAL_QUADS: u32 = 1024;
+const MAX_TEXTURE_SLOTS: u32 = 32;
+// Paths stray at most this many pixels from their curves
+const PATH_TOLERANCE: f32 = 0.25;
 
 const QUAD_VERTEX_POSITIONS: [Vector4<f32>; 4] = [
     Vector4::new(-0.5, -0.5, 0.0, 1.0),
@@ -27,9 +64,29 @@ const QUAD_VERTEX_POSITIONS: [Vector4<f32>; 4] = [
     Vector4::new(-0.5, 0.5, 0.0, 1.0),
 ];
 
+/// What a scene was drawn with, see `Renderer::get_first_scene`.
+#[derive(Clone, Copy, Debug)]
+pub struct SceneView {
+    pub view_projection: Matrix4<f32>,
+    /// Viewport in GL coordinates, y from the bottom.
+    pub viewport: (i32, i32, u32, u32),
+}
+
+impl SceneView {
+    /// Width of a pixel of the viewport in world units.
+    pub fn get_pixel_size(&self) -> f32 {
+        let matrix = &self.view_projection;
+        let scale = Vector2::new(matrix.x.x, matrix.y.x).magnitude();
+        if scale == 0.0 || self.viewport.2 == 0 {
+            return 0.0;
+        }
+        2.0 / (scale * self.viewport.2 as f32)
+    }
+}
+
 pub struct Renderer {
     quad_vertex_array: VertexArray,
-    quad_vertex_buffer: VertexBuffer,
+    quad_vertex_buffer: Arc<VertexBuffer>,
     quad_shader: ShaderProgram,
 
     white_texture: Texture2D,
@@ -37,98 +94,334 @@ pub struct Renderer {
     quad_index_count: u32,
     quad_vertices: Vec<QuadVertex>,
 
-    texture_slots: [u32; 32],
+    texture_slots: [u32; MAX_TEXTURE_SLOTS as usize],
     texture_slot_index: u32,
 
     view_projection: Matrix4<f32>,
+    viewport: (i32, i32, u32, u32),
+    visible_bounds: AABB,
+    culling: bool,
+    previous_viewport: Option<(i32, i32, u32, u32)>, // restored by `end_scene`
+    first_scene: Option<SceneView>,
+    blend_mode: BlendMode,
+    clip: ClipStack,
+
+    lighting: Option<Lighting>,
+
+    stats: RenderStats,
+    gpu_timer: GpuTimer,
 }
 
 impl Renderer {
-    pub fn new() -> Self {
+    pub fn new(assets: &AssetServer) -> Self {
+        let mut quad_vertex_buffer =
+            VertexBuffer::new(INITIAL_QUADS as usize * 4 * mem::size_of::<QuadVertex>());
+        quad_vertex_buffer.set_layout(QuadVertex::layout());
+        let quad_vertex_buffer = Arc::new(quad_vertex_buffer);
+
         let mut renderer = Renderer {
             quad_vertex_array: VertexArray::new(),
-            quad_vertex_buffer: VertexBuffer::new(
-                MAX_VERTICES as usize * mem::size_of::<QuadVertex>(),
-            ),
-            quad_shader: ShaderProgram::new("resources/quad.vert", "resources/quad.frag"),
+            quad_vertex_buffer: quad_vertex_buffer.clone(),
+            quad_shader: assets
+                .load_owned::<ShaderProgram>("quad")
+                .unwrap_or_else(|error| panic!("{}", error)),
 
             white_texture: Texture2D::new(1, 1),
 
             quad_index_count: 0,
-            quad_vertices: Vec::with_capacity(MAX_VERTICES.try_into().unwrap()),
+            quad_vertices: Vec::with_capacity(INITIAL_QUADS as usize * 4),
 
-            texture_slots: [0; 32],
+            texture_slots: [0; MAX_TEXTURE_SLOTS as usize],
             texture_slot_index: 1, // 0 is for the white texture
 
             view_projection: Matrix4::identity(),
+            viewport: (0, 0, 0, 0),
+            visible_bounds: AABB::from_min_max(-1.0, -1.0, 1.0, 1.0),
+            culling: true,
+            previous_viewport: None,
+            first_scene: None,
+            blend_mode: BlendMode::Alpha,
+            clip: ClipStack::default(),
+
+            lighting: None,
+
+            stats: RenderStats::default(),
+            gpu_timer: GpuTimer::new(),
         };
 
         renderer.quad_vertex_array.bind();
-        renderer
-            .quad_vertex_buffer
-            .set_layout(BufferLayout::new(vec![
-                BufferElement::new(String::from("a_position"), ShaderDataType::Float3, None),
-                BufferElement::new(String::from("a_color"), ShaderDataType::Float4, None),
-                BufferElement::new(
-                    String::from("a_texture_coord"),
-                    ShaderDataType::Float2,
-                    None,
-                ),
-                BufferElement::new(String::from("a_texture_index"), ShaderDataType::Float, None),
-            ]));
-        let quad_vertex_buffer_arc = Arc::new(renderer.quad_vertex_buffer.clone());
-
         renderer
             .quad_vertex_array
-            .add_vertex_buffer(quad_vertex_buffer_arc);
-
-        let mut quad_indices: Vec<u32> = Vec::with_capacity(MAX_INDICES as usize);
-
-        let mut offset = 0;
-        for _ in (0..MAX_INDICES).step_by(6) {
-            quad_indices.push(offset + 0);
-            quad_indices.push(offset + 1);
-            quad_indices.push(offset + 2);
-
-            quad_indices.push(offset + 2);
-            quad_indices.push(offset + 3);
-            quad_indices.push(offset + 0);
+            .add_vertex_buffer(quad_vertex_buffer);
 
-            offset += 4;
-        }
-
-        let quad_index_buffer = IndexBuffer::new(quad_indices);
+        let quad_index_buffer = IndexBuffer::new(quad_indices(INITIAL_QUADS));
         renderer
             .quad_vertex_array
-            .set_index_buffer(Arc::new(quad_index_buffer.clone()));
+            .set_index_buffer(Arc::new(quad_index_buffer));
 
         renderer.quad_shader.create_uniform("u_view_projection");
 
-        let white_texture_data: u32 = 0xffffffff;
-        renderer.white_texture.set_data(
-            &white_texture_data as *const u32 as *const c_void,
-            mem::size_of::<u32>(),
-        );
+        let white_texture_data: [u8; 4] = [0xff; 4];
+        renderer.white_texture.set_data(&white_texture_data);
 
         renderer.texture_slots[0] = renderer.white_texture.id;
 
         renderer
     }
 
+    /// Resets the stats, called by the engine before `Game::draw`.
+    pub fn begin_frame(&mut self) {
+        self.reset_stats();
+        self.first_scene = None;
+    }
+
+    /// Collects the GPU timings, called by the engine after `Game::draw`.
+    pub fn end_frame(&mut self) {
+        self.gpu_timer.end_frame();
+    }
+
+    /// Starts a scene seen through `camera`, drawn inside its viewport. A frame can have
+    /// several scenes, e.g. one per player for split-screen.
     pub fn begin_scene(&mut self, camera: &Camera) {
-        self.view_projection = camera.get_projection() * camera.get_view();
+        self.previous_viewport = Some(RenderCommand::get_viewport());
 
-        self.start_batch();
+        let (x, y, width, height) = camera.get_viewport_rect();
+        RenderCommand::set_viewport(x, y, width, height);
+        RenderCommand::set_scissor(x, y, width, height);
+        RenderCommand::set_scissor_test(true);
+
+        if let Some(color) = camera.clear_color {
+            RenderCommand::clear_to_color(color);
+        }
+
+        self.begin_scene_with_matrix(camera.get_projection() * camera.get_view());
     }
 
+    /// Starts a scene covering the current viewport.
     pub fn begin_scene_with_matrix(&mut self, view_projection: Matrix4<f32>) {
         self.view_projection = view_projection;
+        self.viewport = RenderCommand::get_viewport();
+        self.visible_bounds = visible_bounds(&view_projection);
+        if self.first_scene.is_none() {
+            self.first_scene = Some(self.get_scene_view());
+        }
 
         self.start_batch();
+        self.blend_mode = BlendMode::Alpha;
+
+        if let Some(lighting) = &mut self.lighting {
+            lighting.begin();
+        }
     }
 
-    pub fn end_scene(&self) {
+    pub fn end_scene(&mut self) {
         self.flush();
+        self.clear_clip();
+
+        let visible_bounds = self.get_visible_bounds();
+        if let Some(lighting) = &mut self.lighting {
+            let cpu_start = Instant::now();
+            lighting.end(&self.view_projection, &visible_bounds, &mut self.stats);
+            self.stats.cpu_time += cpu_start.elapsed().as_secs_f32() * 1000.0;
+        }
+
+        if let Some((x, y, width, height)) = self.previous_viewport.take() {
+            RenderCommand::set_viewport(x, y, width, height);
+            RenderCommand::set_scissor_test(false);
+        }
+    }
+
+    /// Starts drawing on top of the scenes of the frame, without lighting. Used for debug
+    /// overlays.
+    pub fn begin_overlay(&mut self, view: SceneView) {
+        self.previous_viewport = Some(RenderCommand::get_viewport());
+
+        let (x, y, width, height) = view.viewport;
+        RenderCommand::set_viewport(x, y, width, height);
+
+        self.viewport = view.viewport;
+        self.view_projection = view.view_projection;
+        self.visible_bounds = visible_bounds(&view.view_projection);
+        self.start_batch();
+        self.blend_mode = BlendMode::Alpha;
+    }
+
+    pub fn end_overlay(&mut self) {
+        self.flush();
+        self.clear_clip();
+
+        if let Some((x, y, width, height)) = self.previous_viewport.take() {
+            RenderCommand::set_viewport(x, y, width, height);
+        }
+    }
+
+    /// The scene being drawn.
+    pub fn get_scene_view(&self) -> SceneView {
+        SceneView {
+            view_projection: self.view_projection,
+            viewport: self.viewport,
+        }
+    }
+
+    /// The first scene begun this frame, usually the main camera.
+    pub fn get_first_scene(&self) -> Option<SceneView> {
+        self.first_scene
+    }
+
+    /// Lights scenes with the lights and occluders submitted while drawing them, instead of
+    /// drawing sprites at full brightness. Needs the "light" and "composite" shaders.
+    pub fn enable_lighting(&mut self, assets: &AssetServer) -> Result<(), AssetError> {
+        if self.lighting.is_none() {
+            self.lighting = Some(Lighting::new(assets)?);
+        }
+        Ok(())
+    }
+
+    pub fn disable_lighting(&mut self) {
+        self.lighting = None;
+    }
+
+    pub fn is_lighting_enabled(&self) -> bool {
+        self.lighting.is_some()
+    }
+
+    /// Light reaching every surface, whether lights reach it or not.
+    pub fn set_ambient_light(&mut self, color: Vector3<f32>) {
+        if let Some(lighting) = &mut self.lighting {
+            lighting.ambient = color;
+        }
+    }
+
+    /// Adds a light to the current scene. Ignored while lighting is disabled.
+    pub fn submit_light(&mut self, light: Light) {
+        if let Some(lighting) = &mut self.lighting {
+            lighting.lights.push(light);
+        }
+    }
+
+    /// Adds a shadow caster to the current scene. Ignored while lighting is disabled.
+    pub fn submit_occluder(&mut self, occluder: Occluder) {
+        if let Some(lighting) = &mut self.lighting {
+            lighting.occluders.push(occluder);
+        }
+    }
+
+    /// Area of the world the current scene's camera sees.
+    pub fn get_visible_bounds(&self) -> AABB {
+        self.visible_bounds
+    }
+
+    /// Skips quads and tilemap chunks outside of the visible bounds, on by default. Only worth
+    /// turning off to compare the stats or when drawing with a custom vertex shader that moves
+    /// sprites around.
+    pub fn set_culling(&mut self, enabled: bool) {
+        self.culling = enabled;
+    }
+
+    pub fn is_culling_enabled(&self) -> bool {
+        self.culling
+    }
+
+    /// How the next draws combine with what is behind them, `BlendMode::Alpha` at the start
+    /// of every scene. Quads are batched by blend mode, so changing it starts a new batch.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        if mode != self.blend_mode {
+            self.next_batch();
+            self.blend_mode = mode;
+        }
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Limits the next draws to the pixels covering `bounds` of the world, inside the clip
+    /// rects pushed before. Cheaper than a mask, but only for rectangles aligned with the
+    /// screen. Starts a new batch, like `pop_clip_rect`.
+    pub fn push_clip_rect(&mut self, bounds: &AABB) {
+        self.next_batch();
+        let rect =
+            ScissorRect::from_bounds(bounds, &self.view_projection, RenderCommand::get_viewport());
+        self.clip.push_rect(rect);
+    }
+
+    pub fn pop_clip_rect(&mut self) {
+        self.next_batch();
+        if !self.clip.pop_rect() {
+            log::warn!("pop_clip_rect called without a clip rect");
+        }
+    }
+
+    /// Draws a mask instead of showing the next draws, until `end_mask`. Only the shapes of
+    /// what is drawn count, not its colors, although fully transparent quads are skipped.
+    /// Masks nest, each one inside those pushed before.
+    pub fn begin_mask(&mut self) {
+        self.next_batch();
+        self.clip.begin_mask();
+    }
+
+    /// Limits the next draws to the mask drawn since `begin_mask`, until `pop_mask`.
+    pub fn end_mask(&mut self) {
+        self.next_batch();
+        self.clip.end_mask();
+    }
+
+    /// Limits the next draws to the inside of a path, e.g. a circle for a minimap. The edges
+    /// of the mask are not anti-aliased.
+    pub fn push_clip_path(&mut self, path: &Path, rule: FillRule) {
+        let pixel_size = self.get_scene_view().get_pixel_size();
+        let mesh = fill_polylines(&path.flatten(pixel_size * PATH_TOLERANCE), rule, 0.0);
+
+        self.begin_mask();
+        self.draw_vector_mesh(&mesh, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        self.end_mask();
+    }
+
+    pub fn pop_mask(&mut self) {
+        self.next_batch();
+        if !self.clip.begin_pop_mask() {
+            log::warn!("pop_mask called without a finished mask");
+            return;
+        }
+
+        // Anything covering the viewport resets the stencil of the mask
+        let bounds = self.visible_bounds;
+        let center = (bounds.min() + bounds.max()) / 2.0;
+        let size = (bounds.max() - bounds.min()) * 1.1;
+        let corners = quad_corners(center, size, 0.0);
+        self.push_quad(
+            corners,
+            [Vector4::new(1.0, 1.0, 1.0, 1.0); 4],
+            SubTexture::full().get_texture_coords(),
+            0.0,
+            0.0,
+        );
+        self.next_batch();
+        self.clip.end_pop_mask();
+    }
+
+    /// Number of masks the next draws are limited to.
+    pub fn get_mask_depth(&self) -> u32 {
+        self.clip.get_mask_depth()
+    }
+
+    // Drops the rects and masks a scene left pushed, they would clip the next passes
+    fn clear_clip(&mut self) {
+        if !self.clip.is_empty() {
+            log::warn!("Scene ended with clip rects or masks still pushed");
+            self.clip.clear();
+        }
+    }
+
+    pub fn get_stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = RenderStats {
+            gpu_time: self.gpu_timer.get_last_frame_time(),
+            ..RenderStats::default()
+        };
     }
 
     fn start_batch(&mut self) {
@@ -137,14 +430,20 @@ impl Renderer {
         self.texture_slot_index = 1;
     }
 
-    fn flush(&self) {
+    fn flush(&mut self) {
         if self.quad_vertices.len() == 0 {
             return;
         }
 
-        let data_size = self.quad_vertices.len() * mem::size_of::<QuadVertex>();
-        self.quad_vertex_buffer
-            .set_data(self.quad_vertices.as_ptr() as *const c_void, data_size);
+        let cpu_start = Instant::now();
+        self.gpu_timer.begin();
+
+        let data_size = mem::size_of_val(self.quad_vertices.as_slice());
+        let base_vertex = self.quad_vertex_buffer.stream(&self.quad_vertices);
+        reserve_quad_indices(
+            self.quad_vertex_array.get_index_buffer(),
+            self.quad_index_count / 6,
+        );
 
         for i in 0..self.texture_slot_index {
             unsafe {
@@ -155,7 +454,22 @@ impl Renderer {
         self.quad_shader.bind();
         self.quad_shader
             .set_matrix4fv_uniform("u_view_projection", &self.view_projection);
-        RenderCommand::draw_indexed(&self.quad_vertex_array, Some(self.quad_index_count));
+        self.draw_blended(|renderer| {
+            RenderCommand::draw_indexed_base_vertex(
+                &renderer.quad_vertex_array,
+                renderer.quad_index_count,
+                base_vertex,
+            );
+        });
+
+        self.gpu_timer.end();
+
+        self.stats.draw_calls += 1;
+        self.stats.batches += 1;
+        self.stats.texture_binds += self.texture_slot_index;
+        self.stats.texture_slots_used = self.stats.texture_slots_used.max(self.texture_slot_index);
+        self.stats.bytes_uploaded += data_size;
+        self.stats.cpu_time += cpu_start.elapsed().as_secs_f32() * 1000.0;
     }
 
     pub fn next_batch(&mut self) {
@@ -163,6 +477,33 @@ impl Renderer {
         self.start_batch();
     }
 
+    /// Draws the part of the tilemap the camera sees. Quads drawn before are flushed first so
+    /// the map is layered in draw order.
+    pub fn draw_tilemap(&mut self, tilemap: &mut Tilemap) {
+        self.next_batch();
+
+        let cpu_start = Instant::now();
+        let view = self.culling.then(|| {
+            AABB::new(
+                self.visible_bounds.min() - tilemap.position,
+                self.visible_bounds.max() - tilemap.position,
+            )
+        });
+
+        for (slot, tileset) in tilemap.get_tilesets().iter().enumerate() {
+            tileset.get_texture().bind(slot as u32);
+        }
+        self.stats.texture_binds += tilemap.get_tilesets().len() as u32;
+
+        let model = Matrix4::from_translation(tilemap.position.extend(0.0));
+        self.quad_shader.bind();
+        self.quad_shader
+            .set_matrix4fv_uniform("u_view_projection", &(self.view_projection * model));
+        self.draw_blended(|renderer| tilemap.draw(view.as_ref(), &mut renderer.stats));
+
+        self.stats.cpu_time += cpu_start.elapsed().as_secs_f32() * 1000.0;
+    }
+
     pub fn draw_quad(
         &mut self,
         position: Vector2<f32>,
@@ -170,56 +511,365 @@ impl Renderer {
         color: Vector4<f32>,
         texture: Option<&Texture2D>,
     ) {
-        const QUAD_VERTEX_COUNT: u32 = 4;
-        const TEXTURE_COORDS: [Vector2<f32>; 4] = [
-            Vector2::new(0.0, 0.0),
-            Vector2::new(1.0, 0.0),
-            Vector2::new(1.0, 1.0),
-            Vector2::new(0.0, 1.0),
-        ];
-
-        if self.quad_index_count >= MAX_INDICES as u32 {
+        let corners = quad_corners(position, size, 0.0);
+        if self.cull(&corners) {
+            return;
+        }
+
+        let texture_index = self.get_texture_index(texture);
+        self.push_quad(
+            corners,
+            [color; 4],
+            SubTexture::full().get_texture_coords(),
+            texture_index,
+            0.0,
+        );
+    }
+
+    /// Like `draw_quad`, turned `rotation` radians counter-clockwise around its center.
+    pub fn draw_rotated_quad(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        rotation: f32,
+        color: Vector4<f32>,
+        texture: Option<&Texture2D>,
+    ) {
+        let corners = quad_corners(position, size, rotation);
+        if self.cull(&corners) {
+            return;
+        }
+
+        let texture_index = self.get_texture_index(texture);
+        self.push_quad(
+            corners,
+            [color; 4],
+            SubTexture::full().get_texture_coords(),
+            texture_index,
+            0.0,
+        );
+    }
+
+    /// Like `draw_rotated_quad` with colors varying over the quad, tinting `texture` if any.
+    pub fn draw_gradient_quad(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        rotation: f32,
+        gradient: &Gradient,
+        texture: Option<&Texture2D>,
+    ) {
+        if self.cull(&quad_corners(position, size, rotation)) {
+            return;
+        }
+
+        let rotation = Basis2::from_angle(Rad(rotation));
+        let to_world = |point: Vector2<f32>| {
+            rotation.rotate_vector((point - Vector2::new(0.5, 0.5)).mul_element_wise(size))
+                + position
+        };
+
+        let texture_index = self.get_texture_index(texture);
+        for GradientCell { min, max, colors } in gradient_cells(gradient) {
+            let points = [
+                min,
+                Vector2::new(max.x, min.y),
+                max,
+                Vector2::new(min.x, max.y),
+            ];
+            self.push_quad(points.map(to_world), colors, points, texture_index, 0.0);
+        }
+    }
+
+    /// Fills the inside of `path`, anti-aliased over a pixel. Tessellates the path every
+    /// call, use `draw_vector_mesh` for paths that do not change.
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule, color: Vector4<f32>) {
+        let pixel_size = self.get_scene_view().get_pixel_size();
+        let polylines = path.flatten(pixel_size * PATH_TOLERANCE);
+        let mesh = fill_polylines(&polylines, rule, pixel_size);
+        self.draw_vector_mesh(&mesh, color);
+    }
+
+    /// Draws a line along `path` with `style`, its width in world units.
+    pub fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, color: Vector4<f32>) {
+        let pixel_size = self.get_scene_view().get_pixel_size();
+        let tolerance = pixel_size * PATH_TOLERANCE;
+        let mesh = stroke_polylines(&path.flatten(tolerance), style, tolerance, pixel_size);
+        self.draw_vector_mesh(&mesh, color);
+    }
+
+    /// Draws triangles made by `fill_polylines` or `stroke_polylines` in the quad batch,
+    /// their coverage multiplied into the alpha of `color`.
+    pub fn draw_vector_mesh(&mut self, mesh: &VectorMesh, color: Vector4<f32>) {
+        let Some(bounds) = mesh.get_bounds() else {
+            return;
+        };
+        let (min, max) = (bounds.min(), bounds.max());
+        if self.cull(&[
+            min,
+            Vector2::new(max.x, min.y),
+            max,
+            Vector2::new(min.x, max.y),
+        ]) {
+            return;
+        }
+
+        // Every triangle is a quad whose last two corners are the same
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| mesh.vertices[triangle[corner] as usize]);
+            let colors = [a, b, c, c]
+                .map(|vertex| Vector4::new(color.x, color.y, color.z, color.w * vertex.coverage));
+            self.push_quad(
+                [a.position, b.position, c.position, c.position],
+                colors,
+                [Vector2::zero(); 4],
+                0.0,
+                0.0,
+            );
+        }
+    }
+
+    /// Draws part of a texture, e.g. one sprite of a sprite sheet.
+    pub fn draw_sub_texture(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        texture: &Texture2D,
+        sub_texture: &SubTexture,
+    ) {
+        let corners = quad_corners(position, size, 0.0);
+        if self.cull(&corners) {
+            return;
+        }
+
+        let texture_index = self.get_texture_index(Some(texture));
+        self.push_quad(
+            corners,
+            [color; 4],
+            sub_texture.get_texture_coords(),
+            texture_index,
+            0.0,
+        );
+    }
+
+    /// Draws a sprite sliced so its borders keep their size, e.g. a panel or a frame.
+    pub fn draw_nine_slice(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        texture: &Texture2D,
+        slice: &NineSlice,
+    ) {
+        let texture_size = Vector2::new(texture.get_width(), texture.get_height());
+        let patches: Vec<_> = slice
+            .patches(position, size, texture_size)
+            .into_iter()
+            .map(|patch| {
+                let corners = [
+                    patch.min,
+                    Vector2::new(patch.max.x, patch.min.y),
+                    patch.max,
+                    Vector2::new(patch.min.x, patch.max.y),
+                ];
+                (corners, patch.sub_texture)
+            })
+            .filter(|(corners, _)| !self.cull(corners))
+            .collect();
+
+        if patches.is_empty() {
+            return;
+        }
+
+        let texture_index = self.get_texture_index(Some(texture));
+        for (corners, sub_texture) in patches {
+            self.push_quad(
+                corners,
+                [color; 4],
+                sub_texture.get_texture_coords(),
+                texture_index,
+                0.0,
+            );
+        }
+    }
+
+    /// Draws a sprite whose surface is described by a tangent space normal map, which lights
+    /// shade when lighting is enabled. Without lighting it is drawn like `draw_quad`.
+    pub fn draw_quad_with_normal_map(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        texture: &Texture2D,
+        normal_map: &Texture2D,
+    ) {
+        let corners = quad_corners(position, size, 0.0);
+        if self.cull(&corners) {
+            return;
+        }
+
+        // Both textures have to end up in the same batch
+        if self.texture_slot_index + 2 > MAX_TEXTURE_SLOTS {
             self.next_batch();
         }
 
-        let mut texture_index = 0.0;
-        if texture.is_some() {
-            let texture = texture.unwrap_or(&self.white_texture).id;
+        let texture_index = self.get_texture_index(Some(texture));
+        let normal_index = self.get_texture_index(Some(normal_map));
+        self.push_quad(
+            corners,
+            [color; 4],
+            SubTexture::full().get_texture_coords(),
+            texture_index,
+            normal_index,
+        );
+    }
+
+    /// Draws the live particles of an emitter in the quad batch, with `texture` or plain
+    /// colored quads if none.
+    pub fn draw_particles(&mut self, emitter: &ParticleEmitter, texture: Option<&Texture2D>) {
+        let config = &emitter.config;
+        let texture_coords = config
+            .sub_texture
+            .unwrap_or_else(SubTexture::full)
+            .get_texture_coords();
+
+        let emitter_rotation = Basis2::from_angle(Rad(emitter.rotation));
+        let previous_blend_mode = self.blend_mode;
+        self.set_blend_mode(config.blend_mode);
+
+        for particle in emitter.get_particles() {
+            let progress = particle.get_progress();
+            let size = config.size_over_lifetime.evaluate(progress) * particle.size;
+            let color = config.color_over_lifetime.evaluate(progress);
+
+            let (position, rotation) = match config.space {
+                SimulationSpace::World => (particle.position, particle.rotation),
+                SimulationSpace::Local => (
+                    emitter.position + emitter_rotation.rotate_vector(particle.position),
+                    particle.rotation + emitter.rotation,
+                ),
+            };
 
-            for i in 0..self.texture_slot_index {
-                if self.texture_slots[i as usize] == texture {
-                    texture_index = i as f32;
-                }
+            let corners = quad_corners(position, size, rotation);
+            if self.cull(&corners) {
+                continue;
             }
 
-            if texture_index == 0.0 {
-                if self.texture_slot_index >= 32 as u32 {
-                    self.next_batch();
-                }
+            let texture_index = self.get_texture_index(texture);
+            self.push_quad(corners, [color; 4], texture_coords, texture_index, 0.0);
+        }
+
+        self.set_blend_mode(previous_blend_mode);
+    }
+
+    // Runs draw calls with the blend mode of the batch. Other passes expect alpha blending, so
+    // it is restored afterwards.
+    fn draw_blended(&mut self, draw: impl FnOnce(&mut Self)) {
+        if self.blend_mode == BlendMode::Alpha {
+            draw(self);
+            return;
+        }
+
+        RenderCommand::set_blend_mode(self.blend_mode);
+        draw(self);
+        RenderCommand::set_blend_mode(BlendMode::Alpha);
+    }
 
-                texture_index = self.texture_slot_index as f32;
-                self.texture_slots[self.texture_slot_index as usize] = texture;
-                self.texture_slot_index += 1;
+    // Whether a quad is entirely outside of the visible bounds and should be skipped
+    fn cull(&mut self, corners: &[Vector2<f32>; 4]) -> bool {
+        if !self.culling {
+            return false;
+        }
+
+        let bounds = corners[1..]
+            .iter()
+            .fold(AABB::new(corners[0], corners[0]), |bounds, corner| {
+                bounds.merge(&AABB::new(*corner, *corner))
+            });
+
+        let culled = !bounds.intersects(&self.visible_bounds);
+        if culled {
+            self.stats.culled_quads += 1;
+        }
+        culled
+    }
+
+    // Slot of `texture` in the current batch, starting a new batch when the slots run out.
+    // No texture is the white texture in slot 0.
+    fn get_texture_index(&mut self, texture: Option<&Texture2D>) -> f32 {
+        let texture = match texture {
+            Some(texture) => texture.id,
+            None => return 0.0,
+        };
+
+        for i in 1..self.texture_slot_index {
+            if self.texture_slots[i as usize] == texture {
+                return i as f32;
             }
         }
 
-        for i in 0..QUAD_VERTEX_COUNT {
-            let vertex_position = QUAD_VERTEX_POSITIONS[i as usize]
-                .truncate()
-                .truncate()
-                .mul_element_wise(size)
-                + position;
+        if self.texture_slot_index >= MAX_TEXTURE_SLOTS {
+            self.next_batch();
+        }
 
-            let vertex = QuadVertex {
-                position: Vector3::new(vertex_position.x, vertex_position.y, 0.0),
-                color,
-                texture_coord: TEXTURE_COORDS[i as usize],
-                texture_index,
-            };
+        let texture_index = self.texture_slot_index;
+        self.texture_slots[texture_index as usize] = texture;
+        self.texture_slot_index += 1;
+        texture_index as f32
+    }
 
-            self.quad_vertices.push(vertex);
+    fn push_quad(
+        &mut self,
+        corners: [Vector2<f32>; 4],
+        colors: [Vector4<f32>; 4],
+        texture_coords: [Vector2<f32>; 4],
+        texture_index: f32,
+        normal_index: f32,
+    ) {
+        const QUAD_VERTEX_COUNT: u32 = 4;
+
+        for i in 0..QUAD_VERTEX_COUNT as usize {
+            self.quad_vertices.push(QuadVertex {
+                position: corners[i].extend(0.0),
+                color: colors[i],
+                texture_coord: texture_coords[i],
+                texture_index,
+                normal_index,
+            });
         }
 
         self.quad_index_count += 6;
+
+        self.stats.quads += 1;
+        self.stats.vertices += QUAD_VERTEX_COUNT;
     }
 }
+
+// Area of the world inside the clip space of `view_projection`
+fn visible_bounds(view_projection: &Matrix4<f32>) -> AABB {
+    let inverse = view_projection.invert().unwrap_or_else(Matrix4::identity);
+
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| {
+        let corner = inverse * Vector4::new(x, y, 0.0, 1.0);
+        Vector2::new(corner.x / corner.w, corner.y / corner.w)
+    });
+
+    corners[1..]
+        .iter()
+        .fold(AABB::new(corners[0], corners[0]), |bounds, corner| {
+            bounds.merge(&AABB::new(*corner, *corner))
+        })
+}
+
+/// Corners of a quad centered on `position`, in the order the renderer emits them.
+pub(crate) fn quad_corners(
+    position: Vector2<f32>,
+    size: Vector2<f32>,
+    rotation: f32,
+) -> [Vector2<f32>; 4] {
+    let rotation = Basis2::from_angle(Rad(rotation));
+    QUAD_VERTEX_POSITIONS.map(|corner| {
+        rotation.rotate_vector(corner.truncate().truncate().mul_element_wise(size)) + position
+    })
+}
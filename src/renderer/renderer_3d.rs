@@ -0,0 +1,232 @@
+use cgmath::*;
+
+use crate::asset::{AssetError, AssetServer};
+use crate::model::Model;
+use crate::renderer::{Mesh, PerspectiveCamera, RenderCommand, ShaderProgram, Texture2D};
+
+/// Surface of a mesh for Blinn-Phong shading.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    /// Multiplies the texture.
+    pub color: Vector4<f32>,
+    pub specular: Vector3<f32>,
+    /// Size of the highlights, higher is smaller and sharper.
+    pub shininess: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            specular: Vector3::new(0.5, 0.5, 0.5),
+            shininess: 32.0,
+        }
+    }
+}
+
+/// Light coming from far away along `direction`, e.g. the sun.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+/// Light fading out towards `radius`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    pub radius: f32,
+}
+
+// Must match the size of the light array in mesh.frag
+const MAX_POINT_LIGHTS: usize = 8;
+
+/// Draws meshes with depth testing and Blinn-Phong lighting. Needs the "mesh" shader.
+/// Lights are set before `begin_scene`, which sends them to the shader.
+pub struct Renderer3D {
+    shader: ShaderProgram,
+    white_texture: Texture2D,
+
+    ambient: Vector3<f32>,
+    directional_light: Option<DirectionalLight>,
+    point_lights: Vec<PointLight>,
+
+    previous_viewport: Option<(i32, i32, u32, u32)>,
+}
+
+impl Renderer3D {
+    pub fn new(assets: &AssetServer) -> Result<Self, AssetError> {
+        let mut shader = assets.load_owned::<ShaderProgram>("mesh")?;
+        for uniform in [
+            "u_view_projection",
+            "u_model",
+            "u_normal_matrix",
+            "u_camera_position",
+            "u_color",
+            "u_specular",
+            "u_shininess",
+            "u_ambient",
+            "u_directional_color",
+            "u_directional_direction",
+            "u_point_light_count",
+        ] {
+            shader.create_uniform(uniform);
+        }
+        for index in 0..MAX_POINT_LIGHTS {
+            for field in ["position", "color", "radius"] {
+                shader.create_uniform(&format!("u_point_lights[{}].{}", index, field));
+            }
+        }
+
+        let white_texture = Texture2D::new(1, 1);
+        white_texture.set_data(&[0xff; 4]);
+
+        Ok(Renderer3D {
+            shader,
+            white_texture,
+
+            ambient: Vector3::new(0.1, 0.1, 0.1),
+            directional_light: None,
+            point_lights: Vec::new(),
+
+            previous_viewport: None,
+        })
+    }
+
+    pub fn set_ambient_light(&mut self, color: Vector3<f32>) {
+        self.ambient = color;
+    }
+
+    pub fn set_directional_light(&mut self, light: Option<DirectionalLight>) {
+        self.directional_light = light;
+    }
+
+    /// Adds a light to the next scenes, lights beyond the first 8 are ignored.
+    pub fn submit_point_light(&mut self, light: PointLight) {
+        if self.point_lights.len() < MAX_POINT_LIGHTS {
+            self.point_lights.push(light);
+        }
+    }
+
+    pub fn clear_point_lights(&mut self) {
+        self.point_lights.clear();
+    }
+
+    /// Starts a scene seen through `camera`, inside its viewport and with its own depth.
+    pub fn begin_scene(&mut self, camera: &PerspectiveCamera) {
+        self.previous_viewport = Some(RenderCommand::get_viewport());
+
+        let (x, y, width, height) = camera.get_viewport_rect();
+        RenderCommand::set_viewport(x, y, width, height);
+        RenderCommand::set_scissor(x, y, width, height);
+        RenderCommand::set_scissor_test(true);
+
+        if let Some(color) = camera.clear_color {
+            RenderCommand::clear_to_color(color);
+        }
+        RenderCommand::clear_depth();
+        RenderCommand::set_depth_test(true);
+
+        self.shader.bind();
+        self.shader
+            .set_matrix4fv_uniform("u_view_projection", &camera.get_view_projection());
+        self.shader
+            .set_vector3f_uniform("u_camera_position", &camera.position);
+        self.upload_lights();
+    }
+
+    pub fn end_scene(&mut self) {
+        RenderCommand::set_depth_test(false);
+
+        if let Some((x, y, width, height)) = self.previous_viewport.take() {
+            RenderCommand::set_viewport(x, y, width, height);
+            RenderCommand::set_scissor_test(false);
+        }
+    }
+
+    /// Draws `mesh` placed in the world by `transform`, with `texture` or plain `material`
+    /// color if none.
+    pub fn draw_mesh(
+        &mut self,
+        mesh: &Mesh,
+        transform: &Matrix4<f32>,
+        material: &Material,
+        texture: Option<&Texture2D>,
+    ) {
+        // Normals stay perpendicular to surfaces under non-uniform scaling
+        let normal_matrix = transform
+            .invert()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or_else(Matrix4::identity);
+
+        self.shader.bind();
+        self.shader.set_matrix4fv_uniform("u_model", transform);
+        self.shader
+            .set_matrix4fv_uniform("u_normal_matrix", &normal_matrix);
+        self.shader.set_vector4f_uniform("u_color", &material.color);
+        self.shader
+            .set_vector3f_uniform("u_specular", &material.specular);
+        self.shader
+            .set_float_uniform("u_shininess", material.shininess);
+
+        texture.unwrap_or(&self.white_texture).bind(0);
+        RenderCommand::draw_indexed(mesh.get_vertex_array(), None);
+    }
+
+    /// Draws every mesh of `model` where its nodes place it, the whole model placed in the
+    /// world by `transform`.
+    pub fn draw_model(&mut self, model: &Model, transform: &Matrix4<f32>) {
+        let default_material = Material::default();
+
+        for (node, node_transform) in model.nodes.iter().zip(model.get_global_transforms()) {
+            let node_transform = transform * node_transform;
+            for &index in &node.meshes {
+                let mesh = &model.meshes[index];
+                let (material, texture) = match mesh.material {
+                    Some(material) => (
+                        &model.materials[material],
+                        model.textures[material].as_ref(),
+                    ),
+                    None => (&default_material, None),
+                };
+                self.draw_mesh(&mesh.mesh, &node_transform, material, texture);
+            }
+        }
+    }
+
+    fn upload_lights(&self) {
+        self.shader.set_vector3f_uniform("u_ambient", &self.ambient);
+
+        let directional = self.directional_light.unwrap_or(DirectionalLight {
+            direction: -Vector3::unit_y(),
+            color: Vector3::zero(),
+            intensity: 0.0,
+        });
+        self.shader.set_vector3f_uniform(
+            "u_directional_color",
+            &(directional.color * directional.intensity),
+        );
+        self.shader.set_vector3f_uniform(
+            "u_directional_direction",
+            &directional.direction.normalize(),
+        );
+
+        self.shader
+            .set_int_uniform("u_point_light_count", self.point_lights.len() as i32);
+        for (index, light) in self.point_lights.iter().enumerate() {
+            self.shader.set_vector3f_uniform(
+                &format!("u_point_lights[{}].position", index),
+                &light.position,
+            );
+            self.shader.set_vector3f_uniform(
+                &format!("u_point_lights[{}].color", index),
+                &(light.color * light.intensity),
+            );
+            self.shader
+                .set_float_uniform(&format!("u_point_lights[{}].radius", index), light.radius);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Bookkeeping of live GL objects in debug builds, used to find leaks and double frees.
+//! Every function compiles to nothing in release builds.
+
+#[cfg(debug_assertions)]
+use std::collections::HashMap;
+#[cfg(debug_assertions)]
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GpuResourceKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    ShaderProgram,
+    Query,
+    Framebuffer,
+    Renderbuffer,
+}
+
+#[cfg(debug_assertions)]
+fn live_resources() -> &'static Mutex<HashMap<(GpuResourceKind, u32), u64>> {
+    static LIVE_RESOURCES: OnceLock<Mutex<HashMap<(GpuResourceKind, u32), u64>>> = OnceLock::new();
+    LIVE_RESOURCES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(debug_assertions)]
+fn next_serial() -> u64 {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static SERIAL: AtomicU64 = AtomicU64::new(0);
+    SERIAL.fetch_add(1, Ordering::Relaxed)
+}
+
+#[allow(unused_variables)]
+pub(crate) fn track_create(kind: GpuResourceKind, id: u32) {
+    #[cfg(debug_assertions)]
+    {
+        let previous = live_resources()
+            .lock()
+            .unwrap()
+            .insert((kind, id), next_serial());
+        assert!(
+            previous.is_none(),
+            "{:?} {} was created twice without being deleted!",
+            kind,
+            id
+        );
+    }
+}
+
+#[allow(unused_variables)]
+pub(crate) fn track_delete(kind: GpuResourceKind, id: u32) {
+    #[cfg(debug_assertions)]
+    {
+        let removed = live_resources().lock().unwrap().remove(&(kind, id));
+        assert!(
+            removed.is_some(),
+            "Double free of {:?} {} (or it was never created)!",
+            kind,
+            id
+        );
+    }
+}
+
+/// Number of GL objects of `kind` currently alive. Always 0 in release builds.
+#[allow(unused_variables)]
+pub fn live_resource_count(kind: GpuResourceKind) -> usize {
+    #[cfg(debug_assertions)]
+    {
+        live_resources()
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(resource_kind, _)| *resource_kind == kind)
+            .count()
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        0
+    }
+}
+
+/// Logs every GL object still alive, oldest first, and returns how many there are.
+pub fn report_live_resources() -> usize {
+    #[cfg(debug_assertions)]
+    {
+        let live_resources = live_resources().lock().unwrap();
+        let mut resources: Vec<_> = live_resources.iter().collect();
+        resources.sort_by_key(|(_, serial)| **serial);
+
+        for ((kind, id), _) in &resources {
+            log::warn!("Leaked {:?} {}", kind, id);
+        }
+        resources.len()
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        0
+    }
+}
@@ -1,73 +1,97 @@
 use std::collections::HashMap;
 use std::ffi::CString;
-use std::fs::File;
-
-use std::io::Read;
-
 use std::ptr;
 
 use gl::types::*;
 
 use cgmath::*;
+use thiserror::Error;
 
-fn compile_errors(shader: u32, shader_type: &str) {
-    let mut has_compiled: GLint = 1;
+use crate::asset::vfs;
+use crate::renderer::{track_create, track_delete, GpuResourceKind};
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("shader compilation error for: {stage}\n{log}")]
+    Compile { stage: &'static str, log: String },
+    #[error("shader linking error\n{log}")]
+    Link { log: String },
+    #[error("cannot locate uniform: {0}")]
+    MissingUniform(String),
+}
 
+fn info_log(object: u32, is_program: bool) -> String {
     unsafe {
-        if shader_type != "program" {
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut has_compiled);
-            if has_compiled == 0 {
-                let mut len: GLint = 0;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-
-                // allocate buffer of correct size
-                let mut buffer: Vec<u8> = Vec::with_capacity(len as usize + 1);
-                // fill it with len spaces
-                buffer.extend([b' '].iter().cycle().take(len as usize));
-                // convert buffer to CString
-                let error: CString = CString::from_vec_unchecked(buffer);
-
-                gl::GetShaderInfoLog(
-                    shader,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar,
-                );
-                panic!(
-                    "Shader compilation error for: {} \n {}",
-                    shader_type,
-                    error.to_string_lossy()
-                );
-            }
+        let mut len: GLint = 0;
+        if is_program {
+            gl::GetProgramiv(object, gl::INFO_LOG_LENGTH, &mut len);
         } else {
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut has_compiled);
-            if has_compiled == 0 {
-                let mut len: GLint = 0;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-
-                // allocate buffer of correct size
-                let mut buffer: Vec<u8> = Vec::with_capacity(len as usize + 1);
-                // fill it with len spaces
-                buffer.extend([b' '].iter().cycle().take(len as usize));
-                // convert buffer to CString
-                let error: CString = CString::from_vec_unchecked(buffer);
-
-                gl::GetShaderInfoLog(
-                    shader,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar,
-                );
-                panic!(
-                    "Shader linking error for: {} \n {}",
-                    shader_type,
-                    error.to_string_lossy()
-                );
-            }
+            gl::GetShaderiv(object, gl::INFO_LOG_LENGTH, &mut len);
+        }
+
+        // allocate buffer of correct size
+        let mut buffer: Vec<u8> = vec![0; len.max(1) as usize];
+        if is_program {
+            gl::GetProgramInfoLog(
+                object,
+                len,
+                std::ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        } else {
+            gl::GetShaderInfoLog(
+                object,
+                len,
+                std::ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+
+        // drop the nul terminator
+        buffer.truncate(len.saturating_sub(1).max(0) as usize);
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+fn compile_shader(
+    shader_type: GLenum,
+    source: &str,
+    stage: &'static str,
+) -> Result<u32, ShaderError> {
+    let source = CString::new(source.as_bytes()).map_err(|_| ShaderError::Compile {
+        stage,
+        log: String::from("source contains a nul byte"),
+    })?;
+
+    unsafe {
+        let shader = gl::CreateShader(shader_type);
+        gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut has_compiled: GLint = 1;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut has_compiled);
+        if has_compiled == 0 {
+            let log = info_log(shader, false);
+            gl::DeleteShader(shader);
+            return Err(ShaderError::Compile { stage, log });
         }
+
+        Ok(shader)
     }
 }
 
+fn read_source(path: &str) -> Result<String, ShaderError> {
+    vfs::read_to_string(path).map_err(|source| ShaderError::Io {
+        path: path.to_string(),
+        source,
+    })
+}
+
 pub struct ShaderProgram {
     program_handle: u32,
     uniform_ids: HashMap<String, GLint>,
@@ -76,51 +100,83 @@ pub struct ShaderProgram {
 #[allow(temporary_cstring_as_ptr)]
 impl ShaderProgram {
     pub fn new(vertex_shader_path: &str, fragment_shader_path: &str) -> ShaderProgram {
-        let mut vertex_shader_file = File::open(vertex_shader_path)
-            .unwrap_or_else(|_| panic!("Failed to open {}", vertex_shader_path));
-        let mut fragment_shader_file = File::open(fragment_shader_path)
-            .unwrap_or_else(|_| panic!("Failed to open {}", fragment_shader_path));
+        Self::from_files(vertex_shader_path, fragment_shader_path)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let mut vertex_shader_source = String::new();
-        let mut fragment_shader_source = String::new();
+    pub fn from_files(
+        vertex_shader_path: &str,
+        fragment_shader_path: &str,
+    ) -> Result<ShaderProgram, ShaderError> {
+        let vertex_shader_source = read_source(vertex_shader_path)?;
+        let fragment_shader_source = read_source(fragment_shader_path)?;
 
-        vertex_shader_file
-            .read_to_string(&mut vertex_shader_source)
-            .expect("Failed to read vertex shader");
+        Self::from_sources(&vertex_shader_source, &fragment_shader_source)
+    }
 
-        fragment_shader_file
-            .read_to_string(&mut fragment_shader_source)
-            .expect("Failed to read fragment shader");
+    pub fn from_sources(
+        vertex_shader_source: &str,
+        fragment_shader_source: &str,
+    ) -> Result<ShaderProgram, ShaderError> {
+        let vertex_shader = compile_shader(gl::VERTEX_SHADER, vertex_shader_source, "vertex")?;
+        let fragment_shader =
+            match compile_shader(gl::FRAGMENT_SHADER, fragment_shader_source, "fragment") {
+                Ok(shader) => shader,
+                Err(error) => {
+                    unsafe { gl::DeleteShader(vertex_shader) };
+                    return Err(error);
+                }
+            };
 
         unsafe {
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            let c_str_vert = CString::new(vertex_shader_source.as_bytes()).unwrap();
-            gl::ShaderSource(vertex_shader, 1, &c_str_vert.as_ptr(), ptr::null());
-            gl::CompileShader(vertex_shader);
-            compile_errors(vertex_shader, "vertex");
-
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            let c_str_frag = CString::new(fragment_shader_source.as_bytes()).unwrap();
-            gl::ShaderSource(fragment_shader, 1, &c_str_frag.as_ptr(), ptr::null());
-            gl::CompileShader(fragment_shader);
-            compile_errors(fragment_shader, "fragment");
-
             let program_handle = gl::CreateProgram();
             gl::AttachShader(program_handle, vertex_shader);
             gl::AttachShader(program_handle, fragment_shader);
             gl::LinkProgram(program_handle);
-            compile_errors(program_handle, "program");
 
             gl::DeleteShader(vertex_shader);
             gl::DeleteShader(fragment_shader);
 
-            return ShaderProgram {
+            let mut has_linked: GLint = 1;
+            gl::GetProgramiv(program_handle, gl::LINK_STATUS, &mut has_linked);
+            if has_linked == 0 {
+                let log = info_log(program_handle, true);
+                gl::DeleteProgram(program_handle);
+                return Err(ShaderError::Link { log });
+            }
+
+            track_create(GpuResourceKind::ShaderProgram, program_handle);
+
+            Ok(ShaderProgram {
                 program_handle,
                 uniform_ids: HashMap::new(),
-            };
+            })
         }
     }
 
+    /// Replaces the program with one built from new sources, e.g. after its files changed.
+    /// Uniforms created so far are located again in the new program. The current program is
+    /// kept if the new one fails to build or lacks one of them.
+    pub fn reload_sources(
+        &mut self,
+        vertex_shader_source: &str,
+        fragment_shader_source: &str,
+    ) -> Result<(), ShaderError> {
+        let mut program = Self::from_sources(vertex_shader_source, fragment_shader_source)?;
+        for uniform_name in self.uniform_ids.keys() {
+            let uniform_location = program.get_uniform_location(uniform_name);
+            if uniform_location < 0 {
+                return Err(ShaderError::MissingUniform(uniform_name.clone()));
+            }
+            program
+                .uniform_ids
+                .insert(uniform_name.clone(), uniform_location);
+        }
+
+        *self = program;
+        Ok(())
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::UseProgram(self.program_handle);
@@ -133,13 +189,17 @@ impl ShaderProgram {
         }
     }
 
-    pub fn create_uniform(&mut self, uniform_name: &str) {
-        let uniform_location = unsafe {
+    fn get_uniform_location(&self, uniform_name: &str) -> GLint {
+        unsafe {
             gl::GetUniformLocation(
                 self.program_handle,
                 CString::new(uniform_name).unwrap().as_ptr(),
             )
-        };
+        }
+    }
+
+    pub fn create_uniform(&mut self, uniform_name: &str) {
+        let uniform_location = self.get_uniform_location(uniform_name);
         if uniform_location < 0 {
             panic!("Cannot locate uniform: {}", uniform_name);
         } else {
@@ -170,4 +230,29 @@ impl ShaderProgram {
     pub fn set_float_uniform(&self, uniform_name: &str, float: f32) {
         unsafe { gl::Uniform1f(self.uniform_ids[uniform_name], float) }
     }
+
+    pub fn set_vector4f_uniform(&self, uniform_name: &str, vector: &Vector4<f32>) {
+        unsafe {
+            gl::Uniform4f(
+                self.uniform_ids[uniform_name],
+                vector.x,
+                vector.y,
+                vector.z,
+                vector.w,
+            )
+        }
+    }
+
+    pub fn set_int_uniform(&self, uniform_name: &str, int: i32) {
+        unsafe { gl::Uniform1i(self.uniform_ids[uniform_name], int) }
+    }
+}
+
+impl Drop for ShaderProgram {
+    fn drop(&mut self) {
+        track_delete(GpuResourceKind::ShaderProgram, self.program_handle);
+        unsafe {
+            gl::DeleteProgram(self.program_handle);
+        }
+    }
 }
@@ -0,0 +1,80 @@
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Texture2D;
+
+/// Rectangle of a texture in texture coordinates, e.g. one sprite of a sprite sheet or one
+/// tile of a tileset. It does not own the texture it refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubTexture {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl SubTexture {
+    pub fn new(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        SubTexture { min, max }
+    }
+
+    /// The whole texture.
+    pub fn full() -> Self {
+        Self::new(Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0))
+    }
+
+    /// Rectangle given in pixels from the top left corner, the way image editors and Tiled
+    /// measure it.
+    pub fn from_pixels(
+        texture_width: u32,
+        texture_height: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture_size = Vector2::new(texture_width as f32, texture_height as f32);
+        // Textures are uploaded bottom row first, so v grows upwards
+        let min = Vector2::new(x as f32, (texture_height - y - height) as f32);
+        let max = min + Vector2::new(width as f32, height as f32);
+
+        Self::new(
+            min.div_element_wise(texture_size),
+            max.div_element_wise(texture_size),
+        )
+    }
+
+    /// Cell `coords` of a grid of `cell_size` pixels, counted from the bottom left corner.
+    /// `sprite_size` is in cells, for sprites spanning more than one.
+    pub fn from_grid(
+        texture: &Texture2D,
+        coords: Vector2<u32>,
+        cell_size: Vector2<u32>,
+        sprite_size: Vector2<u32>,
+    ) -> Self {
+        let texture_size = Vector2::new(texture.get_width() as f32, texture.get_height() as f32);
+        let min = Vector2::new(
+            (coords.x * cell_size.x) as f32,
+            (coords.y * cell_size.y) as f32,
+        );
+        let max = min
+            + Vector2::new(
+                (sprite_size.x * cell_size.x) as f32,
+                (sprite_size.y * cell_size.y) as f32,
+            );
+
+        Self::new(
+            min.div_element_wise(texture_size),
+            max.div_element_wise(texture_size),
+        )
+    }
+
+    /// Coordinates of the bottom left, bottom right, top right and top left corners, the
+    /// order the renderer emits quad vertices in.
+    pub fn get_texture_coords(&self) -> [Vector2<f32>; 4] {
+        [
+            Vector2::new(self.min.x, self.min.y),
+            Vector2::new(self.max.x, self.min.y),
+            Vector2::new(self.max.x, self.max.y),
+            Vector2::new(self.min.x, self.max.y),
+        ]
+    }
+}
@@ -0,0 +1,931 @@
+//! Turns flattened paths into triangles on the CPU. Anti-aliasing adds a fringe of
+//! `feather` units around the shapes whose coverage fades from 1 to 0, multiplied into the
+//! alpha when drawn.
+
+use cgmath::*;
+
+use crate::math::AABB;
+use crate::renderer::Polyline;
+
+/// Which parts of overlapping or nested contours are inside.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside where the contours wind around a point a non-zero number of times, so holes
+    /// must go the other way.
+    #[default]
+    NonZero,
+    /// Inside where a ray crosses an odd number of edges, every nested contour is a hole.
+    EvenOdd,
+}
+
+impl FillRule {
+    pub fn is_inside(&self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// Shape of the corners between the segments of a stroke.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineJoin {
+    /// Sharp corner, beveled when longer than `StrokeStyle::miter_limit` times the width.
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// Shape of the ends of open strokes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineCap {
+    /// Stops at the end point.
+    #[default]
+    Butt,
+    Round,
+    /// Goes on for half the width.
+    Square,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    /// Alternating dash and gap lengths, repeated along the path. Empty for a solid line.
+    pub dashes: Vec<f32>,
+    /// Distance into the dash pattern at which the path starts.
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 1.0,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        StrokeStyle {
+            width,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathVertex {
+    pub position: Vector2<f32>,
+    /// 1 on the shape, 0 at the outer edge of the anti-aliasing fringe.
+    pub coverage: f32,
+}
+
+/// Triangles produced by `fill_polylines` and `stroke_polylines`, three indices each.
+#[derive(Clone, Debug, Default)]
+pub struct VectorMesh {
+    pub vertices: Vec<PathVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl VectorMesh {
+    pub fn get_triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Box around every vertex, fringe included.
+    pub fn get_bounds(&self) -> Option<AABB> {
+        let first = self.vertices.first()?.position;
+        Some(
+            self.vertices
+                .iter()
+                .fold(AABB::new(first, first), |bounds, vertex| {
+                    bounds.merge(&AABB::new(vertex.position, vertex.position))
+                }),
+        )
+    }
+
+    /// Area of the fully covered triangles, without the fringe.
+    pub fn get_covered_area(&self) -> f32 {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| self.vertices[triangle[corner] as usize]))
+            .filter(|triangle| triangle.iter().all(|vertex| vertex.coverage >= 1.0))
+            .map(|[a, b, c]| {
+                (b.position - a.position)
+                    .perp_dot(c.position - a.position)
+                    .abs()
+                    / 2.0
+            })
+            .sum()
+    }
+
+    /// Appends the triangles of another mesh.
+    pub fn append(&mut self, other: &VectorMesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + offset));
+    }
+
+    fn push_vertex(&mut self, position: Vector2<f32>, coverage: f32) -> u32 {
+        self.vertices.push(PathVertex { position, coverage });
+        self.vertices.len() as u32 - 1
+    }
+
+    fn push_triangle(&mut self, corners: [(Vector2<f32>, f32); 3]) {
+        for (position, coverage) in corners {
+            let index = self.push_vertex(position, coverage);
+            self.indices.push(index);
+        }
+    }
+
+    // Two triangles, corners going around the quad
+    fn push_quad(&mut self, corners: [(Vector2<f32>, f32); 4]) {
+        let [a, b, c, d] = corners.map(|(position, coverage)| self.push_vertex(position, coverage));
+        self.indices.extend_from_slice(&[a, b, c, c, d, a]);
+    }
+
+    // Fringe outside the edge from `a` to `b`, on the side of `outward`
+    fn push_fringe(
+        &mut self,
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+        outward: Vector2<f32>,
+        feather: f32,
+    ) {
+        if feather <= 0.0 {
+            return;
+        }
+        let offset = outward * feather;
+        self.push_quad([(a, 1.0), (b, 1.0), (b + offset, 0.0), (a + offset, 0.0)]);
+    }
+
+    // Pie slice of `sweep` radians from `from`, e.g. round joins and caps
+    fn push_fan(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        from: Vector2<f32>,
+        sweep: f32,
+        tolerance: f32,
+        feather: f32,
+    ) {
+        let segments = arc_segments(radius, sweep, tolerance);
+        let start = from.y.atan2(from.x);
+        let direction = |index: u32| {
+            let angle = start + sweep * index as f32 / segments as f32;
+            Vector2::new(angle.cos(), angle.sin())
+        };
+
+        for index in 0..segments {
+            let (first, second) = (direction(index), direction(index + 1));
+            let (p1, p2) = (center + first * radius, center + second * radius);
+            self.push_triangle([(center, 1.0), (p1, 1.0), (p2, 1.0)]);
+            if feather > 0.0 {
+                self.push_quad([
+                    (p1, 1.0),
+                    (p2, 1.0),
+                    (p2 + second * feather, 0.0),
+                    (p1 + first * feather, 0.0),
+                ]);
+            }
+        }
+    }
+}
+
+/// Winding number of the closed contours around `point`, counter-clockwise being positive.
+pub fn winding_number(polylines: &[Polyline], point: Vector2<f32>) -> i32 {
+    let mut winding = 0;
+    for (a, b) in closed_edges(polylines) {
+        let side = (b - a).perp_dot(point - a);
+        if a.y <= point.y && b.y > point.y && side > 0.0 {
+            winding += 1;
+        } else if b.y <= point.y && a.y > point.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+/// Triangulates the inside of the contours, which are closed even if their polyline is
+/// not. Works for any contours, self-intersecting or nested, by splitting them into
+/// horizontal slabs at every vertex and crossing and filling the spans inside each slab.
+pub fn fill_polylines(polylines: &[Polyline], rule: FillRule, feather: f32) -> VectorMesh {
+    let mut mesh = VectorMesh::default();
+    let edges: Vec<_> = closed_edges(polylines)
+        .filter(|(a, b)| a.y != b.y)
+        .collect();
+    if edges.is_empty() {
+        return mesh;
+    }
+
+    let mut rows: Vec<f32> = edges.iter().flat_map(|(a, b)| [a.y, b.y]).collect();
+    for (index, first) in edges.iter().enumerate() {
+        for second in &edges[index + 1..] {
+            if let Some(crossing) = intersection(*first, *second) {
+                rows.push(crossing.y);
+            }
+        }
+    }
+    rows.sort_by(|a, b| a.total_cmp(b));
+    rows.dedup();
+
+    let mut crossings: Vec<(f32, f32, f32, i32)> = Vec::new();
+    for slab in rows.windows(2) {
+        let (top, bottom) = (slab[0], slab[1]);
+        let middle = (top + bottom) / 2.0;
+
+        crossings.clear();
+        for (a, b) in &edges {
+            let (low, high) = if a.y < b.y { (a, b) } else { (b, a) };
+            if low.y > middle || high.y < middle {
+                continue;
+            }
+            let x_at = |y: f32| low.x + (high.x - low.x) * (y - low.y) / (high.y - low.y);
+            let direction = if b.y > a.y { 1 } else { -1 };
+            crossings.push((x_at(middle), x_at(top), x_at(bottom), direction));
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding -= pair[0].3;
+            if rule.is_inside(winding) {
+                let (left, right) = (pair[0], pair[1]);
+                mesh.push_quad([
+                    (Vector2::new(left.1, top), 1.0),
+                    (Vector2::new(right.1, top), 1.0),
+                    (Vector2::new(right.2, bottom), 1.0),
+                    (Vector2::new(left.2, bottom), 1.0),
+                ]);
+            }
+        }
+    }
+
+    if feather > 0.0 {
+        add_fill_fringe(&mut mesh, polylines, rule, feather);
+    }
+    mesh
+}
+
+// Fringe on the outer side of every edge that separates the inside from the outside
+fn add_fill_fringe(mesh: &mut VectorMesh, polylines: &[Polyline], rule: FillRule, feather: f32) {
+    let is_inside = |point: Vector2<f32>| rule.is_inside(winding_number(polylines, point));
+
+    for polyline in polylines {
+        let points = &polyline.points;
+        let count = points.len();
+        let outward: Vec<Option<Vector2<f32>>> = (0..count)
+            .map(|index| {
+                let (a, b) = (points[index], points[(index + 1) % count]);
+                let length = (b - a).magnitude();
+                if length == 0.0 {
+                    return None;
+                }
+                let normal = Vector2::new(b.y - a.y, a.x - b.x) / length;
+                let probe = normal * length.min(feather) * 0.01;
+                let middle = (a + b) / 2.0;
+                match (is_inside(middle + probe), is_inside(middle - probe)) {
+                    (false, true) => Some(normal),
+                    (true, false) => Some(-normal),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        for index in 0..count {
+            let (a, b) = (points[index], points[(index + 1) % count]);
+            let Some(normal) = outward[index] else {
+                continue;
+            };
+            mesh.push_fringe(a, b, normal, feather);
+
+            // Fills the gap between the fringes of a convex corner
+            let next = (index + 1) % count;
+            if let Some(next_normal) = outward[next] {
+                let direction = points[(next + 1) % count] - b;
+                if normal.dot(direction) < 0.0 {
+                    mesh.push_triangle([
+                        (b, 1.0),
+                        (b + normal * feather, 0.0),
+                        (b + next_normal * feather, 0.0),
+                    ]);
+                }
+            }
+        }
+    }
+}
+
+/// Triangulates a stroke of `style` along the polylines. Round joins and caps stray from
+/// circles by at most `tolerance`.
+pub fn stroke_polylines(
+    polylines: &[Polyline],
+    style: &StrokeStyle,
+    tolerance: f32,
+    feather: f32,
+) -> VectorMesh {
+    let mut mesh = VectorMesh::default();
+    if style.width <= 0.0 {
+        return mesh;
+    }
+
+    let dashed;
+    let polylines = if style.dashes.is_empty() {
+        polylines
+    } else {
+        dashed = dash_polylines(polylines, &style.dashes, style.dash_offset);
+        &dashed
+    };
+
+    for polyline in polylines {
+        stroke_polyline(&mut mesh, polyline, style, tolerance, feather);
+    }
+    mesh
+}
+
+fn stroke_polyline(
+    mesh: &mut VectorMesh,
+    polyline: &Polyline,
+    style: &StrokeStyle,
+    tolerance: f32,
+    feather: f32,
+) {
+    let points = &polyline.points;
+    if points.len() < 2 {
+        return;
+    }
+    let closed = polyline.closed && points.len() > 2;
+    let segment_count = if closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+
+    let half = style.width / 2.0;
+    let directions: Vec<Vector2<f32>> = (0..segment_count)
+        .map(|index| {
+            let direction = points[(index + 1) % points.len()] - points[index];
+            if direction.magnitude2() > 0.0 {
+                direction.normalize()
+            } else {
+                Vector2::unit_x()
+            }
+        })
+        .collect();
+    let left = |direction: Vector2<f32>| Vector2::new(-direction.y, direction.x);
+
+    for index in 0..segment_count {
+        let direction = directions[index];
+        let mut a = points[index];
+        let mut b = points[(index + 1) % points.len()];
+
+        if !closed && style.cap == LineCap::Square {
+            if index == 0 {
+                a -= direction * half;
+            }
+            if index == segment_count - 1 {
+                b += direction * half;
+            }
+        }
+
+        let normal = left(direction);
+        mesh.push_quad([
+            (a + normal * half, 1.0),
+            (b + normal * half, 1.0),
+            (b - normal * half, 1.0),
+            (a - normal * half, 1.0),
+        ]);
+        mesh.push_fringe(a + normal * half, b + normal * half, normal, feather);
+        mesh.push_fringe(a - normal * half, b - normal * half, -normal, feather);
+    }
+
+    let joints = if closed {
+        0..points.len()
+    } else {
+        1..points.len() - 1
+    };
+    for index in joints {
+        let incoming = directions[(index + segment_count - 1) % segment_count];
+        let outgoing = directions[index % segment_count];
+        push_join(
+            mesh,
+            points[index],
+            incoming,
+            outgoing,
+            style,
+            half,
+            tolerance,
+            feather,
+        );
+    }
+
+    if !closed {
+        let last = points.len() - 1;
+        push_cap(
+            mesh,
+            points[0],
+            -directions[0],
+            style.cap,
+            half,
+            tolerance,
+            feather,
+        );
+        push_cap(
+            mesh,
+            points[last],
+            directions[segment_count - 1],
+            style.cap,
+            half,
+            tolerance,
+            feather,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn push_join(
+    mesh: &mut VectorMesh,
+    point: Vector2<f32>,
+    incoming: Vector2<f32>,
+    outgoing: Vector2<f32>,
+    style: &StrokeStyle,
+    half: f32,
+    tolerance: f32,
+    feather: f32,
+) {
+    let turn = incoming.perp_dot(outgoing);
+    if turn.abs() < 1e-6 && incoming.dot(outgoing) > 0.0 {
+        return;
+    }
+
+    // The gap to fill is on the outer side of the turn
+    let side = if turn > 0.0 { -1.0 } else { 1.0 };
+    let first = Vector2::new(-incoming.y, incoming.x) * side;
+    let second = Vector2::new(-outgoing.y, outgoing.x) * side;
+    let (p1, p2) = (point + first * half, point + second * half);
+
+    let bevel = |mesh: &mut VectorMesh| {
+        mesh.push_triangle([(point, 1.0), (p1, 1.0), (p2, 1.0)]);
+        let outward = (first + second).normalize();
+        mesh.push_fringe(p1, p2, outward, feather);
+    };
+
+    match style.join {
+        LineJoin::Bevel => bevel(mesh),
+        LineJoin::Round => {
+            let sweep = first.perp_dot(second).atan2(first.dot(second));
+            mesh.push_fan(point, half, first, sweep, tolerance, feather);
+        }
+        LineJoin::Miter => {
+            let middle = first + second;
+            let cosine = middle.magnitude() / 2.0;
+            if middle.magnitude2() == 0.0 || 1.0 / cosine > style.miter_limit {
+                bevel(mesh);
+                return;
+            }
+
+            let tip = point + middle.normalize() * (half / cosine);
+            mesh.push_triangle([(point, 1.0), (p1, 1.0), (tip, 1.0)]);
+            mesh.push_triangle([(point, 1.0), (tip, 1.0), (p2, 1.0)]);
+            mesh.push_fringe(p1, tip, (first + middle.normalize()).normalize(), feather);
+            mesh.push_fringe(tip, p2, (second + middle.normalize()).normalize(), feather);
+        }
+    }
+}
+
+// `forward` points away from the line
+fn push_cap(
+    mesh: &mut VectorMesh,
+    point: Vector2<f32>,
+    forward: Vector2<f32>,
+    cap: LineCap,
+    half: f32,
+    tolerance: f32,
+    feather: f32,
+) {
+    let normal = Vector2::new(-forward.y, forward.x);
+    match cap {
+        LineCap::Butt => mesh.push_fringe(
+            point + normal * half,
+            point - normal * half,
+            forward,
+            feather,
+        ),
+        LineCap::Square => {
+            let end = point + forward * half;
+            mesh.push_fringe(end + normal * half, end - normal * half, forward, feather)
+        }
+        LineCap::Round => mesh.push_fan(
+            point,
+            half,
+            normal,
+            -std::f32::consts::PI,
+            tolerance,
+            feather,
+        ),
+    }
+}
+
+/// Splits the polylines into the dashes of a pattern of alternating dash and gap lengths.
+/// Patterns with an odd number of lengths are repeated once, like in SVG.
+pub fn dash_polylines(polylines: &[Polyline], pattern: &[f32], offset: f32) -> Vec<Polyline> {
+    let mut pattern = pattern.to_vec();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_within(..);
+    }
+    let period: f32 = pattern.iter().sum();
+    if pattern.iter().any(|length| *length < 0.0) || period <= 0.0 {
+        return polylines.to_vec();
+    }
+
+    let mut dashes = Vec::new();
+    for polyline in polylines {
+        let mut points = polyline.points.clone();
+        if polyline.closed {
+            if let Some(first) = points.first() {
+                points.push(*first);
+            }
+        }
+
+        // Where the path starts in the pattern. Zero-length dashes at the start are kept, so
+        // they can become dots with round caps like anywhere else on the path.
+        let mut phase = offset.rem_euclid(period);
+        let mut index = 0;
+        while phase > pattern[index] || (phase > 0.0 && phase == pattern[index]) {
+            phase -= pattern[index];
+            index = (index + 1) % pattern.len();
+        }
+        let mut left = pattern[index] - phase;
+        let mut current = Polyline::default();
+        if index % 2 == 0 {
+            current.points.push(points[0]);
+        }
+
+        for segment in points.windows(2) {
+            let (mut a, b) = (segment[0], segment[1]);
+            let mut length = (b - a).magnitude();
+            while length > left {
+                let split = a + (b - a) * (left / length);
+                if index % 2 == 0 {
+                    current.points.push(split);
+                    dashes.push(std::mem::take(&mut current));
+                } else {
+                    current.points.push(split);
+                }
+                length -= left;
+                a = split;
+                index = (index + 1) % pattern.len();
+                left = pattern[index];
+            }
+            left -= length;
+            if index % 2 == 0 {
+                current.points.push(b);
+            }
+        }
+        if index % 2 == 0 && current.points.len() > 1 {
+            dashes.push(current);
+        }
+    }
+
+    dashes.retain(|dash| dash.points.len() > 1);
+    dashes
+}
+
+fn closed_edges(polylines: &[Polyline]) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>)> + '_ {
+    polylines.iter().flat_map(|polyline| {
+        let points = &polyline.points;
+        (0..points.len()).map(move |index| (points[index], points[(index + 1) % points.len()]))
+    })
+}
+
+// Crossing point of two segments strictly inside both
+fn intersection(
+    (a, b): (Vector2<f32>, Vector2<f32>),
+    (c, d): (Vector2<f32>, Vector2<f32>),
+) -> Option<Vector2<f32>> {
+    let (ab, cd) = (b - a, d - c);
+    let denominator = ab.perp_dot(cd);
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let t = (c - a).perp_dot(cd) / denominator;
+    let u = (c - a).perp_dot(ab) / denominator;
+    if t > 0.0 && t < 1.0 && u > 0.0 && u < 1.0 {
+        Some(a + ab * t)
+    } else {
+        None
+    }
+}
+
+// Segments for an arc of `sweep` radians straying at most `tolerance` from the circle
+fn arc_segments(radius: f32, sweep: f32, tolerance: f32) -> u32 {
+    let tolerance = tolerance.clamp(1e-4 * radius.max(1e-4), radius.max(1e-4));
+    let step = 2.0 * (1.0 - tolerance / radius.max(1e-4)).clamp(-1.0, 1.0).acos();
+    ((sweep.abs() / step.max(1e-3)).ceil() as u32).clamp(1, 256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn polyline(points: &[(f32, f32)]) -> Polyline {
+        Polyline {
+            points: points.iter().map(|&(x, y)| Vector2::new(x, y)).collect(),
+            closed: true,
+        }
+    }
+
+    fn open(points: &[(f32, f32)]) -> Polyline {
+        Polyline {
+            closed: false,
+            ..polyline(points)
+        }
+    }
+
+    // Counter-clockwise square, clockwise if `size` is negative
+    fn square(min: f32, size: f32) -> Polyline {
+        let max = min + size.abs();
+        let mut square = polyline(&[(min, min), (max, min), (max, max), (min, max)]);
+        if size < 0.0 {
+            square.points.reverse();
+        }
+        square
+    }
+
+    fn assert_near(value: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{} is not {}",
+            value,
+            expected
+        );
+    }
+
+    fn dash_ends(dashes: &[Polyline]) -> Vec<(f32, f32)> {
+        dashes
+            .iter()
+            .map(|dash| (dash.points[0].x, dash.points.last().unwrap().x))
+            .collect()
+    }
+
+    #[test]
+    fn fills_nested_contours() {
+        let same_direction = [square(0.0, 4.0), square(1.0, 2.0)];
+        let covered =
+            |polylines: &[Polyline], rule| fill_polylines(polylines, rule, 0.0).get_covered_area();
+        assert_near(covered(&same_direction, FillRule::NonZero), 16.0, 1e-4);
+        assert_near(covered(&same_direction, FillRule::EvenOdd), 12.0, 1e-4);
+
+        let hole = [square(0.0, 4.0), square(1.0, -2.0)];
+        assert_near(covered(&hole, FillRule::NonZero), 12.0, 1e-4);
+        assert_near(covered(&hole, FillRule::EvenOdd), 12.0, 1e-4);
+    }
+
+    #[test]
+    fn fills_overlapping_contours() {
+        let squares = [square(0.0, 2.0), square(1.0, 2.0)];
+        let nonzero = fill_polylines(&squares, FillRule::NonZero, 0.0);
+        let even_odd = fill_polylines(&squares, FillRule::EvenOdd, 0.0);
+        assert_near(nonzero.get_covered_area(), 7.0, 1e-4);
+        assert_near(even_odd.get_covered_area(), 6.0, 1e-4);
+    }
+
+    #[test]
+    fn fills_self_intersecting_contours() {
+        // Each half of a bowtie winds once, in opposite directions
+        let bowtie = [polyline(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)])];
+        for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            assert_near(
+                fill_polylines(&bowtie, rule, 0.0).get_covered_area(),
+                2.0,
+                1e-4,
+            );
+        }
+
+        // The center of a pentagram winds twice
+        let star: Vec<(f32, f32)> = (0..5)
+            .map(|point| {
+                let angle = PI / 2.0 + point as f32 * 4.0 * PI / 5.0;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        let star = [polyline(&star)];
+        let inner_radius = (2.0 * PI / 5.0).cos() / (PI / 5.0).cos();
+        let outline = 5.0 * inner_radius * (PI / 5.0).sin();
+        let pentagon = 2.5 * inner_radius * inner_radius * (2.0 * PI / 5.0).sin();
+
+        let nonzero = fill_polylines(&star, FillRule::NonZero, 0.0);
+        let even_odd = fill_polylines(&star, FillRule::EvenOdd, 0.0);
+        assert_near(nonzero.get_covered_area(), outline, 1e-4);
+        assert_near(even_odd.get_covered_area(), outline - pentagon, 1e-4);
+    }
+
+    #[test]
+    fn fringes_fade_out_around_fills() {
+        let feather = 0.5;
+        let mesh = fill_polylines(&[square(0.0, 4.0)], FillRule::NonZero, feather);
+
+        // The fringe does not count as covered and fades to 0 exactly `feather` out
+        assert_near(mesh.get_covered_area(), 16.0, 1e-4);
+        for vertex in &mesh.vertices {
+            let distance = (vertex.position.x - 2.0)
+                .abs()
+                .max((vertex.position.y - 2.0).abs())
+                - 2.0;
+            match vertex.coverage {
+                1.0 => assert!(distance <= 1e-5),
+                0.0 => assert_near(distance, feather, 1e-5),
+                coverage => panic!("unexpected coverage {}", coverage),
+            }
+        }
+
+        let bounds = mesh.get_bounds().unwrap();
+        assert_near(bounds.min().x, -feather, 1e-5);
+        assert_near(bounds.max().y, 4.0 + feather, 1e-5);
+
+        let sharp = fill_polylines(&[square(0.0, 4.0)], FillRule::NonZero, 0.0);
+        assert!(sharp.vertices.iter().all(|vertex| vertex.coverage == 1.0));
+    }
+
+    #[test]
+    fn fringes_go_into_holes() {
+        let polylines = [square(0.0, 4.0), square(1.0, 2.0)];
+        let mesh = fill_polylines(&polylines, FillRule::EvenOdd, 0.25);
+
+        // Every faded vertex is outside the filled ring, some of them inside the hole
+        let in_hole =
+            |point: Vector2<f32>| (1.0..=3.0).contains(&point.x) && (1.0..=3.0).contains(&point.y);
+        let in_ring = |point: Vector2<f32>| {
+            point.x > 0.0 && point.x < 4.0 && point.y > 0.0 && point.y < 4.0 && !in_hole(point)
+        };
+        let faded: Vec<_> = mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.coverage == 0.0)
+            .map(|vertex| vertex.position)
+            .collect();
+        assert!(faded.iter().all(|&point| !in_ring(point)));
+        assert!(faded.iter().any(|&point| in_hole(point)));
+    }
+
+    #[test]
+    fn strokes_caps() {
+        let line = [open(&[(0.0, 0.0), (10.0, 0.0)])];
+        let area = |cap| {
+            let style = StrokeStyle {
+                cap,
+                ..StrokeStyle::new(2.0)
+            };
+            stroke_polylines(&line, &style, 0.001, 0.0).get_covered_area()
+        };
+
+        assert_near(area(LineCap::Butt), 20.0, 1e-4);
+        assert_near(area(LineCap::Square), 24.0, 1e-4);
+        assert_near(area(LineCap::Round), 20.0 + PI, 0.01);
+    }
+
+    #[test]
+    fn strokes_joins() {
+        // Two 10 by 2 segments meeting at a right angle, the join filling the outer corner
+        let corner = [open(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)])];
+        let area = |join, miter_limit| {
+            let style = StrokeStyle {
+                join,
+                miter_limit,
+                ..StrokeStyle::new(2.0)
+            };
+            stroke_polylines(&corner, &style, 0.001, 0.0).get_covered_area()
+        };
+
+        assert_near(area(LineJoin::Bevel, 4.0), 40.5, 1e-4);
+        assert_near(area(LineJoin::Miter, 4.0), 41.0, 1e-4);
+        assert_near(area(LineJoin::Round, 4.0), 40.0 + PI / 4.0, 0.01);
+        // Right angles need a limit of √2
+        assert_near(area(LineJoin::Miter, 1.4), 40.5, 1e-4);
+    }
+
+    #[test]
+    fn strokes_closed_polylines_without_caps() {
+        let style = StrokeStyle {
+            cap: LineCap::Square,
+            ..StrokeStyle::new(2.0)
+        };
+        // Four 4 by 2 sides and four mitered corners
+        let mesh = stroke_polylines(&[square(0.0, 4.0)], &style, 0.001, 0.0);
+        assert_near(mesh.get_covered_area(), 36.0, 1e-4);
+    }
+
+    #[test]
+    fn strokes_fringes_outside_the_stroke() {
+        let line = [open(&[(0.0, 0.0), (10.0, 0.0)])];
+        let mesh = stroke_polylines(&line, &StrokeStyle::new(2.0), 0.01, 0.5);
+
+        assert_near(mesh.get_covered_area(), 20.0, 1e-4);
+        for vertex in mesh.vertices.iter().filter(|vertex| vertex.coverage == 0.0) {
+            let outside = vertex.position.y.abs() - 1.0;
+            let past_end = (-vertex.position.x).max(vertex.position.x - 10.0);
+            assert_near(outside.max(past_end), 0.5, 1e-5);
+        }
+    }
+
+    #[test]
+    fn dashes_follow_the_offset() {
+        let line = [open(&[(0.0, 0.0), (10.0, 0.0)])];
+
+        let dashes = dash_polylines(&line, &[2.0, 1.0], 0.0);
+        assert_eq!(
+            dash_ends(&dashes),
+            [(0.0, 2.0), (3.0, 5.0), (6.0, 8.0), (9.0, 10.0)]
+        );
+
+        let dashes = dash_polylines(&line, &[2.0, 1.0], 1.0);
+        assert_eq!(
+            dash_ends(&dashes),
+            [(0.0, 1.0), (2.0, 4.0), (5.0, 7.0), (8.0, 10.0)]
+        );
+
+        // Negative offsets and whole periods wrap around
+        let dashes = dash_polylines(&line, &[2.0, 1.0], -1.0);
+        assert_eq!(dash_ends(&dashes), [(1.0, 3.0), (4.0, 6.0), (7.0, 9.0)]);
+        assert_eq!(
+            dash_polylines(&line, &[2.0, 1.0], 7.0),
+            dash_polylines(&line, &[2.0, 1.0], 1.0)
+        );
+
+        // Starting right at the end of a dash starts with the gap
+        let dashes = dash_polylines(&line, &[2.0, 1.0], 2.0);
+        assert_eq!(dash_ends(&dashes)[0], (1.0, 3.0));
+    }
+
+    #[test]
+    fn dashes_repeat_odd_patterns() {
+        let line = [open(&[(0.0, 0.0), (10.0, 0.0)])];
+        let dashes = dash_polylines(&line, &[3.0], 0.0);
+        assert_eq!(dash_ends(&dashes), [(0.0, 3.0), (6.0, 9.0)]);
+    }
+
+    #[test]
+    fn dashes_keep_zero_length_entries_as_dots() {
+        let line = [open(&[(0.0, 0.0), (10.0, 0.0)])];
+        let dashes = dash_polylines(&line, &[0.0, 2.0], 0.0);
+        assert_eq!(
+            dash_ends(&dashes),
+            [(0.0, 0.0), (2.0, 2.0), (4.0, 4.0), (6.0, 6.0), (8.0, 8.0)]
+        );
+
+        // Which round caps turn into dots
+        let style = StrokeStyle {
+            cap: LineCap::Round,
+            dashes: vec![0.0, 2.0],
+            ..StrokeStyle::new(1.0)
+        };
+        let mesh = stroke_polylines(&line, &style, 0.001, 0.0);
+        assert_near(mesh.get_covered_area(), 5.0 * PI / 4.0, 0.02);
+
+        // A zero gap joins the dashes up
+        let dashes = dash_polylines(&line, &[2.0, 0.0], 0.0);
+        let length: f32 = dashes
+            .iter()
+            .map(|dash| dash.points.last().unwrap().x - dash.points[0].x)
+            .sum();
+        assert_near(length, 10.0, 1e-5);
+    }
+
+    #[test]
+    fn dashes_ignore_invalid_patterns() {
+        let line = [open(&[(0.0, 0.0), (10.0, 0.0)])];
+        assert_eq!(dash_polylines(&line, &[0.0, 0.0], 0.0), line);
+        assert_eq!(dash_polylines(&line, &[2.0, -1.0], 0.0), line);
+    }
+
+    #[test]
+    fn dashes_go_around_closed_polylines() {
+        // 16 units around, the last gap ending where the square starts
+        let dashes = dash_polylines(&[square(0.0, 4.0)], &[3.0, 1.0], 0.0);
+        assert_eq!(dashes.len(), 4);
+        assert_eq!(
+            dashes[0].points,
+            [Vector2::new(0.0, 0.0), Vector2::new(3.0, 0.0)]
+        );
+        assert_eq!(
+            dashes[1].points,
+            [Vector2::new(4.0, 0.0), Vector2::new(4.0, 3.0)]
+        );
+        assert_eq!(*dashes[3].points.last().unwrap(), Vector2::new(0.0, 1.0));
+    }
+}
@@ -1,109 +1,359 @@
-use std::ffi::CString;
-
+use cgmath::*;
 use gl::types::*;
 
+use crate::renderer::{track_create, track_delete, GpuResourceKind, Image};
+
+// Core in OpenGL 4.6 (and ARB/EXT_texture_filter_anisotropic), not exposed by the 4.5 bindings.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    RGBA16F,
+    RGBA32F,
+    SRGB8,
+    SRGB8Alpha8,
+}
+
+impl TextureFormat {
+    pub fn internal_format(&self) -> GLenum {
+        match self {
+            TextureFormat::R8 => gl::R8,
+            TextureFormat::RG8 => gl::RG8,
+            TextureFormat::RGB8 => gl::RGB8,
+            TextureFormat::RGBA8 => gl::RGBA8,
+            TextureFormat::RGBA16F => gl::RGBA16F,
+            TextureFormat::RGBA32F => gl::RGBA32F,
+            TextureFormat::SRGB8 => gl::SRGB8,
+            TextureFormat::SRGB8Alpha8 => gl::SRGB8_ALPHA8,
+        }
+    }
+
+    pub fn data_format(&self) -> GLenum {
+        match self {
+            TextureFormat::R8 => gl::RED,
+            TextureFormat::RG8 => gl::RG,
+            TextureFormat::RGB8 | TextureFormat::SRGB8 => gl::RGB,
+            TextureFormat::RGBA8
+            | TextureFormat::RGBA16F
+            | TextureFormat::RGBA32F
+            | TextureFormat::SRGB8Alpha8 => gl::RGBA,
+        }
+    }
+
+    pub fn data_type(&self) -> GLenum {
+        match self {
+            TextureFormat::RGBA16F => gl::HALF_FLOAT,
+            TextureFormat::RGBA32F => gl::FLOAT,
+            _ => gl::UNSIGNED_BYTE,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::RG8 => 2,
+            TextureFormat::RGB8 | TextureFormat::SRGB8 => 3,
+            TextureFormat::RGBA8 | TextureFormat::SRGB8Alpha8 => 4,
+            TextureFormat::RGBA16F => 4 * 2,
+            TextureFormat::RGBA32F => 4 * 4,
+        }
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        matches!(self, TextureFormat::SRGB8 | TextureFormat::SRGB8Alpha8)
+    }
+
+    // Picks the 8 bit format matching an image's channel count, keeping the color space of `self`
+    fn for_channels(&self, channels: i32) -> TextureFormat {
+        match (channels, self.is_srgb()) {
+            (1, _) => TextureFormat::R8,
+            (2, _) => TextureFormat::RG8,
+            (3, false) => TextureFormat::RGB8,
+            (3, true) => TextureFormat::SRGB8,
+            (4, false) => TextureFormat::RGBA8,
+            (4, true) => TextureFormat::SRGB8Alpha8,
+            _ => panic!("Images with {} channels are not supported!", channels),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextureSpec {
+    pub format: TextureFormat,
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub border_color: Vector4<f32>, // only used with TextureWrap::ClampToBorder
+    pub generate_mipmaps: bool,
+    pub mipmap_filter: TextureFilter, // filter between mip levels when generate_mipmaps is set
+    pub anisotropy: f32,              // 1.0 disables anisotropic filtering
+}
+
+impl Default for TextureSpec {
+    fn default() -> Self {
+        TextureSpec {
+            format: TextureFormat::RGBA8,
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            wrap_s: TextureWrap::Repeat,
+            wrap_t: TextureWrap::Repeat,
+            border_color: Vector4::new(0.0, 0.0, 0.0, 0.0),
+            generate_mipmaps: false,
+            mipmap_filter: TextureFilter::Linear,
+            anisotropy: 1.0,
+        }
+    }
+}
+
+fn filter_to_gl(filter: TextureFilter) -> GLenum {
+    match filter {
+        TextureFilter::Nearest => gl::NEAREST,
+        TextureFilter::Linear => gl::LINEAR,
+    }
+}
+
+fn min_filter_to_gl(spec: &TextureSpec) -> GLenum {
+    if !spec.generate_mipmaps {
+        return filter_to_gl(spec.min_filter);
+    }
+
+    match (spec.min_filter, spec.mipmap_filter) {
+        (TextureFilter::Nearest, TextureFilter::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, TextureFilter::Linear) => gl::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, TextureFilter::Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Linear, TextureFilter::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+fn wrap_to_gl(wrap: TextureWrap) -> GLenum {
+    match wrap {
+        TextureWrap::Repeat => gl::REPEAT,
+        TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        TextureWrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+    }
+}
+
+fn mip_level_count(width: u32, height: u32) -> i32 {
+    32 - width.max(height).max(1).leading_zeros() as i32
+}
+
 pub struct Texture2D {
     pub id: u32,
     width: u32,
     height: u32,
-    internal_format: GLenum,
-    data_format: GLenum,
+    spec: TextureSpec,
     path: String,
+    mutable_storage: bool, // textures created from images can be resized when reloaded
 }
 
 impl Texture2D {
     pub fn new(width: u32, height: u32) -> Self {
+        Self::from_spec(width, height, TextureSpec::default())
+    }
+
+    pub fn from_spec(width: u32, height: u32, spec: TextureSpec) -> Self {
+        Self::create(width, height, spec, false)
+    }
+
+    fn create(width: u32, height: u32, spec: TextureSpec, mutable_storage: bool) -> Self {
         let mut id: u32 = 0;
-        let internal_format = gl::RGBA8;
-        let data_format = gl::RGBA;
 
         unsafe {
             gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
-            gl::TextureStorage2D(id, 1, internal_format, width as i32, height as i32);
+            track_create(GpuResourceKind::Texture, id);
+
+            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, min_filter_to_gl(&spec) as i32);
+            gl::TextureParameteri(
+                id,
+                gl::TEXTURE_MAG_FILTER,
+                filter_to_gl(spec.mag_filter) as i32,
+            );
 
-            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, wrap_to_gl(spec.wrap_s) as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, wrap_to_gl(spec.wrap_t) as i32);
+            gl::TextureParameterfv(id, gl::TEXTURE_BORDER_COLOR, spec.border_color.as_ptr());
 
-            gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            if spec.anisotropy > 1.0 {
+                let mut max_anisotropy: f32 = 1.0;
+                gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+                gl::TextureParameterf(
+                    id,
+                    TEXTURE_MAX_ANISOTROPY,
+                    spec.anisotropy.min(max_anisotropy),
+                );
+            }
         };
 
-        Self {
+        let texture = Self {
             id,
             width,
             height,
-            internal_format,
-            data_format,
+            spec,
             path: String::new(),
-        }
+            mutable_storage,
+        };
+        texture.allocate_storage();
+        texture
     }
 
-    pub fn from_path(path: &str) -> Self {
-        let mut texture = Self::new(0, 0);
-        texture.path = path.to_string();
-        let mut width: i32 = 0;
-        let mut height: i32 = 0;
-        let mut channels: i32 = 0;
+    // Immutable storage lets the driver skip completeness checks, mutable storage can be
+    // re-specified with a new size while keeping the texture id
+    fn allocate_storage(&self) {
+        let format = self.spec.format;
+        let levels = if self.spec.generate_mipmaps {
+            mip_level_count(self.width, self.height)
+        } else {
+            1
+        };
 
-        let path_cstring = CString::new(path).expect("CString conversion failed");
         unsafe {
-            stb_image::stb_image::stbi_set_flip_vertically_on_load(1);
-            let data = stb_image::stb_image::stbi_load(
-                path_cstring.as_ptr(),
-                &mut width,
-                &mut height,
-                &mut channels,
-                0,
-            );
-            assert!(!data.is_null(), "Failed to load image!");
+            if !self.mutable_storage {
+                gl::TextureStorage2D(
+                    self.id,
+                    levels,
+                    format.internal_format(),
+                    self.width as i32,
+                    self.height as i32,
+                );
+                return;
+            }
+
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            for level in 0..levels {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    level,
+                    format.internal_format() as i32,
+                    (self.width >> level).max(1) as i32,
+                    (self.height >> level).max(1) as i32,
+                    0,
+                    format.data_format(),
+                    format.data_type(),
+                    std::ptr::null(),
+                );
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::TextureParameteri(self.id, gl::TEXTURE_MAX_LEVEL, levels - 1);
+        }
+    }
 
-            texture.width = width as u32;
-            texture.width = height as u32;
+    pub fn from_path(path: &str) -> Self {
+        let image =
+            Image::load(path).unwrap_or_else(|error| panic!("Failed to load {}: {}", path, error));
+        let mut texture = Self::from_image(&image, Self::default_image_spec());
+        texture.path = path.to_string();
+        texture
+    }
 
-            let (internal_format, data_format) = match channels {
-                4 => (gl::RGBA8, gl::RGBA),
-                3 => (gl::RGB8, gl::RGB),
-                _ => panic!("Format not supported!"),
-            };
+    /// Decodes an encoded image (PNG, JPEG, ...), e.g. one embedded with `include_bytes!`.
+    pub fn from_memory(bytes: &[u8]) -> Self {
+        let image = Image::from_memory(bytes)
+            .unwrap_or_else(|error| panic!("Failed to load image from memory: {}", error));
+        Self::from_image(&image, Self::default_image_spec())
+    }
 
-            texture.internal_format = internal_format;
-            texture.data_format = data_format;
+    /// Creates an RGBA8 texture from tightly packed pixels, first row at the top.
+    pub fn from_rgba(width: u32, height: u32, data: &[u8]) -> Self {
+        let image = Image::from_raw(width, height, 4, data.to_vec())
+            .unwrap_or_else(|error| panic!("Invalid RGBA data: {}", error));
+        Self::from_image(&image, TextureSpec::default())
+    }
 
-            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture.id);
-            gl::TextureStorage2D(texture.id, 1, internal_format, width, height);
+    fn default_image_spec() -> TextureSpec {
+        TextureSpec {
+            min_filter: TextureFilter::Linear,
+            ..TextureSpec::default()
+        }
+    }
 
-            gl::TextureParameteri(texture.id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TextureParameteri(texture.id, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    /// The pixel format is chosen from the image's channel count; `spec.format` only selects
+    /// whether color images are treated as sRGB.
+    pub fn from_image(image: &Image, spec: TextureSpec) -> Self {
+        let channels = image.get_channels() as i32;
+        let format = spec.format.for_channels(channels);
+        let texture = Self::create(
+            image.get_width(),
+            image.get_height(),
+            TextureSpec { format, ..spec },
+            true,
+        );
+        texture.upload_image(image);
+        texture
+    }
 
-            gl::TextureParameteri(texture.id, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TextureParameteri(texture.id, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+    /// Replaces the contents with `image` while keeping the same GL id, so anything holding
+    /// the id (like the renderer's texture slots) keeps working. Panics if the size or channel
+    /// count changes and the texture was not created from an image.
+    pub fn reload_image(&mut self, image: &Image) {
+        let format = self.spec.format.for_channels(image.get_channels() as i32);
+        let changed = image.get_width() != self.width
+            || image.get_height() != self.height
+            || format != self.spec.format;
 
-            gl::TextureSubImage2D(
-                texture.id,
-                0,
-                0,
-                0,
-                width,
-                height,
-                data_format,
-                gl::UNSIGNED_BYTE,
-                data as *const std::ffi::c_void,
+        if changed {
+            assert!(
+                self.mutable_storage,
+                "Texture {} has immutable storage and cannot be resized!",
+                self.id
             );
+            self.width = image.get_width();
+            self.height = image.get_height();
+            self.spec.format = format;
+            self.allocate_storage();
+        }
 
-            stb_image::stb_image::stbi_image_free(data as *mut std::ffi::c_void);
+        self.upload_image(image);
+    }
+
+    fn upload_image(&self, image: &Image) {
+        // Expand grayscale images so they sample as grey instead of red
+        let swizzle = match image.get_channels() {
+            1 => [gl::RED, gl::RED, gl::RED, gl::ONE],
+            2 => [gl::RED, gl::RED, gl::RED, gl::GREEN],
+            _ => [gl::RED, gl::GREEN, gl::BLUE, gl::ALPHA],
+        }
+        .map(|channel| channel as GLint);
+        unsafe {
+            gl::TextureParameteriv(self.id, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
         }
 
-        texture
+        // OpenGL expects the bottom row first
+        let mut flipped = image.clone();
+        flipped.flip_vertical();
+        self.set_data(flipped.get_data());
     }
 
-    pub fn set_data(&self, data: *const std::ffi::c_void, size: usize) {
-        let bpp = if self.data_format == gl::RGBA { 4 } else { 3 };
+    pub fn set_data(&self, data: &[u8]) {
+        let format = self.spec.format;
         assert_eq!(
-            size as u32,
-            self.width * self.height * bpp,
+            data.len() as u32,
+            self.width * self.height * format.bytes_per_pixel(),
             "Data must be entire texture!"
         );
         unsafe {
+            // Rows of 1 and 3 byte formats are not 4 byte aligned
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
             gl::TextureSubImage2D(
                 self.id,
                 0,
@@ -111,11 +361,43 @@ impl Texture2D {
                 0,
                 self.width as i32,
                 self.height as i32,
-                self.data_format,
+                format.data_format(),
+                format.data_type(),
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+
+            if self.spec.generate_mipmaps {
+                gl::GenerateTextureMipmap(self.id);
+            }
+        }
+    }
+
+    /// Reads the base level back into an 8 bit image (first row at the top). Float formats
+    /// are clamped to [0, 1].
+    pub fn read_pixels(&self) -> Image {
+        let (data_format, channels) = match self.spec.format {
+            TextureFormat::R8 => (gl::RED, 1),
+            TextureFormat::RG8 => (gl::RG, 2),
+            TextureFormat::RGB8 | TextureFormat::SRGB8 => (gl::RGB, 3),
+            _ => (gl::RGBA, 4),
+        };
+
+        let mut data = vec![0u8; (self.width * self.height * channels) as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTextureImage(
+                self.id,
+                0,
+                data_format,
                 gl::UNSIGNED_BYTE,
-                data,
+                data.len() as i32,
+                data.as_mut_ptr() as *mut std::ffi::c_void,
             );
         }
+
+        let mut image = Image::from_raw(self.width, self.height, channels, data).unwrap();
+        image.flip_vertical();
+        image
     }
 
     pub fn bind(&self, slot: u32) {
@@ -123,4 +405,29 @@ impl Texture2D {
             gl::BindTextureUnit(slot, self.id);
         }
     }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_spec(&self) -> &TextureSpec {
+        &self.spec
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        track_delete(GpuResourceKind::Texture, self.id);
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
 }
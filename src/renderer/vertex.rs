@@ -0,0 +1,45 @@
+use cgmath::*;
+
+use crate::renderer::{BufferLayout, ShaderDataType};
+
+pub use engine_derive::Vertex;
+
+/// Vertex stored in a `VertexBuffer`, usually implemented with `#[derive(Vertex)]`.
+pub trait Vertex: Copy {
+    fn layout() -> BufferLayout;
+}
+
+/// Field type a `#[derive(Vertex)]` struct can have, with the buffer element it maps to.
+pub trait VertexAttribute: Copy {
+    const DATA_TYPE: ShaderDataType;
+}
+
+macro_rules! vertex_attributes {
+    ($($ty:ty => $data_type:ident),* $(,)?) => {
+        $(
+            impl VertexAttribute for $ty {
+                const DATA_TYPE: ShaderDataType = ShaderDataType::$data_type;
+            }
+        )*
+    };
+}
+
+vertex_attributes! {
+    f32 => Float,
+    [f32; 2] => Float2,
+    [f32; 3] => Float3,
+    [f32; 4] => Float4,
+    Vector2<f32> => Float2,
+    Vector3<f32> => Float3,
+    Vector4<f32> => Float4,
+    Matrix3<f32> => Mat3,
+    Matrix4<f32> => Mat4,
+    i32 => Int,
+    [i32; 2] => Int2,
+    [i32; 3] => Int3,
+    [i32; 4] => Int4,
+    Vector2<i32> => Int2,
+    Vector3<i32> => Int3,
+    Vector4<i32> => Int4,
+    bool => Bool,
+}
@@ -1,7 +1,9 @@
 use gl::types::*;
 use std::sync::Arc;
 
-use crate::renderer::{IndexBuffer, ShaderDataType, VertexBuffer};
+use crate::renderer::{
+    track_create, track_delete, GpuResourceKind, IndexBuffer, ShaderDataType, VertexBuffer,
+};
 
 fn shader_data_type_to_gl_base_type(shader_data_type: &ShaderDataType) -> GLenum {
     match shader_data_type {
@@ -32,6 +34,7 @@ impl VertexArray {
         unsafe {
             gl::GenVertexArrays(1, &mut id);
         }
+        track_create(GpuResourceKind::VertexArray, id);
 
         return VertexArray {
             id,
@@ -80,6 +83,28 @@ impl VertexArray {
                 | ShaderDataType::Int2
                 | ShaderDataType::Int3
                 | ShaderDataType::Int4
+                | ShaderDataType::Bool
+                    if element.integer =>
+                unsafe {
+                    gl::EnableVertexAttribArray(self.vertex_buffer_index);
+                    // Integer attributes cannot be read from GL_BOOL, bools are single bytes
+                    let base_type = match element.data_type {
+                        ShaderDataType::Bool => gl::UNSIGNED_BYTE,
+                        _ => shader_data_type_to_gl_base_type(&element.data_type),
+                    };
+                    gl::VertexAttribIPointer(
+                        self.vertex_buffer_index,
+                        element.get_component_count() as i32,
+                        base_type,
+                        layout.get_stride() as i32,
+                        element.offset as *const _,
+                    );
+                    self.vertex_buffer_index += 1;
+                },
+                ShaderDataType::Int
+                | ShaderDataType::Int2
+                | ShaderDataType::Int3
+                | ShaderDataType::Int4
                 | ShaderDataType::Bool => unsafe {
                     gl::EnableVertexAttribArray(self.vertex_buffer_index);
                     gl::VertexAttribPointer(
@@ -125,3 +150,12 @@ impl VertexArray {
         &self.index_buffer.as_ref().unwrap()
     }
 }
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        track_delete(GpuResourceKind::VertexArray, self.id);
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.id);
+        }
+    }
+}
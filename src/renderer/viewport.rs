@@ -0,0 +1,90 @@
+/// Unit of the values of a `Viewport`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewportUnit {
+    /// Fractions of the window, 0 to 1.
+    Normalized,
+    Pixels,
+}
+
+/// Rectangle of the window a camera draws to, from the top left corner of the window like
+/// cursor positions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub unit: ViewportUnit,
+}
+
+impl Viewport {
+    pub fn normalized(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+            unit: ViewportUnit::Normalized,
+        }
+    }
+
+    pub fn pixels(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+            unit: ViewportUnit::Pixels,
+        }
+    }
+
+    /// The whole window.
+    pub fn full() -> Self {
+        Self::normalized(0.0, 0.0, 1.0, 1.0)
+    }
+
+    /// Area of `player` (from 0) when the window is shared by `players`, 1 to 4. Two players
+    /// are side by side, three and four get a quarter each.
+    pub fn split_screen(players: u32, player: u32) -> Self {
+        match players {
+            0 | 1 => Self::full(),
+            2 => Self::normalized(player as f32 * 0.5, 0.0, 0.5, 1.0),
+            _ => Self::normalized(
+                (player % 2) as f32 * 0.5,
+                (player / 2) as f32 * 0.5,
+                0.5,
+                0.5,
+            ),
+        }
+    }
+
+    /// The rectangle in pixels as x, y, width and height, with y from the bottom of the
+    /// window as OpenGL expects it. The size is at least one pixel.
+    pub fn to_pixels(&self, window_width: u32, window_height: u32) -> (i32, i32, u32, u32) {
+        let (window_width, window_height) = (window_width as f32, window_height as f32);
+        let (x, y, width, height) = match self.unit {
+            ViewportUnit::Normalized => (
+                self.x * window_width,
+                self.y * window_height,
+                self.width * window_width,
+                self.height * window_height,
+            ),
+            ViewportUnit::Pixels => (self.x, self.y, self.width, self.height),
+        };
+
+        let (left, top) = (x.round(), y.round());
+        let (right, bottom) = ((x + width).round(), (y + height).round());
+        (
+            left as i32,
+            (window_height - bottom) as i32,
+            (right - left).max(1.0) as u32,
+            (bottom - top).max(1.0) as u32,
+        )
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::full()
+    }
+}
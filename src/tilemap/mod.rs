@@ -0,0 +1,10 @@
+pub mod object_layer;
+pub mod properties;
+pub mod tiled;
+pub mod tilemap;
+pub mod tileset;
+
+pub use self::object_layer::*;
+pub use self::properties::*;
+pub use self::tilemap::*;
+pub use self::tileset::*;
@@ -0,0 +1,80 @@
+use cgmath::*;
+
+use crate::tilemap::Properties;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Closed outline, points relative to the object position.
+    Polygon(Vec<Vector2<f32>>),
+    /// Open outline, points relative to the object position.
+    Polyline(Vec<Vector2<f32>>),
+    Text(String),
+}
+
+/// Object placed in an object layer, like a spawn point or a trigger area. Positions and
+/// sizes are in map units with y up, relative to the top left corner of the map.
+#[derive(Clone, Debug)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    /// Called "type" before Tiled 1.9.
+    pub class: String,
+    /// The object's origin: its top left corner, or bottom left for tile objects.
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    /// Global tile id, flip flags included, for tile objects.
+    pub tile: Option<u32>,
+    pub properties: Properties,
+}
+
+impl MapObject {
+    pub fn new(id: u32, position: Vector2<f32>, size: Vector2<f32>) -> Self {
+        MapObject {
+            id,
+            name: String::new(),
+            class: String::new(),
+            position,
+            size,
+            rotation: 0.0,
+            visible: true,
+            shape: ObjectShape::Rectangle,
+            tile: None,
+            properties: Properties::new(),
+        }
+    }
+}
+
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub objects: Vec<MapObject>,
+    pub properties: Properties,
+}
+
+impl ObjectLayer {
+    pub fn new(name: &str) -> Self {
+        ObjectLayer {
+            name: name.to_string(),
+            visible: true,
+            objects: Vec::new(),
+            properties: Properties::new(),
+        }
+    }
+
+    pub fn find_object(&self, name: &str) -> Option<&MapObject> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+
+    pub fn objects_of_class<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a MapObject> {
+        self.objects
+            .iter()
+            .filter(move |object| object.class == class)
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use cgmath::*;
+
+/// Custom property of a map, layer, tileset, tile or object, as typed in Tiled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// RGBA in [0, 1].
+    Color(Vector4<f32>),
+    /// Path relative to the file the property was loaded from.
+    File(String),
+    /// Id of another object of the map, 0 for none.
+    Object(u32),
+    /// Custom class, a nested set of properties.
+    Class(Properties),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+impl PropertyValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PropertyValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            PropertyValue::Int(value) => Some(*value),
+            PropertyValue::Object(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    /// Ints are converted, since Tiled users rarely care about the distinction.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Float(value) => Some(*value),
+            PropertyValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::String(value) | PropertyValue::File(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<Vector4<f32>> {
+        match self {
+            PropertyValue::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_class(&self) -> Option<&Properties> {
+        match self {
+            PropertyValue::Class(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Parses Tiled's "#AARRGGBB" or "#RRGGBB" notation.
+pub fn parse_color(text: &str) -> Option<Vector4<f32>> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let channel = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.0;
+
+    match hex.len() {
+        6 => Some(Vector4::new(channel(16), channel(8), channel(0), 1.0)),
+        8 => Some(Vector4::new(
+            channel(16),
+            channel(8),
+            channel(0),
+            channel(24),
+        )),
+        _ => None,
+    }
+}
@@ -0,0 +1,903 @@
+//! Loader for maps made with Tiled (https://www.mapeditor.org), saved as JSON (.tmj, .json)
+//! or XML (.tmx). External tilesets (.tsj, .json, .tsx) are supported, infinite maps,
+//! image collection tilesets and non orthogonal maps are not.
+//!
+//! One tile width is one world unit. Object positions and layer offsets are converted from
+//! pixels accordingly, with y pointing up.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use base64::Engine;
+use cgmath::*;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use roxmltree::Node;
+use serde_json::Value;
+
+use crate::asset::{relative_to, Asset, AssetError, LoadContext};
+use crate::renderer::{Image, Texture2D, TextureSpec, TextureWrap};
+use crate::tilemap::{
+    parse_color, MapObject, ObjectLayer, ObjectShape, Properties, PropertyValue, TileLayer,
+    Tilemap, Tileset,
+};
+
+/// A map read and decoded on a worker thread, before its tileset textures are created.
+pub struct TiledMapData {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    tilesets: Vec<TilesetData>,
+    layers: Vec<TileLayer>,
+    object_layers: Vec<ObjectLayer>,
+    properties: Properties,
+}
+
+struct TilesetData {
+    name: String,
+    first_gid: u32,
+    image: Image,
+    // The image and the tileset file if it is not embedded in the map
+    files: Vec<String>,
+    tile_size: Vector2<u32>,
+    margin: u32,
+    spacing: u32,
+    columns: u32,
+    tile_count: u32,
+    properties: Properties,
+    tile_properties: HashMap<u32, Properties>,
+}
+
+// Visibility, opacity and offset inherited from group layers
+#[derive(Clone, Copy)]
+struct Inherited {
+    visible: bool,
+    opacity: f32,
+    offset: Vector2<f32>,
+}
+
+impl Inherited {
+    fn combine(&self, visible: bool, opacity: f32, offset: Vector2<f32>) -> Self {
+        Inherited {
+            visible: self.visible && visible,
+            opacity: self.opacity * opacity,
+            offset: self.offset + offset,
+        }
+    }
+}
+
+const ROOT: Inherited = Inherited {
+    visible: true,
+    opacity: 1.0,
+    offset: Vector2::new(0.0, 0.0),
+};
+
+struct Loader<'a> {
+    context: &'a LoadContext,
+    scale: f32, // world units per pixel
+}
+
+impl<'a> Loader<'a> {
+    fn error(&self, file: &str, message: impl ToString) -> AssetError {
+        AssetError::Parse {
+            path: self.context.resolve(file),
+            message: message.to_string(),
+        }
+    }
+
+    fn load_image(&self, file: &str) -> Result<Image, AssetError> {
+        let bytes = self.context.read(file)?;
+        Image::from_memory(&bytes).map_err(|source| AssetError::Image {
+            path: self.context.resolve(file),
+            source,
+        })
+    }
+
+    fn point(&self, x: f32, y: f32) -> Vector2<f32> {
+        Vector2::new(x * self.scale, -y * self.scale)
+    }
+
+    fn check_layer_size(
+        &self,
+        file: &str,
+        name: &str,
+        tiles: &[u32],
+        width: u32,
+        height: u32,
+    ) -> Result<(), AssetError> {
+        let size = width
+            .checked_mul(height)
+            .ok_or_else(|| self.error(file, format!("layer {} is too big", name)))?;
+        if tiles.len() != size as usize {
+            return Err(self.error(
+                file,
+                format!(
+                    "layer {} has {} tiles instead of {}",
+                    name,
+                    tiles.len(),
+                    size
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    // The tiles of the grid must be inside the image, whatever the file claims
+    fn check_tileset_grid(&self, file: &str, tileset: &TilesetData) -> Result<(), AssetError> {
+        if tileset.columns == 0 || tileset.tile_count == 0 {
+            return Ok(());
+        }
+
+        let rows = tileset.tile_count.div_ceil(tileset.columns);
+        let extent = |count: u32, tile_length: u32| {
+            tileset.margin as u64
+                + count as u64 * tile_length as u64
+                + (count as u64 - 1) * tileset.spacing as u64
+        };
+        let (width, height) = (tileset.image.get_width(), tileset.image.get_height());
+        let columns = tileset.columns.min(tileset.tile_count);
+        if extent(columns, tileset.tile_size.x) > width as u64
+            || extent(rows, tileset.tile_size.y) > height as u64
+        {
+            return Err(self.error(
+                file,
+                format!(
+                    "the {} tiles of tileset {} do not fit in its {}x{} image",
+                    tileset.tile_count, tileset.name, width, height
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn inflate(mut decoder: impl Read) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    decoder
+        .read_to_end(&mut data)
+        .map_err(|error| error.to_string())?;
+    Ok(data)
+}
+
+fn decode_tile_data(encoding: &str, compression: &str, text: &str) -> Result<Vec<u32>, String> {
+    match encoding {
+        "csv" => text
+            .split(',')
+            .map(|tile| tile.trim())
+            .filter(|tile| !tile.is_empty())
+            .map(|tile| tile.parse::<u32>().map_err(|error| error.to_string()))
+            .collect(),
+        "base64" => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(text.trim())
+                .map_err(|error| error.to_string())?;
+
+            let data = match compression {
+                "" => bytes,
+                "zlib" => inflate(ZlibDecoder::new(bytes.as_slice()))?,
+                "gzip" => inflate(GzDecoder::new(bytes.as_slice()))?,
+                _ => return Err(format!("{} compression is not supported", compression)),
+            };
+
+            Ok(data
+                .chunks_exact(4)
+                .map(|tile| u32::from_le_bytes(tile.try_into().unwrap()))
+                .collect())
+        }
+        _ => Err(format!("{} encoding is not supported", encoding)),
+    }
+}
+
+fn property_from_text(property_type: &str, value: &str) -> PropertyValue {
+    match property_type {
+        "bool" => PropertyValue::Bool(value == "true"),
+        "int" => PropertyValue::Int(value.parse().unwrap_or(0)),
+        "float" => PropertyValue::Float(value.parse().unwrap_or(0.0)),
+        "color" => PropertyValue::Color(parse_color(value).unwrap_or(Vector4::zero())),
+        "file" => PropertyValue::File(value.to_string()),
+        "object" => PropertyValue::Object(value.parse().unwrap_or(0)),
+        _ => PropertyValue::String(value.to_string()),
+    }
+}
+
+//
+// JSON
+//
+
+fn json_u32(value: &Value, key: &str) -> u32 {
+    value[key].as_u64().unwrap_or(0) as u32
+}
+
+fn json_f32(value: &Value, key: &str, default: f32) -> f32 {
+    value[key].as_f64().map_or(default, |number| number as f32)
+}
+
+fn json_str<'v>(value: &'v Value, key: &str) -> &'v str {
+    value[key].as_str().unwrap_or("")
+}
+
+fn json_bool(value: &Value, key: &str, default: bool) -> bool {
+    value[key].as_bool().unwrap_or(default)
+}
+
+// Members of a class property carry no type, so it is guessed from the JSON value
+fn json_class(value: &Value) -> Properties {
+    let Some(members) = value.as_object() else {
+        return Properties::new();
+    };
+
+    members
+        .iter()
+        .map(|(name, member)| {
+            let value = match member {
+                Value::Bool(value) => PropertyValue::Bool(*value),
+                Value::Number(number) => match number.as_i64() {
+                    Some(value) => PropertyValue::Int(value),
+                    None => PropertyValue::Float(number.as_f64().unwrap_or(0.0)),
+                },
+                Value::Object(_) => PropertyValue::Class(json_class(member)),
+                Value::String(value) => PropertyValue::String(value.clone()),
+                _ => PropertyValue::String(member.to_string()),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+fn json_properties(value: &Value) -> Properties {
+    let Some(properties) = value["properties"].as_array() else {
+        return Properties::new();
+    };
+
+    properties
+        .iter()
+        .map(|property| {
+            let name = json_str(property, "name").to_string();
+            let value = &property["value"];
+            let value = match json_str(property, "type") {
+                "bool" => PropertyValue::Bool(value.as_bool().unwrap_or(false)),
+                "int" => PropertyValue::Int(value.as_i64().unwrap_or(0)),
+                "float" => PropertyValue::Float(value.as_f64().unwrap_or(0.0)),
+                "object" => PropertyValue::Object(value.as_u64().unwrap_or(0) as u32),
+                "class" => PropertyValue::Class(json_class(value)),
+                property_type => property_from_text(property_type, value.as_str().unwrap_or("")),
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+impl<'a> Loader<'a> {
+    fn parse_json_map(&mut self, file: &str) -> Result<TiledMapData, AssetError> {
+        let text = self.context.read_to_string(file)?;
+        let map: Value = serde_json::from_str(&text).map_err(|error| self.error(file, error))?;
+
+        let orientation = map["orientation"].as_str().unwrap_or("orthogonal");
+        if orientation != "orthogonal" {
+            return Err(self.error(file, format!("{} maps are not supported", orientation)));
+        }
+        if json_bool(&map, "infinite", false) {
+            return Err(self.error(file, "infinite maps are not supported"));
+        }
+
+        let tile_width = json_u32(&map, "tilewidth").max(1);
+        self.scale = 1.0 / tile_width as f32;
+
+        let mut data = TiledMapData {
+            width: json_u32(&map, "width"),
+            height: json_u32(&map, "height"),
+            tile_width,
+            tile_height: json_u32(&map, "tileheight").max(1),
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            object_layers: Vec::new(),
+            properties: json_properties(&map),
+        };
+
+        for tileset in map["tilesets"].as_array().into_iter().flatten() {
+            let first_gid = json_u32(tileset, "firstgid");
+            let tileset = match tileset["source"].as_str() {
+                Some(source) => {
+                    let source = relative_to(file, source);
+                    let mut tileset = if source.ends_with(".tsx") {
+                        let text = self.context.read_to_string(&source)?;
+                        let document = roxmltree::Document::parse(&text)
+                            .map_err(|error| self.error(&source, error))?;
+                        self.parse_xml_tileset(&source, document.root_element(), first_gid)?
+                    } else {
+                        let text = self.context.read_to_string(&source)?;
+                        let tileset: Value = serde_json::from_str(&text)
+                            .map_err(|error| self.error(&source, error))?;
+                        self.parse_json_tileset(&source, &tileset, first_gid)?
+                    };
+                    tileset.files.push(source);
+                    tileset
+                }
+                None => self.parse_json_tileset(file, tileset, first_gid)?,
+            };
+            data.tilesets.push(tileset);
+        }
+
+        let layers = map["layers"].as_array().map_or(&[][..], Vec::as_slice);
+        self.parse_json_layers(file, layers, ROOT, &mut data)?;
+        Ok(data)
+    }
+
+    fn parse_json_tileset(
+        &self,
+        file: &str,
+        tileset: &Value,
+        first_gid: u32,
+    ) -> Result<TilesetData, AssetError> {
+        let Some(image) = tileset["image"].as_str() else {
+            return Err(self.error(file, "image collection tilesets are not supported"));
+        };
+
+        let tile_properties = tileset["tiles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|tile| (json_u32(tile, "id"), json_properties(tile)))
+            .filter(|(_, properties)| !properties.is_empty())
+            .collect();
+
+        let image = relative_to(file, image);
+        let data = TilesetData {
+            name: json_str(tileset, "name").to_string(),
+            first_gid,
+            image: self.load_image(&image)?,
+            files: vec![image],
+            tile_size: Vector2::new(
+                json_u32(tileset, "tilewidth").max(1),
+                json_u32(tileset, "tileheight").max(1),
+            ),
+            margin: json_u32(tileset, "margin"),
+            spacing: json_u32(tileset, "spacing"),
+            columns: json_u32(tileset, "columns"),
+            tile_count: json_u32(tileset, "tilecount"),
+            properties: json_properties(tileset),
+            tile_properties,
+        };
+        self.check_tileset_grid(file, &data)?;
+        Ok(data)
+    }
+
+    fn parse_json_layers(
+        &self,
+        file: &str,
+        layers: &[Value],
+        inherited: Inherited,
+        data: &mut TiledMapData,
+    ) -> Result<(), AssetError> {
+        for layer in layers {
+            let name = json_str(layer, "name");
+            let inherited = inherited.combine(
+                json_bool(layer, "visible", true),
+                json_f32(layer, "opacity", 1.0),
+                self.point(
+                    json_f32(layer, "offsetx", 0.0),
+                    json_f32(layer, "offsety", 0.0),
+                ),
+            );
+
+            match json_str(layer, "type") {
+                "tilelayer" => {
+                    let tiles = match &layer["data"] {
+                        Value::Array(tiles) => tiles
+                            .iter()
+                            .map(|tile| tile.as_u64().unwrap_or(0) as u32)
+                            .collect(),
+                        Value::String(text) => decode_tile_data(
+                            json_str(layer, "encoding"),
+                            json_str(layer, "compression"),
+                            text,
+                        )
+                        .map_err(|error| self.error(file, error))?,
+                        _ => return Err(self.error(file, format!("layer {} has no data", name))),
+                    };
+                    self.check_layer_size(file, name, &tiles, data.width, data.height)?;
+
+                    let mut tile_layer =
+                        TileLayer::from_tiles(name, data.width, data.height, tiles);
+                    tile_layer.visible = inherited.visible;
+                    tile_layer.set_opacity(inherited.opacity);
+                    tile_layer.set_offset(inherited.offset);
+                    tile_layer.properties = json_properties(layer);
+                    data.layers.push(tile_layer);
+                }
+                "objectgroup" => {
+                    let mut object_layer = ObjectLayer::new(name);
+                    object_layer.visible = inherited.visible;
+                    object_layer.properties = json_properties(layer);
+                    object_layer.objects = layer["objects"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|object| self.parse_json_object(object, inherited.offset))
+                        .collect();
+                    data.object_layers.push(object_layer);
+                }
+                "group" => {
+                    let layers = layer["layers"].as_array().map_or(&[][..], Vec::as_slice);
+                    self.parse_json_layers(file, layers, inherited, data)?;
+                }
+                layer_type => log::warn!("Skipping {} layer {} of {}", layer_type, name, file),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_json_object(&self, object: &Value, offset: Vector2<f32>) -> MapObject {
+        let points = |key: &str| {
+            object[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|point| self.point(json_f32(point, "x", 0.0), json_f32(point, "y", 0.0)))
+                .collect::<Vec<_>>()
+        };
+
+        let shape = if json_bool(object, "point", false) {
+            ObjectShape::Point
+        } else if json_bool(object, "ellipse", false) {
+            ObjectShape::Ellipse
+        } else if object["polygon"].is_array() {
+            ObjectShape::Polygon(points("polygon"))
+        } else if object["polyline"].is_array() {
+            ObjectShape::Polyline(points("polyline"))
+        } else if object["text"].is_object() {
+            ObjectShape::Text(json_str(&object["text"], "text").to_string())
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        let class = match json_str(object, "class") {
+            "" => json_str(object, "type"),
+            class => class,
+        };
+
+        MapObject {
+            id: json_u32(object, "id"),
+            name: json_str(object, "name").to_string(),
+            class: class.to_string(),
+            position: offset + self.point(json_f32(object, "x", 0.0), json_f32(object, "y", 0.0)),
+            size: Vector2::new(
+                json_f32(object, "width", 0.0),
+                json_f32(object, "height", 0.0),
+            ) * self.scale,
+            rotation: -json_f32(object, "rotation", 0.0).to_radians(),
+            visible: json_bool(object, "visible", true),
+            shape,
+            tile: object["gid"].as_u64().map(|gid| gid as u32),
+            properties: json_properties(object),
+        }
+    }
+}
+
+//
+// XML
+//
+
+fn xml_u32(node: Node, name: &str) -> u32 {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+fn xml_f32(node: Node, name: &str, default: f32) -> f32 {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn xml_bool(node: Node, name: &str, default: bool) -> bool {
+    node.attribute(name).map_or(default, |value| value != "0")
+}
+
+fn xml_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn xml_properties(node: Node) -> Properties {
+    let Some(properties) = xml_child(node, "properties") else {
+        return Properties::new();
+    };
+
+    properties
+        .children()
+        .filter(|child| child.has_tag_name("property"))
+        .map(|property| {
+            let name = property.attribute("name").unwrap_or("").to_string();
+            let property_type = property.attribute("type").unwrap_or("string");
+            let value = if property_type == "class" {
+                PropertyValue::Class(xml_properties(property))
+            } else {
+                // Multiline strings are stored as the element's text
+                let text = property
+                    .attribute("value")
+                    .or_else(|| property.text())
+                    .unwrap_or("");
+                property_from_text(property_type, text)
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+impl<'a> Loader<'a> {
+    fn parse_xml_map(&mut self, file: &str) -> Result<TiledMapData, AssetError> {
+        let text = self.context.read_to_string(file)?;
+        let document =
+            roxmltree::Document::parse(&text).map_err(|error| self.error(file, error))?;
+        let map = document.root_element();
+
+        let orientation = map.attribute("orientation").unwrap_or("orthogonal");
+        if orientation != "orthogonal" {
+            return Err(self.error(file, format!("{} maps are not supported", orientation)));
+        }
+        if xml_bool(map, "infinite", false) {
+            return Err(self.error(file, "infinite maps are not supported"));
+        }
+
+        let tile_width = xml_u32(map, "tilewidth").max(1);
+        self.scale = 1.0 / tile_width as f32;
+
+        let mut data = TiledMapData {
+            width: xml_u32(map, "width"),
+            height: xml_u32(map, "height"),
+            tile_width,
+            tile_height: xml_u32(map, "tileheight").max(1),
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            object_layers: Vec::new(),
+            properties: xml_properties(map),
+        };
+
+        for tileset in map.children().filter(|child| child.has_tag_name("tileset")) {
+            let first_gid = xml_u32(tileset, "firstgid");
+            let tileset = match tileset.attribute("source") {
+                Some(source) => {
+                    let source = relative_to(file, source);
+                    let text = self.context.read_to_string(&source)?;
+                    let mut tileset = if source.ends_with(".tsx") {
+                        let document = roxmltree::Document::parse(&text)
+                            .map_err(|error| self.error(&source, error))?;
+                        self.parse_xml_tileset(&source, document.root_element(), first_gid)?
+                    } else {
+                        let tileset: Value = serde_json::from_str(&text)
+                            .map_err(|error| self.error(&source, error))?;
+                        self.parse_json_tileset(&source, &tileset, first_gid)?
+                    };
+                    tileset.files.push(source);
+                    tileset
+                }
+                None => self.parse_xml_tileset(file, tileset, first_gid)?,
+            };
+            data.tilesets.push(tileset);
+        }
+
+        self.parse_xml_layers(file, map, ROOT, &mut data)?;
+        Ok(data)
+    }
+
+    fn parse_xml_tileset(
+        &self,
+        file: &str,
+        tileset: Node,
+        first_gid: u32,
+    ) -> Result<TilesetData, AssetError> {
+        let Some(image) = xml_child(tileset, "image").and_then(|image| image.attribute("source"))
+        else {
+            return Err(self.error(file, "image collection tilesets are not supported"));
+        };
+
+        let tile_properties = tileset
+            .children()
+            .filter(|child| child.has_tag_name("tile"))
+            .map(|tile| (xml_u32(tile, "id"), xml_properties(tile)))
+            .filter(|(_, properties)| !properties.is_empty())
+            .collect();
+
+        let image = relative_to(file, image);
+        let data = TilesetData {
+            name: tileset.attribute("name").unwrap_or("").to_string(),
+            first_gid,
+            image: self.load_image(&image)?,
+            files: vec![image],
+            tile_size: Vector2::new(
+                xml_u32(tileset, "tilewidth").max(1),
+                xml_u32(tileset, "tileheight").max(1),
+            ),
+            margin: xml_u32(tileset, "margin"),
+            spacing: xml_u32(tileset, "spacing"),
+            columns: xml_u32(tileset, "columns"),
+            tile_count: xml_u32(tileset, "tilecount"),
+            properties: xml_properties(tileset),
+            tile_properties,
+        };
+        self.check_tileset_grid(file, &data)?;
+        Ok(data)
+    }
+
+    // Layers are the children of the map or of a group, in draw order
+    fn parse_xml_layers(
+        &self,
+        file: &str,
+        parent: Node,
+        inherited: Inherited,
+        data: &mut TiledMapData,
+    ) -> Result<(), AssetError> {
+        for layer in parent.children().filter(Node::is_element) {
+            let name = layer.attribute("name").unwrap_or("");
+            let inherited = inherited.combine(
+                xml_bool(layer, "visible", true),
+                xml_f32(layer, "opacity", 1.0),
+                self.point(
+                    xml_f32(layer, "offsetx", 0.0),
+                    xml_f32(layer, "offsety", 0.0),
+                ),
+            );
+
+            match layer.tag_name().name() {
+                "layer" => {
+                    let Some(tile_data) = xml_child(layer, "data") else {
+                        return Err(self.error(file, format!("layer {} has no data", name)));
+                    };
+
+                    let tiles = match tile_data.attribute("encoding") {
+                        Some(encoding) => decode_tile_data(
+                            encoding,
+                            tile_data.attribute("compression").unwrap_or(""),
+                            tile_data.text().unwrap_or(""),
+                        )
+                        .map_err(|error| self.error(file, error))?,
+                        // Oldest format, one element per tile
+                        None => tile_data
+                            .children()
+                            .filter(|child| child.has_tag_name("tile"))
+                            .map(|tile| xml_u32(tile, "gid"))
+                            .collect(),
+                    };
+                    self.check_layer_size(file, name, &tiles, data.width, data.height)?;
+
+                    let mut tile_layer =
+                        TileLayer::from_tiles(name, data.width, data.height, tiles);
+                    tile_layer.visible = inherited.visible;
+                    tile_layer.set_opacity(inherited.opacity);
+                    tile_layer.set_offset(inherited.offset);
+                    tile_layer.properties = xml_properties(layer);
+                    data.layers.push(tile_layer);
+                }
+                "objectgroup" => {
+                    let mut object_layer = ObjectLayer::new(name);
+                    object_layer.visible = inherited.visible;
+                    object_layer.properties = xml_properties(layer);
+                    object_layer.objects = layer
+                        .children()
+                        .filter(|child| child.has_tag_name("object"))
+                        .map(|object| self.parse_xml_object(object, inherited.offset))
+                        .collect();
+                    data.object_layers.push(object_layer);
+                }
+                "group" => self.parse_xml_layers(file, layer, inherited, data)?,
+                "imagelayer" => log::warn!("Skipping image layer {} of {}", name, file),
+                _ => {} // tilesets, properties, editor settings
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_xml_object(&self, object: Node, offset: Vector2<f32>) -> MapObject {
+        let points = |node: Node| {
+            node.attribute("points")
+                .unwrap_or("")
+                .split_whitespace()
+                .filter_map(|point| {
+                    let (x, y) = point.split_once(',')?;
+                    Some(self.point(x.parse().ok()?, y.parse().ok()?))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut shape = ObjectShape::Rectangle;
+        for child in object.children().filter(Node::is_element) {
+            shape = match child.tag_name().name() {
+                "point" => ObjectShape::Point,
+                "ellipse" => ObjectShape::Ellipse,
+                "polygon" => ObjectShape::Polygon(points(child)),
+                "polyline" => ObjectShape::Polyline(points(child)),
+                "text" => ObjectShape::Text(child.text().unwrap_or("").to_string()),
+                _ => continue,
+            };
+        }
+
+        let class = object
+            .attribute("class")
+            .or_else(|| object.attribute("type"))
+            .unwrap_or("");
+
+        MapObject {
+            id: xml_u32(object, "id"),
+            name: object.attribute("name").unwrap_or("").to_string(),
+            class: class.to_string(),
+            position: offset + self.point(xml_f32(object, "x", 0.0), xml_f32(object, "y", 0.0)),
+            size: Vector2::new(
+                xml_f32(object, "width", 0.0),
+                xml_f32(object, "height", 0.0),
+            ) * self.scale,
+            rotation: -xml_f32(object, "rotation", 0.0).to_radians(),
+            visible: xml_bool(object, "visible", true),
+            shape,
+            tile: object.attribute("gid").and_then(|gid| gid.parse().ok()),
+            properties: xml_properties(object),
+        }
+    }
+}
+
+/// Tiled maps load by file extension: ".tmx" is read as XML, anything else as JSON.
+impl Asset for Tilemap {
+    type Data = TiledMapData;
+
+    fn decode(path: &str, context: &LoadContext) -> Result<TiledMapData, AssetError> {
+        let mut loader = Loader {
+            context,
+            scale: 1.0,
+        };
+
+        let data = if path.ends_with(".tmx") {
+            loader.parse_xml_map(path)?
+        } else {
+            loader.parse_json_map(path)?
+        };
+
+        if data.tilesets.len() > 32 {
+            return Err(loader.error(path, "maps can use at most 32 tilesets"));
+        }
+        Ok(data)
+    }
+
+    fn create(_path: &str, data: TiledMapData, _context: &LoadContext) -> Result<Self, AssetError> {
+        let tile_size = Vector2::new(1.0, data.tile_height as f32 / data.tile_width as f32);
+        let mut tilemap = Tilemap::new(data.width, data.height, tile_size);
+        tilemap.properties = data.properties;
+
+        // Tiles sampled across their edges would bleed into the neighbouring tiles
+        let spec = TextureSpec {
+            wrap_s: TextureWrap::ClampToEdge,
+            wrap_t: TextureWrap::ClampToEdge,
+            ..TextureSpec::default()
+        };
+
+        for tileset_data in data.tilesets {
+            tilemap.source_files.extend(tileset_data.files);
+            let texture = Texture2D::from_image(&tileset_data.image, spec);
+            // Files written by old Tiled versions lack the column and tile counts
+            let mut tileset = if tileset_data.columns == 0 {
+                Tileset::new(
+                    &tileset_data.name,
+                    texture,
+                    tileset_data.tile_size,
+                    tileset_data.margin,
+                    tileset_data.spacing,
+                )
+            } else {
+                Tileset::with_grid(
+                    &tileset_data.name,
+                    texture,
+                    tileset_data.tile_size,
+                    tileset_data.margin,
+                    tileset_data.spacing,
+                    tileset_data.columns,
+                    tileset_data.tile_count,
+                )
+            };
+            tileset.first_gid = tileset_data.first_gid;
+            tileset.properties = tileset_data.properties;
+            tileset.tile_properties = tileset_data.tile_properties;
+            tilemap.insert_tileset(tileset);
+        }
+
+        for layer in data.layers {
+            tilemap.add_tile_layer(layer);
+        }
+        for layer in data.object_layers {
+            tilemap.add_object_layer(layer);
+        }
+
+        Ok(tilemap)
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.source_files.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOURCES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/engine_tester/resources");
+
+    fn level() -> Value {
+        let text = std::fs::read_to_string(format!("{}/level.tmj", RESOURCES)).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    // Decodes a map saved next to a copy of the tileset image
+    fn decode_json(name: &str, map: &Value) -> Result<TiledMapData, AssetError> {
+        let root = std::env::temp_dir().join("engine_tiled_tests");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::copy(format!("{}/tiles.png", RESOURCES), root.join("tiles.png")).unwrap();
+        let file = format!("{}.tmj", name);
+        std::fs::write(root.join(&file), map.to_string()).unwrap();
+        Tilemap::decode(&file, &LoadContext::new(root))
+    }
+
+    fn assert_parse_error(result: Result<TiledMapData, AssetError>) {
+        match result {
+            Err(AssetError::Parse { .. }) => {}
+            Err(error) => panic!("expected a parse error, got {}", error),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn decodes_level() {
+        let data = Tilemap::decode("level.tmj", &LoadContext::new(RESOURCES)).unwrap();
+        assert_eq!((data.width, data.height), (24, 16));
+        assert_eq!(data.tilesets.len(), 1);
+        assert_eq!(data.tilesets[0].tile_count, 4);
+        assert_eq!(data.layers.len(), 2);
+        assert_eq!(data.object_layers.len(), 1);
+    }
+
+    #[test]
+    fn lists_tileset_files() {
+        let data = decode_json("embedded", &level()).unwrap();
+        assert_eq!(data.tilesets[0].files, ["tiles.png"]);
+
+        let mut map = level();
+        let mut tileset = map["tilesets"][0].take();
+        tileset.as_object_mut().unwrap().remove("firstgid");
+        let root = std::env::temp_dir().join("engine_tiled_tests");
+        std::fs::write(root.join("tiles.tsj"), tileset.to_string()).unwrap();
+        map["tilesets"][0] = serde_json::json!({ "firstgid": 1, "source": "tiles.tsj" });
+
+        let data = decode_json("external", &map).unwrap();
+        assert_eq!(data.tilesets[0].files, ["tiles.png", "tiles.tsj"]);
+    }
+
+    #[test]
+    fn rejects_tiles_outside_the_image() {
+        let mut map = level();
+        map["tilesets"][0]["tilecount"] = Value::from(6);
+        assert_parse_error(decode_json("too_many_tiles", &map));
+
+        let mut map = level();
+        map["tilesets"][0]["columns"] = Value::from(3);
+        assert_parse_error(decode_json("too_many_columns", &map));
+
+        let mut map = level();
+        map["tilesets"][0]["margin"] = Value::from(1);
+        assert_parse_error(decode_json("margin", &map));
+
+        let mut map = level();
+        map["tilesets"][0]["spacing"] = Value::from(u32::MAX);
+        assert_parse_error(decode_json("spacing", &map));
+
+        let mut map = level();
+        map["tilesets"][0]["tilecount"] = Value::from(u32::MAX);
+        map["tilesets"][0]["columns"] = Value::from(1);
+        assert_parse_error(decode_json("huge_tile_count", &map));
+    }
+
+    #[test]
+    fn rejects_layers_too_big_to_count() {
+        let mut map = level();
+        map["width"] = Value::from(u32::MAX);
+        map["height"] = Value::from(u32::MAX);
+        assert_parse_error(decode_json("huge_layers", &map));
+    }
+}
@@ -0,0 +1,496 @@
+use std::sync::Arc;
+
+use cgmath::*;
+
+use crate::math::AABB;
+use crate::renderer::{
+    quad_indices, IndexBuffer, QuadVertex, RenderCommand, RenderStats, SubTexture, Vertex,
+    VertexArray, VertexBuffer,
+};
+use crate::tilemap::{ObjectLayer, Properties, Tileset};
+
+/// Width and height of a chunk in tiles. Every chunk has its own vertex buffer and is drawn
+/// with one draw call.
+pub const CHUNK_SIZE: u32 = 16;
+
+// Tiled stores how a tile is flipped in the top bits of its global id
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const FLAGS: u32 =
+    FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120;
+
+/// Strips the flip flags from a tile, leaving its global id. 0 is an empty tile.
+pub fn tile_gid(tile: u32) -> u32 {
+    tile & !FLAGS
+}
+
+struct Chunk {
+    vertex_array: Option<VertexArray>,
+    vertex_buffer: Option<Arc<VertexBuffer>>,
+    capacity: usize, // vertices the buffer can hold
+    quad_count: u32,
+    dirty: bool,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            vertex_array: None,
+            vertex_buffer: None,
+            capacity: 0,
+            quad_count: 0,
+            dirty: true,
+        }
+    }
+}
+
+/// Grid of tiles, stored as global tile ids with Tiled's flip flags.
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub properties: Properties,
+
+    opacity: f32,
+    offset: Vector2<f32>,
+    width: u32,
+    height: u32,
+    tiles: Vec<u32>,
+    chunks: Vec<Chunk>,
+}
+
+impl TileLayer {
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        Self::from_tiles(name, width, height, vec![0; (width * height) as usize])
+    }
+
+    /// `tiles` is row by row from the top left.
+    pub fn from_tiles(name: &str, width: u32, height: u32, tiles: Vec<u32>) -> Self {
+        assert_eq!(
+            tiles.len(),
+            (width * height) as usize,
+            "Tile data must cover the entire layer!"
+        );
+
+        let chunk_count = width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE);
+        TileLayer {
+            name: name.to_string(),
+            visible: true,
+            properties: Properties::new(),
+
+            opacity: 1.0,
+            offset: Vector2::zero(),
+            width,
+            height,
+            tiles,
+            chunks: (0..chunk_count).map(|_| Chunk::new()).collect(),
+        }
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// The tile at (`x`, `y`) counted from the top left, 0 if empty or out of bounds.
+    pub fn get_tile(&self, x: u32, y: u32) -> u32 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: u32) {
+        assert!(
+            x < self.width && y < self.height,
+            "Tile ({}, {}) is outside of layer {}!",
+            x,
+            y,
+            self.name
+        );
+
+        let index = (y * self.width + x) as usize;
+        if self.tiles[index] != tile {
+            self.tiles[index] = tile;
+            let chunk = self.chunk_index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+            self.chunks[chunk].dirty = true;
+        }
+    }
+
+    pub fn get_tiles(&self) -> &[u32] {
+        &self.tiles
+    }
+
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+        self.mark_dirty();
+    }
+
+    pub fn get_offset(&self) -> Vector2<f32> {
+        self.offset
+    }
+
+    /// Shifts the layer, in map units.
+    pub fn set_offset(&mut self, offset: Vector2<f32>) {
+        self.offset = offset;
+        self.mark_dirty();
+    }
+
+    fn mark_dirty(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.dirty = true;
+        }
+    }
+
+    fn chunks_x(&self) -> u32 {
+        self.width.div_ceil(CHUNK_SIZE)
+    }
+
+    fn chunk_index(&self, chunk_x: u32, chunk_y: u32) -> usize {
+        (chunk_y * self.chunks_x() + chunk_x) as usize
+    }
+
+    fn chunk_bounds(&self, index: usize, tile_size: Vector2<f32>) -> AABB {
+        let chunk_x = index as u32 % self.chunks_x();
+        let chunk_y = index as u32 / self.chunks_x();
+        let min_tile = Vector2::new(chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE);
+        let max_tile = Vector2::new(
+            (min_tile.x + CHUNK_SIZE).min(self.width),
+            (min_tile.y + CHUNK_SIZE).min(self.height),
+        );
+
+        AABB::from_min_max(
+            min_tile.x as f32 * tile_size.x + self.offset.x,
+            -(max_tile.y as f32) * tile_size.y + self.offset.y,
+            max_tile.x as f32 * tile_size.x + self.offset.x,
+            -(min_tile.y as f32) * tile_size.y + self.offset.y,
+        )
+    }
+
+    fn build_chunk(
+        &mut self,
+        index: usize,
+        tilesets: &[Tileset],
+        tile_size: Vector2<f32>,
+        index_buffer: &Arc<IndexBuffer>,
+    ) {
+        let chunk_x = index as u32 % self.chunks_x();
+        let chunk_y = index as u32 / self.chunks_x();
+        let color = Vector4::new(1.0, 1.0, 1.0, self.opacity);
+
+        let mut vertices = Vec::new();
+        for y in chunk_y * CHUNK_SIZE..((chunk_y + 1) * CHUNK_SIZE).min(self.height) {
+            for x in chunk_x * CHUNK_SIZE..((chunk_x + 1) * CHUNK_SIZE).min(self.width) {
+                let tile = self.get_tile(x, y);
+                let gid = tile_gid(tile);
+                if gid == 0 {
+                    continue;
+                }
+
+                let Some((texture_index, tileset)) = tilesets
+                    .iter()
+                    .enumerate()
+                    .find(|(_, tileset)| tileset.contains(gid))
+                else {
+                    continue;
+                };
+                let sub_texture = tileset.get_tile(gid - tileset.first_gid).unwrap();
+                let texture_coords = flipped_texture_coords(sub_texture, tile);
+
+                let min = Vector2::new(x as f32 * tile_size.x, -((y + 1) as f32) * tile_size.y)
+                    + self.offset;
+                let corners = [
+                    min,
+                    min + Vector2::new(tile_size.x, 0.0),
+                    min + tile_size,
+                    min + Vector2::new(0.0, tile_size.y),
+                ];
+
+                for (corner, texture_coord) in corners.into_iter().zip(texture_coords) {
+                    vertices.push(QuadVertex {
+                        position: corner.extend(0.0),
+                        color,
+                        texture_coord,
+                        texture_index: texture_index as f32,
+                        normal_index: 0.0,
+                    });
+                }
+            }
+        }
+
+        let chunk = &mut self.chunks[index];
+        chunk.dirty = false;
+        chunk.quad_count = (vertices.len() / 4) as u32;
+        if vertices.is_empty() {
+            return;
+        }
+
+        if chunk.capacity < vertices.len() {
+            let mut vertex_buffer = VertexBuffer::new_static(&vertices);
+            vertex_buffer.set_layout(QuadVertex::layout());
+            let vertex_buffer = Arc::new(vertex_buffer);
+
+            let mut vertex_array = VertexArray::new();
+            vertex_array.add_vertex_buffer(vertex_buffer.clone());
+            vertex_array.set_index_buffer(index_buffer.clone());
+
+            chunk.vertex_array = Some(vertex_array);
+            chunk.vertex_buffer = Some(vertex_buffer);
+            chunk.capacity = vertices.len();
+        } else if let Some(vertex_buffer) = &chunk.vertex_buffer {
+            vertex_buffer.set_data(&vertices, 0);
+        }
+    }
+}
+
+// Applies Tiled's flip flags: diagonal first, then horizontal, then vertical
+fn flipped_texture_coords(sub_texture: &SubTexture, tile: u32) -> [Vector2<f32>; 4] {
+    let size = sub_texture.max - sub_texture.min;
+    let corners = [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(1.0, 1.0),
+        Vector2::new(0.0, 1.0),
+    ];
+
+    corners.map(|corner: Vector2<f32>| {
+        // Work in image space, with y pointing down like in Tiled
+        let mut image = Vector2::new(corner.x, 1.0 - corner.y);
+        if tile & FLIPPED_VERTICALLY != 0 {
+            image.y = 1.0 - image.y;
+        }
+        if tile & FLIPPED_HORIZONTALLY != 0 {
+            image.x = 1.0 - image.x;
+        }
+        if tile & FLIPPED_DIAGONALLY != 0 {
+            image = Vector2::new(image.y, image.x);
+        }
+
+        Vector2::new(
+            sub_texture.min.x + image.x * size.x,
+            sub_texture.max.y - image.y * size.y,
+        )
+    })
+}
+
+/// Layered grid of tiles drawn with `Renderer::draw_tilemap`. Tile (0, 0) is the top left
+/// one, its top left corner is at `position` and y grows downwards in tile coordinates.
+pub struct Tilemap {
+    pub position: Vector2<f32>,
+    pub properties: Properties,
+
+    width: u32,
+    height: u32,
+    tile_size: Vector2<f32>,
+    tilesets: Vec<Tileset>,
+    layers: Vec<TileLayer>,
+    object_layers: Vec<ObjectLayer>,
+    // Files a loaded map read besides its own, e.g. tileset images
+    pub(crate) source_files: Vec<String>,
+
+    index_buffer: Option<Arc<IndexBuffer>>, // shared by every chunk
+}
+
+impl Tilemap {
+    /// `tile_size` is the size of one tile in world units.
+    pub fn new(width: u32, height: u32, tile_size: Vector2<f32>) -> Self {
+        Tilemap {
+            position: Vector2::zero(),
+            properties: Properties::new(),
+
+            width,
+            height,
+            tile_size,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            object_layers: Vec::new(),
+            source_files: Vec::new(),
+
+            index_buffer: None,
+        }
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_tile_size(&self) -> Vector2<f32> {
+        self.tile_size
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: Vector2<f32>) {
+        self.tile_size = tile_size;
+        for layer in &mut self.layers {
+            layer.mark_dirty();
+        }
+    }
+
+    /// Adds a tileset after the previous ones and returns the global id of its first tile.
+    /// A map can use at most 32 tilesets, one per texture slot.
+    pub fn add_tileset(&mut self, mut tileset: Tileset) -> u32 {
+        tileset.first_gid = self
+            .tilesets
+            .last()
+            .map_or(1, |last| last.first_gid + last.get_tile_count());
+        self.insert_tileset(tileset)
+    }
+
+    // Keeps the first gid the tileset already has, used by the Tiled loader
+    pub(crate) fn insert_tileset(&mut self, tileset: Tileset) -> u32 {
+        assert!(
+            self.tilesets.len() < 32,
+            "A tilemap can use at most 32 tilesets!"
+        );
+
+        let first_gid = tileset.first_gid;
+        self.tilesets.push(tileset);
+        first_gid
+    }
+
+    pub fn get_tilesets(&self) -> &[Tileset] {
+        &self.tilesets
+    }
+
+    /// The tileset `tile` belongs to.
+    pub fn get_tileset_of(&self, tile: u32) -> Option<&Tileset> {
+        let gid = tile_gid(tile);
+        self.tilesets.iter().find(|tileset| tileset.contains(gid))
+    }
+
+    /// Adds an empty layer on top of the others and returns its index.
+    pub fn add_layer(&mut self, name: &str) -> usize {
+        self.add_tile_layer(TileLayer::new(name, self.width, self.height))
+    }
+
+    pub fn add_tile_layer(&mut self, layer: TileLayer) -> usize {
+        assert!(
+            layer.width == self.width && layer.height == self.height,
+            "Layer {} is {}x{} but the map is {}x{}!",
+            layer.name,
+            layer.width,
+            layer.height,
+            self.width,
+            self.height
+        );
+
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
+
+    /// Tile layers, bottom to top.
+    pub fn get_layers(&self) -> &[TileLayer] {
+        &self.layers
+    }
+
+    pub fn get_layer_mut(&mut self, index: usize) -> Option<&mut TileLayer> {
+        self.layers.get_mut(index)
+    }
+
+    pub fn find_layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn find_layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: u32) {
+        self.layers[layer].set_tile(x, y, tile);
+    }
+
+    pub fn add_object_layer(&mut self, layer: ObjectLayer) -> usize {
+        self.object_layers.push(layer);
+        self.object_layers.len() - 1
+    }
+
+    pub fn get_object_layers(&self) -> &[ObjectLayer] {
+        &self.object_layers
+    }
+
+    pub fn find_object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        self.object_layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// World position of the center of tile (`x`, `y`).
+    pub fn tile_to_world(&self, x: u32, y: u32) -> Vector2<f32> {
+        self.position
+            + Vector2::new(
+                (x as f32 + 0.5) * self.tile_size.x,
+                -(y as f32 + 0.5) * self.tile_size.y,
+            )
+    }
+
+    /// The tile covering `position`, if it is inside the map.
+    pub fn world_to_tile(&self, position: Vector2<f32>) -> Option<(u32, u32)> {
+        let local = position - self.position;
+        let x = (local.x / self.tile_size.x).floor();
+        let y = (-local.y / self.tile_size.y).floor();
+
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+
+    /// Area the map covers in world units, ignoring layer offsets.
+    pub fn get_bounds(&self) -> AABB {
+        let size = Vector2::new(
+            self.width as f32 * self.tile_size.x,
+            self.height as f32 * self.tile_size.y,
+        );
+        AABB::from_min_max(
+            self.position.x,
+            self.position.y - size.y,
+            self.position.x + size.x,
+            self.position.y,
+        )
+    }
+
+    /// Draws the chunks overlapping `view` (in map space), or all of them without a view,
+    /// rebuilding the ones whose tiles changed. Expects the quad shader to be bound and tileset textures in slots 0 to 31.
+    pub(crate) fn draw(&mut self, view: Option<&AABB>, stats: &mut RenderStats) {
+        let index_buffer = self
+            .index_buffer
+            .get_or_insert_with(|| {
+                Arc::new(IndexBuffer::new(quad_indices(CHUNK_SIZE * CHUNK_SIZE)))
+            })
+            .clone();
+
+        for layer in self.layers.iter_mut().filter(|layer| layer.visible) {
+            for index in 0..layer.chunks.len() {
+                if let Some(view) = view {
+                    if !layer.chunk_bounds(index, self.tile_size).intersects(view) {
+                        stats.culled_chunks += 1;
+                        continue;
+                    }
+                }
+
+                if layer.chunks[index].dirty {
+                    layer.build_chunk(index, &self.tilesets, self.tile_size, &index_buffer);
+                }
+
+                let chunk = &layer.chunks[index];
+                if let Some(vertex_array) = &chunk.vertex_array {
+                    if chunk.quad_count > 0 {
+                        RenderCommand::draw_indexed(vertex_array, Some(chunk.quad_count * 6));
+                        stats.draw_calls += 1;
+                        stats.quads += chunk.quad_count;
+                        stats.vertices += chunk.quad_count * 4;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use cgmath::*;
+
+use crate::renderer::{SubTexture, Texture2D};
+use crate::tilemap::Properties;
+
+/// Image cut into equally sized tiles. Tiles are numbered row by row from the top left, the
+/// way Tiled numbers them.
+pub struct Tileset {
+    pub name: String,
+    pub properties: Properties,
+    /// Properties of individual tiles, by local tile id.
+    pub tile_properties: HashMap<u32, Properties>,
+
+    texture: Texture2D,
+    pub(crate) first_gid: u32,
+    tile_size: Vector2<u32>,
+    columns: u32,
+    tiles: Vec<SubTexture>,
+}
+
+impl Tileset {
+    /// `margin` is the border around the whole image and `spacing` the gap between tiles,
+    /// both in pixels.
+    pub fn new(
+        name: &str,
+        texture: Texture2D,
+        tile_size: Vector2<u32>,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let columns = Self::fit(texture.get_width(), tile_size.x, margin, spacing);
+        let rows = Self::fit(texture.get_height(), tile_size.y, margin, spacing);
+        Self::with_grid(
+            name,
+            texture,
+            tile_size,
+            margin,
+            spacing,
+            columns,
+            columns * rows,
+        )
+    }
+
+    fn fit(length: u32, tile_length: u32, margin: u32, spacing: u32) -> u32 {
+        let usable = length.saturating_sub(margin.saturating_mul(2)) as u64 + spacing as u64;
+        (usable / (tile_length as u64 + spacing as u64).max(1)) as u32
+    }
+
+    /// Like `new`, with the column and tile count given instead of derived from the image
+    /// size, for tilesets whose last row is not full. Every tile must be inside the texture.
+    pub fn with_grid(
+        name: &str,
+        texture: Texture2D,
+        tile_size: Vector2<u32>,
+        margin: u32,
+        spacing: u32,
+        columns: u32,
+        tile_count: u32,
+    ) -> Self {
+        let columns = columns.max(1);
+        let tiles = (0..tile_count)
+            .map(|id| {
+                let x = margin + (id % columns) * (tile_size.x + spacing);
+                let y = margin + (id / columns) * (tile_size.y + spacing);
+                SubTexture::from_pixels(
+                    texture.get_width(),
+                    texture.get_height(),
+                    x,
+                    y,
+                    tile_size.x,
+                    tile_size.y,
+                )
+            })
+            .collect();
+
+        Tileset {
+            name: name.to_string(),
+            properties: Properties::new(),
+            tile_properties: HashMap::new(),
+
+            texture,
+            first_gid: 1,
+            tile_size,
+            columns,
+            tiles,
+        }
+    }
+
+    pub fn get_texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    /// Global id of the first tile, assigned when the tileset is added to a `Tilemap`.
+    pub fn get_first_gid(&self) -> u32 {
+        self.first_gid
+    }
+
+    pub fn get_tile_count(&self) -> u32 {
+        self.tiles.len() as u32
+    }
+
+    /// Tile size in pixels.
+    pub fn get_tile_size(&self) -> Vector2<u32> {
+        self.tile_size
+    }
+
+    pub fn get_columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.get_tile_count()
+    }
+
+    pub fn get_tile(&self, local_id: u32) -> Option<&SubTexture> {
+        self.tiles.get(local_id as usize)
+    }
+
+    pub fn get_tile_properties(&self, local_id: u32) -> Option<&Properties> {
+        self.tile_properties.get(&local_id)
+    }
+}
@@ -3,7 +3,10 @@ use std::{cell::RefCell, rc::Rc};
 use crate::math::*;
 
 use crate::core::{GameEngine, MouseCode};
-use crate::ui::UiRenderer;
+use crate::renderer::{
+    BlendMode, FillRule, Gradient, NineSlice, Path, StrokeStyle, SubTexture, Texture2D,
+};
+use crate::ui::{RectStyle, UiRenderer};
 
 pub enum CordinateType {
     Relative, // the element position is relative to its parent's size and the range of values inside the parent is [0, 1]
@@ -106,6 +109,8 @@ pub struct UiElementProps {
     pub height: Dimension,
 
     pub active: bool,
+    /// How the element's own drawing combines with what is behind it.
+    pub blend_mode: BlendMode,
 
     render_size: Vector2<f32>,
     render_position: Vector2<f32>,
@@ -178,6 +183,7 @@ impl UiBase {
                 height,
 
                 active: true,
+                blend_mode: BlendMode::Alpha,
 
                 render_size: Vector2::new(0.0, 0.0),
                 render_position: Vector2::new(0.0, 0.0),
@@ -229,9 +235,14 @@ impl UiElement for UiBase {
 pub struct UiBox {
     pub props: UiElementProps,
     pub color: Vector4<f32>,
+    /// Background drawn instead of `color` if set.
+    pub gradient: Option<Gradient>,
     pub corner_radius: f32,
     pub outline_thickness: f32,
     pub outline_color: Vector4<f32>,
+    /// Hides the parts of the children outside of the box and its rounded corners, e.g. for
+    /// scroll areas.
+    pub clip_children: bool,
 }
 
 impl UiBox {
@@ -254,16 +265,29 @@ impl UiBox {
                 height,
 
                 active: true,
+                blend_mode: BlendMode::Alpha,
 
                 render_size: Vector2::new(0.0, 0.0),
                 render_position: Vector2::new(0.0, 0.0),
             },
             color,
+            gradient: None,
             corner_radius,
             outline_thickness,
             outline_color,
+            clip_children: false,
         }
     }
+
+    pub fn with_gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
+    pub fn with_clip_children(mut self) -> Self {
+        self.clip_children = true;
+        self
+    }
 }
 
 impl UiElement for UiBox {
@@ -285,19 +309,42 @@ impl UiElement for UiBox {
     }
 
     fn render(&self, renderer: &mut UiRenderer) {
-        renderer.draw_rect(
+        renderer.set_blend_mode(self.props.blend_mode);
+        renderer.draw_gradient_rect(
             self.props.render_position,
             self.props.render_size,
-            self.color,
-            self.corner_radius,
-            self.outline_thickness,
-            self.outline_color,
-            None,
+            &self.gradient.unwrap_or(Gradient::from(self.color)),
+            &RectStyle {
+                corner_radius: self.corner_radius,
+                outline_thickness: self.outline_thickness,
+                outline_color: self.outline_color,
+                texture: None,
+            },
         );
 
+        if self.clip_children {
+            if self.corner_radius > 0.0 {
+                renderer.push_clip_rounded_rect(
+                    self.props.render_position,
+                    self.props.render_size,
+                    self.corner_radius,
+                );
+            } else {
+                renderer.push_clip_rect(self.props.render_position, self.props.render_size);
+            }
+        }
+
         for child in self.props.children.iter() {
             child.borrow_mut().render(renderer);
         }
+
+        if self.clip_children {
+            if self.corner_radius > 0.0 {
+                renderer.pop_mask();
+            } else {
+                renderer.pop_clip_rect();
+            }
+        }
     }
 
     fn push_child(&mut self, child: Box<dyn UiElement>) -> Rc<RefCell<Box<dyn UiElement>>> {
@@ -307,6 +354,248 @@ impl UiElement for UiBox {
     }
 }
 
+/// Texture, or part of one, stretched over the element.
+pub struct UiImage {
+    pub props: UiElementProps,
+    pub texture: Rc<Texture2D>,
+    pub sub_texture: SubTexture,
+    pub color: Vector4<f32>,
+}
+
+impl UiImage {
+    pub fn new(
+        x: Cordinate,
+        y: Cordinate,
+        width: Dimension,
+        height: Dimension,
+        texture: Rc<Texture2D>,
+    ) -> UiImage {
+        UiImage {
+            props: UiElementProps {
+                children: Vec::new(),
+                x,
+                y,
+                width,
+                height,
+
+                active: true,
+                blend_mode: BlendMode::Alpha,
+
+                render_size: Vector2::new(0.0, 0.0),
+                render_position: Vector2::new(0.0, 0.0),
+            },
+            texture,
+            sub_texture: SubTexture::full(),
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl UiElement for UiImage {
+    fn update(
+        &mut self,
+        parent_position: Vector2<f32>,
+        parent_size: Vector2<f32>,
+        ui_state: &UiState,
+    ) {
+        self.props.render_size = self.calculate_render_size(&self.props, parent_size);
+        self.props.render_position =
+            self.calculate_render_position(&self.props, parent_position, parent_size);
+
+        for child in self.props.children.iter_mut() {
+            child
+                .borrow_mut()
+                .update(self.props.render_position, self.props.render_size, ui_state);
+        }
+    }
+
+    fn render(&self, renderer: &mut UiRenderer) {
+        renderer.set_blend_mode(self.props.blend_mode);
+        renderer.draw_image(
+            self.props.render_position,
+            self.props.render_size,
+            self.color,
+            &self.texture,
+            &self.sub_texture,
+        );
+
+        for child in self.props.children.iter() {
+            child.borrow_mut().render(renderer);
+        }
+    }
+
+    fn push_child(&mut self, child: Box<dyn UiElement>) -> Rc<RefCell<Box<dyn UiElement>>> {
+        let child_reference = Rc::new(RefCell::new(child));
+        self.props.children.push(child_reference.clone());
+        child_reference
+    }
+}
+
+/// Panel drawn from a skinned texture sliced so its frame keeps its size at any panel size.
+pub struct UiPanel {
+    pub props: UiElementProps,
+    pub texture: Rc<Texture2D>,
+    pub slice: NineSlice,
+    pub color: Vector4<f32>,
+}
+
+impl UiPanel {
+    pub fn new(
+        x: Cordinate,
+        y: Cordinate,
+        width: Dimension,
+        height: Dimension,
+        texture: Rc<Texture2D>,
+        slice: NineSlice,
+    ) -> UiPanel {
+        UiPanel {
+            props: UiElementProps {
+                children: Vec::new(),
+                x,
+                y,
+                width,
+                height,
+
+                active: true,
+                blend_mode: BlendMode::Alpha,
+
+                render_size: Vector2::new(0.0, 0.0),
+                render_position: Vector2::new(0.0, 0.0),
+            },
+            texture,
+            slice,
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl UiElement for UiPanel {
+    fn update(
+        &mut self,
+        parent_position: Vector2<f32>,
+        parent_size: Vector2<f32>,
+        ui_state: &UiState,
+    ) {
+        self.props.render_size = self.calculate_render_size(&self.props, parent_size);
+        self.props.render_position =
+            self.calculate_render_position(&self.props, parent_position, parent_size);
+
+        for child in self.props.children.iter_mut() {
+            child
+                .borrow_mut()
+                .update(self.props.render_position, self.props.render_size, ui_state);
+        }
+    }
+
+    fn render(&self, renderer: &mut UiRenderer) {
+        renderer.set_blend_mode(self.props.blend_mode);
+        renderer.draw_nine_slice(
+            self.props.render_position,
+            self.props.render_size,
+            self.color,
+            &self.texture,
+            &self.slice,
+        );
+
+        for child in self.props.children.iter() {
+            child.borrow_mut().render(renderer);
+        }
+    }
+
+    fn push_child(&mut self, child: Box<dyn UiElement>) -> Rc<RefCell<Box<dyn UiElement>>> {
+        let child_reference = Rc::new(RefCell::new(child));
+        self.props.children.push(child_reference.clone());
+        child_reference
+    }
+}
+
+/// Vector icon scaled to the element, drawn from a path in [0, 1] coordinates with (0, 0) at
+/// the top-left.
+pub struct UiIcon {
+    pub props: UiElementProps,
+    pub path: Path,
+    pub fill: Option<(FillRule, Vector4<f32>)>,
+    /// Stroke along the path, its width in pixels.
+    pub stroke: Option<(StrokeStyle, Vector4<f32>)>,
+}
+
+impl UiIcon {
+    /// Icon filled in white, without a stroke.
+    pub fn new(
+        x: Cordinate,
+        y: Cordinate,
+        width: Dimension,
+        height: Dimension,
+        path: Path,
+    ) -> UiIcon {
+        UiIcon {
+            props: UiElementProps {
+                children: Vec::new(),
+                x,
+                y,
+                width,
+                height,
+
+                active: true,
+                blend_mode: BlendMode::Alpha,
+
+                render_size: Vector2::new(0.0, 0.0),
+                render_position: Vector2::new(0.0, 0.0),
+            },
+            path,
+            fill: Some((FillRule::NonZero, Vector4::new(1.0, 1.0, 1.0, 1.0))),
+            stroke: None,
+        }
+    }
+}
+
+impl UiElement for UiIcon {
+    fn update(
+        &mut self,
+        parent_position: Vector2<f32>,
+        parent_size: Vector2<f32>,
+        ui_state: &UiState,
+    ) {
+        self.props.render_size = self.calculate_render_size(&self.props, parent_size);
+        self.props.render_position =
+            self.calculate_render_position(&self.props, parent_position, parent_size);
+
+        for child in self.props.children.iter_mut() {
+            child
+                .borrow_mut()
+                .update(self.props.render_position, self.props.render_size, ui_state);
+        }
+    }
+
+    fn render(&self, renderer: &mut UiRenderer) {
+        renderer.set_blend_mode(self.props.blend_mode);
+
+        // The render position is the center of the element
+        let size = self.props.render_size;
+        let top_left = self.props.render_position - size / 2.0;
+        let path = self
+            .path
+            .map_points(|point| top_left + point.mul_element_wise(size));
+
+        if let Some((rule, color)) = self.fill {
+            renderer.fill_path(&path, rule, color);
+        }
+        if let Some((style, color)) = &self.stroke {
+            renderer.stroke_path(&path, style, *color);
+        }
+
+        for child in self.props.children.iter() {
+            child.borrow_mut().render(renderer);
+        }
+    }
+
+    fn push_child(&mut self, child: Box<dyn UiElement>) -> Rc<RefCell<Box<dyn UiElement>>> {
+        let child_reference = Rc::new(RefCell::new(child));
+        self.props.children.push(child_reference.clone());
+        child_reference
+    }
+}
+
 pub struct UiState {
     pub(crate) cursor_position: Vector2<f32>,
     pub(crate) right_click: bool,
@@ -331,7 +620,7 @@ impl Ui {
         let cursor_position = Vector2::new(cursor_position.0, cursor_position.1);
 
         Ui {
-            renderer: UiRenderer::new(),
+            renderer: UiRenderer::new(&engine.assets),
             base: UiBase::default(),
             state: UiState {
                 cursor_position,
@@ -1,14 +1,18 @@
+use crate::asset::AssetServer;
+use crate::math::AABB;
 use crate::renderer::{
-    BufferElement, BufferLayout, IndexBuffer, RenderCommand, ShaderDataType, ShaderProgram,
-    Texture2D, VertexArray, VertexBuffer,
+    fill_polylines, gradient_cells, quad_indices, reserve_quad_indices, stroke_polylines,
+    BlendMode, ClipStack, FillRule, Gradient, GradientCell, IndexBuffer, NineSlice, Path,
+    RenderCommand, ScissorRect, ShaderProgram, StrokeStyle, SubTexture, Texture2D, VectorMesh,
+    Vertex, VertexArray, VertexBuffer,
 };
 
 use cgmath::*;
-use std::ffi::c_void;
 use std::mem;
 use std::sync::Arc;
 
 #[repr(C)]
+#[derive(Clone, Copy, Vertex)]
 struct RectVertex {
     position: Vector2<f32>,
     color: Vector4<f32>,
@@ -20,9 +24,31 @@ struct RectVertex {
     outline_color: Vector4<f32>,
 }
 
-const MAX_RECTS: u32 = 10000;
-const MAX_VERTICES: u32 = MAX_RECTS * 4;
-const MAX_INDICES: u32 = MAX_RECTS * 6;
+/// Shape of a rectangle drawn by `UiRenderer::draw_gradient_rect`.
+#[derive(Clone, Copy)]
+pub struct RectStyle<'a> {
+    pub corner_radius: f32,
+    pub outline_thickness: f32,
+    pub outline_color: Vector4<f32>,
+    /// Tinted by the rectangle's colors.
+    pub texture: Option<&'a Texture2D>,
+}
+
+impl Default for RectStyle<'_> {
+    fn default() -> Self {
+        RectStyle {
+            corner_radius: 0.0,
+            outline_thickness: 0.0,
+            outline_color: Vector4::zero(),
+            texture: None,
+        }
+    }
+}
+
+// Room for this many rects at first, the buffers grow when a batch needs more
+const INITIAL_RECTS: u32 = 1024;
+// Paths stray at most this many pixels from their curves
+const PATH_TOLERANCE: f32 = 0.25;
 
 const RECT_VERTEX_POSITIONS: [Vector4<f32>; 4] = [
     Vector4::new(-0.5, -0.5, 0.0, 1.0),
@@ -33,7 +59,7 @@ const RECT_VERTEX_POSITIONS: [Vector4<f32>; 4] = [
 
 pub struct UiRenderer {
     rect_vertex_array: VertexArray,
-    rect_vertex_buffer: VertexBuffer,
+    rect_vertex_buffer: Arc<VertexBuffer>,
     rect_shader: ShaderProgram,
 
     white_texture: Texture2D,
@@ -45,86 +71,53 @@ pub struct UiRenderer {
     texture_slot_index: u32,
 
     screen_matrix: Matrix4<f32>,
+    window_size: Vector2<f32>,
+    blend_mode: BlendMode,
+    clip: ClipStack,
 }
 
 impl UiRenderer {
-    pub fn new() -> Self {
+    pub fn new(assets: &AssetServer) -> Self {
+        let mut rect_vertex_buffer =
+            VertexBuffer::new(INITIAL_RECTS as usize * 4 * mem::size_of::<RectVertex>());
+        rect_vertex_buffer.set_layout(RectVertex::layout());
+        let rect_vertex_buffer = Arc::new(rect_vertex_buffer);
+
         let mut renderer = UiRenderer {
             rect_vertex_array: VertexArray::new(),
-            rect_vertex_buffer: VertexBuffer::new(
-                MAX_VERTICES as usize * mem::size_of::<RectVertex>(),
-            ),
-            rect_shader: ShaderProgram::new("resources/rect.vert", "resources/rect.frag"),
+            rect_vertex_buffer: rect_vertex_buffer.clone(),
+            rect_shader: assets
+                .load_owned::<ShaderProgram>("rect")
+                .unwrap_or_else(|error| panic!("{}", error)),
 
             white_texture: Texture2D::new(1, 1),
 
             rect_index_count: 0,
-            rect_vertices: Vec::with_capacity(MAX_VERTICES.try_into().unwrap()),
+            rect_vertices: Vec::with_capacity(INITIAL_RECTS as usize * 4),
 
             texture_slots: [0; 32],
             texture_slot_index: 1, // 0 is for the white texture
 
             screen_matrix: Matrix4::identity(),
+            window_size: Vector2::zero(),
+            blend_mode: BlendMode::Alpha,
+            clip: ClipStack::default(),
         };
 
         renderer.rect_vertex_array.bind();
-        renderer
-            .rect_vertex_buffer
-            .set_layout(BufferLayout::new(vec![
-                BufferElement::new(String::from("a_position"), ShaderDataType::Float2, None),
-                BufferElement::new(String::from("a_color"), ShaderDataType::Float4, None),
-                BufferElement::new(
-                    String::from("a_texture_coord"),
-                    ShaderDataType::Float2,
-                    None,
-                ),
-                BufferElement::new(String::from("a_texture_index"), ShaderDataType::Float, None),
-                BufferElement::new(String::from("a_size"), ShaderDataType::Float2, None),
-                BufferElement::new(String::from("a_corner_radius"), ShaderDataType::Float, None),
-                BufferElement::new(
-                    String::from("a_outline_thickness"),
-                    ShaderDataType::Float,
-                    None,
-                ),
-                BufferElement::new(
-                    String::from("a_outline_color"),
-                    ShaderDataType::Float4,
-                    None,
-                ),
-            ]));
-        let rect_vertex_buffer_arc = Arc::new(renderer.rect_vertex_buffer.clone());
-
         renderer
             .rect_vertex_array
-            .add_vertex_buffer(rect_vertex_buffer_arc);
-
-        let mut rect_indices: Vec<u32> = Vec::with_capacity(MAX_INDICES as usize);
-
-        let mut offset = 0;
-        for _ in (0..MAX_INDICES).step_by(6) {
-            rect_indices.push(offset + 0);
-            rect_indices.push(offset + 1);
-            rect_indices.push(offset + 2);
-
-            rect_indices.push(offset + 2);
-            rect_indices.push(offset + 3);
-            rect_indices.push(offset + 0);
-
-            offset += 4;
-        }
+            .add_vertex_buffer(rect_vertex_buffer);
 
-        let rect_index_buffer = IndexBuffer::new(rect_indices);
+        let rect_index_buffer = IndexBuffer::new(quad_indices(INITIAL_RECTS));
         renderer
             .rect_vertex_array
-            .set_index_buffer(Arc::new(rect_index_buffer.clone()));
+            .set_index_buffer(Arc::new(rect_index_buffer));
 
         renderer.rect_shader.create_uniform("u_screen_matrix");
 
-        let white_texture_data: u32 = 0xffffffff;
-        renderer.white_texture.set_data(
-            &white_texture_data as *const u32 as *const c_void,
-            mem::size_of::<u32>(),
-        );
+        let white_texture_data: [u8; 4] = [0xff; 4];
+        renderer.white_texture.set_data(&white_texture_data);
 
         renderer.texture_slots[0] = renderer.white_texture.id;
 
@@ -132,6 +125,7 @@ impl UiRenderer {
     }
 
     pub fn begin_frame(&mut self, window_size: Vector2<f32>) {
+        self.window_size = window_size;
         self.screen_matrix =
             Matrix4::from_nonuniform_scale(2.0 / window_size.x, 2.0 / -window_size.y, 1.0)
                 * Matrix4::from_translation(Vector3::new(
@@ -140,10 +134,17 @@ impl UiRenderer {
                     0.0,
                 ));
         self.start_batch();
+        self.blend_mode = BlendMode::Alpha;
     }
 
-    pub fn end_frame(&self) {
+    pub fn end_frame(&mut self) {
         self.flush();
+
+        // Rects and masks left pushed would clip whatever is drawn next
+        if !self.clip.is_empty() {
+            log::warn!("UI frame ended with clip rects or masks still pushed");
+            self.clip.clear();
+        }
     }
 
     fn start_batch(&mut self) {
@@ -157,9 +158,11 @@ impl UiRenderer {
             return;
         }
 
-        let data_size = self.rect_vertices.len() * mem::size_of::<RectVertex>();
-        self.rect_vertex_buffer
-            .set_data(self.rect_vertices.as_ptr() as *const c_void, data_size);
+        let base_vertex = self.rect_vertex_buffer.stream(&self.rect_vertices);
+        reserve_quad_indices(
+            self.rect_vertex_array.get_index_buffer(),
+            self.rect_index_count / 6,
+        );
 
         for i in 0..self.texture_slot_index {
             unsafe {
@@ -170,7 +173,13 @@ impl UiRenderer {
         self.rect_shader.bind();
         self.rect_shader
             .set_matrix4fv_uniform("u_screen_matrix", &self.screen_matrix);
-        RenderCommand::draw_indexed(&self.rect_vertex_array, Some(self.rect_index_count));
+        RenderCommand::set_blend_mode(self.blend_mode);
+        RenderCommand::draw_indexed_base_vertex(
+            &self.rect_vertex_array,
+            self.rect_index_count,
+            base_vertex,
+        );
+        RenderCommand::set_blend_mode(BlendMode::Alpha);
     }
 
     pub fn next_batch(&mut self) {
@@ -178,6 +187,98 @@ impl UiRenderer {
         self.start_batch();
     }
 
+    /// How the next rects combine with what is behind them, `BlendMode::Alpha` at the start
+    /// of every frame. Changing it starts a new batch.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        if mode != self.blend_mode {
+            self.next_batch();
+            self.blend_mode = mode;
+        }
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Limits the next draws to a rectangle of the screen, inside the clip rects pushed
+    /// before, e.g. for scroll areas. Starts a new batch, like `pop_clip_rect`.
+    pub fn push_clip_rect(&mut self, position: Vector2<f32>, size: Vector2<f32>) {
+        self.next_batch();
+        let bounds = AABB::new(position - size / 2.0, position + size / 2.0);
+        let rect =
+            ScissorRect::from_bounds(&bounds, &self.screen_matrix, RenderCommand::get_viewport());
+        self.clip.push_rect(rect);
+    }
+
+    pub fn pop_clip_rect(&mut self) {
+        self.next_batch();
+        if !self.clip.pop_rect() {
+            log::warn!("pop_clip_rect called without a clip rect");
+        }
+    }
+
+    /// Draws a mask instead of showing the next draws, until `end_mask`. Only the shapes of
+    /// what is drawn count, not their colors or rounded corners. Masks nest, each one inside
+    /// those pushed before.
+    pub fn begin_mask(&mut self) {
+        self.next_batch();
+        self.clip.begin_mask();
+    }
+
+    /// Limits the next draws to the mask drawn since `begin_mask`, until `pop_mask`.
+    pub fn end_mask(&mut self) {
+        self.next_batch();
+        self.clip.end_mask();
+    }
+
+    /// Limits the next draws to the inside of a path, in pixels. The edges of the mask are
+    /// not anti-aliased.
+    pub fn push_clip_path(&mut self, path: &Path, rule: FillRule) {
+        let mesh = fill_polylines(&path.flatten(PATH_TOLERANCE), rule, 0.0);
+
+        self.begin_mask();
+        self.draw_vector_mesh(&mesh, Vector4::new(1.0, 1.0, 1.0, 1.0));
+        self.end_mask();
+    }
+
+    /// Limits the next draws to a rectangle with the corners `draw_rect` gives it.
+    pub fn push_clip_rounded_rect(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        corner_radius: f32,
+    ) {
+        let mut path = Path::new();
+        path.rounded_rect(position - size / 2.0, position + size / 2.0, corner_radius);
+        self.push_clip_path(&path, FillRule::NonZero);
+    }
+
+    pub fn pop_mask(&mut self) {
+        self.next_batch();
+        if !self.clip.begin_pop_mask() {
+            log::warn!("pop_mask called without a finished mask");
+            return;
+        }
+
+        // Anything covering the screen resets the stencil of the mask
+        self.draw_rect(
+            self.window_size / 2.0,
+            self.window_size,
+            Vector4::new(1.0, 1.0, 1.0, 1.0),
+            -1.0,
+            0.0,
+            Vector4::zero(),
+            None,
+        );
+        self.next_batch();
+        self.clip.end_pop_mask();
+    }
+
+    /// Number of masks the next draws are limited to.
+    pub fn get_mask_depth(&self) -> u32 {
+        self.clip.get_mask_depth()
+    }
+
     pub fn draw_rect(
         &mut self,
         position: Vector2<f32>,
@@ -188,58 +289,192 @@ impl UiRenderer {
         outline_color: Vector4<f32>,
         texture: Option<&Texture2D>,
     ) {
-        const RECT_VERTEX_COUNT: u32 = 4;
-        const TEXTURE_COORDS: [Vector2<f32>; 4] = [
-            Vector2::new(0.0, 0.0),
-            Vector2::new(1.0, 0.0),
-            Vector2::new(1.0, 1.0),
-            Vector2::new(0.0, 1.0),
-        ];
+        self.draw_gradient_rect(
+            position,
+            size,
+            &Gradient::from(color),
+            &RectStyle {
+                corner_radius,
+                outline_thickness,
+                outline_color,
+                texture,
+            },
+        );
+    }
 
-        if self.rect_index_count >= MAX_INDICES as u32 {
-            self.next_batch();
+    /// Like `draw_rect` with colors varying over the rectangle, bottom to top being up on
+    /// the screen.
+    pub fn draw_gradient_rect(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        gradient: &Gradient,
+        style: &RectStyle,
+    ) {
+        let RectStyle {
+            corner_radius,
+            outline_thickness,
+            outline_color,
+            texture,
+        } = *style;
+        let texture_index = self.get_texture_index(texture);
+
+        for GradientCell { min, max, colors } in gradient_cells(gradient) {
+            // Gradients have y up, the corners of the rectangle start at the top left. Every
+            // cell keeps the texture coordinates and size of the whole rectangle so the
+            // rounded corners are computed for the rectangle and not for the cell.
+            let points = [
+                Vector2::new(min.x, max.y),
+                max,
+                Vector2::new(max.x, min.y),
+                min,
+            ];
+            let colors = [colors[3], colors[2], colors[1], colors[0]];
+
+            for (point, color) in points.into_iter().zip(colors) {
+                let texture_coord = Vector2::new(point.x, 1.0 - point.y);
+                self.rect_vertices.push(RectVertex {
+                    position: (texture_coord - Vector2::new(0.5, 0.5)).mul_element_wise(size)
+                        + position,
+                    color,
+                    texture_coord,
+                    texture_index,
+                    size,
+                    corner_radius,
+                    outline_thickness,
+                    outline_color,
+                });
+            }
+
+            self.rect_index_count += 6;
         }
+    }
 
-        let mut texture_index = 0.0;
-        if texture.is_some() {
-            let texture = texture.unwrap_or(&self.white_texture).id;
+    /// Fills the inside of `path`, given in pixels, anti-aliased over a pixel.
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule, color: Vector4<f32>) {
+        let mesh = fill_polylines(&path.flatten(PATH_TOLERANCE), rule, 1.0);
+        self.draw_vector_mesh(&mesh, color);
+    }
+
+    /// Draws a line along `path` with `style`, both in pixels.
+    pub fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, color: Vector4<f32>) {
+        let polylines = path.flatten(PATH_TOLERANCE);
+        let mesh = stroke_polylines(&polylines, style, PATH_TOLERANCE, 1.0);
+        self.draw_vector_mesh(&mesh, color);
+    }
 
-            for i in 0..self.texture_slot_index {
-                if self.texture_slots[i as usize] == texture {
-                    texture_index = i as f32;
-                }
+    /// Draws triangles made by `fill_polylines` or `stroke_polylines`, their coverage
+    /// multiplied into the alpha of `color`.
+    pub fn draw_vector_mesh(&mut self, mesh: &VectorMesh, color: Vector4<f32>) {
+        for triangle in mesh.indices.chunks_exact(3) {
+            // A rectangle whose last two corners are the same
+            let [a, b, c] = [0, 1, 2].map(|corner| mesh.vertices[triangle[corner] as usize]);
+            for vertex in [a, b, c, c] {
+                self.rect_vertices.push(RectVertex {
+                    position: vertex.position,
+                    color: Vector4::new(color.x, color.y, color.z, color.w * vertex.coverage),
+                    texture_coord: Vector2::zero(),
+                    texture_index: 0.0,
+                    size: Vector2::zero(),
+                    corner_radius: -1.0,
+                    outline_thickness: 0.0,
+                    outline_color: Vector4::zero(),
+                });
             }
 
-            if texture_index == 0.0 {
-                if self.texture_slot_index >= 32 as u32 {
-                    self.next_batch();
-                }
+            self.rect_index_count += 6;
+        }
+    }
+
+    /// Draws a texture, or part of it, stretched over the rectangle.
+    pub fn draw_image(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        texture: &Texture2D,
+        sub_texture: &SubTexture,
+    ) {
+        let texture_index = self.get_texture_index(Some(texture));
+        self.push_sprite(position, size, color, texture_index, sub_texture);
+    }
+
+    /// Draws a sliced texture whose borders keep their size, `slice.scale` being the size of
+    /// a texture pixel on screen.
+    pub fn draw_nine_slice(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        texture: &Texture2D,
+        slice: &NineSlice,
+    ) {
+        let texture_size = Vector2::new(texture.get_width(), texture.get_height());
+        let texture_index = self.get_texture_index(Some(texture));
+
+        // Patches are laid out with y up, the UI has y down
+        for patch in slice.patches(Vector2::zero(), size, texture_size) {
+            let center = (patch.min + patch.max) / 2.0;
+            self.push_sprite(
+                position + Vector2::new(center.x, -center.y),
+                patch.max - patch.min,
+                color,
+                texture_index,
+                &patch.sub_texture,
+            );
+        }
+    }
 
-                texture_index = self.texture_slot_index as f32;
-                self.texture_slots[self.texture_slot_index as usize] = texture;
-                self.texture_slot_index += 1;
+    fn get_texture_index(&mut self, texture: Option<&Texture2D>) -> f32 {
+        let texture = match texture {
+            Some(texture) => texture.id,
+            None => return 0.0,
+        };
+
+        for i in 1..self.texture_slot_index {
+            if self.texture_slots[i as usize] == texture {
+                return i as f32;
             }
         }
 
-        for i in 0..RECT_VERTEX_COUNT {
-            let vertex_position = RECT_VERTEX_POSITIONS[i as usize]
-                .truncate()
-                .truncate()
-                .mul_element_wise(size)
-                + position;
+        if self.texture_slot_index >= 32 {
+            self.next_batch();
+        }
+
+        let texture_index = self.texture_slot_index;
+        self.texture_slots[texture_index as usize] = texture;
+        self.texture_slot_index += 1;
+        texture_index as f32
+    }
+
+    // A plain textured rectangle, without the rounded corners and outline of `draw_rect`
+    fn push_sprite(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        texture_index: f32,
+        sub_texture: &SubTexture,
+    ) {
+        // Screen corners run from the top left, textures have v growing upwards
+        let texture_coords = [
+            Vector2::new(sub_texture.min.x, sub_texture.max.y),
+            Vector2::new(sub_texture.max.x, sub_texture.max.y),
+            Vector2::new(sub_texture.max.x, sub_texture.min.y),
+            Vector2::new(sub_texture.min.x, sub_texture.min.y),
+        ];
 
-            let vertex = RectVertex {
-                position: Vector2::new(vertex_position.x, vertex_position.y),
+        for (corner, texture_coord) in RECT_VERTEX_POSITIONS.iter().zip(texture_coords) {
+            self.rect_vertices.push(RectVertex {
+                position: corner.truncate().truncate().mul_element_wise(size) + position,
                 color,
-                texture_coord: TEXTURE_COORDS[i as usize],
+                texture_coord,
                 texture_index,
                 size,
-                corner_radius,
-                outline_thickness: outline_thickness,
-                outline_color: outline_color,
-            };
-
-            self.rect_vertices.push(vertex);
+                corner_radius: -1.0, // tells the shader to skip the rounded rectangle
+                outline_thickness: 0.0,
+                outline_color: Vector4::zero(),
+            });
         }
 
         self.rect_index_count += 6;